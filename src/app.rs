@@ -9,15 +9,16 @@ const INITIAL_WIDTH: u32 = 1280;
 const INITIAL_HEIGHT: u32 = 720;
 
 pub async fn run() {
+    run_app(crate::protos::ProtosApp::new()).await
+}
+
+pub(crate) async fn run_app(mut protos_app: crate::protos::ProtosApp) {
     use winit::keyboard::{Key, NamedKey};
 
     #[cfg(not(target_arch = "wasm32"))]
     std::env::set_var("RUST_BACKTRACE", "1");
-    /*std::panic::set_hook(Box::new(|_panic_info| {
-        let backtrace = backtrace::Backtrace::new();
-        //  Do something with backtrace and panic_info.
-        println!("{:?}", backtrace);
-    }));*/
+    #[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+    crate::recovery::install_panic_hook();
 
     let event_loop = EventLoop::new().expect("Failed to create event loop");
     let builder = WindowBuilder::new().with_title("A fantastic window!");
@@ -60,7 +61,8 @@ pub async fn run() {
 
     let (device, queue) = adapter.request_device(
         &wgpu::DeviceDescriptor {
-            features: wgpu::Features::VERTEX_WRITABLE_STORAGE | wgpu::Features::default(),
+            // Timestamps & pipeline statistics are only used by the profiler, when available.
+            features: wgpu::Features::VERTEX_WRITABLE_STORAGE | (adapter.features() & (wgpu::Features::TIMESTAMP_QUERY | wgpu::Features::PIPELINE_STATISTICS_QUERY)),
             limits: wgpu::Limits::default(),
             label: None,
         },
@@ -109,8 +111,7 @@ pub async fn run() {
     // Display the demo application that ships with egui.
     //let mut demo_app = egui_demo_lib::DemoWindows::default();
 
-    // Create Protos app
-    let mut protos_app = crate::protos::ProtosApp::new();
+    let mut window_title = String::new();
 
     //let start_time = Instant::now();
     let _ = event_loop.run(move |event, elwt| {
@@ -152,6 +153,15 @@ pub async fn run() {
                         protos_app.ui(&egui_context, &device, &queue, &mut encoder, &mut egui_renderer);
                     });
                     state.handle_platform_output(&window, full_output.platform_output);
+                    if protos_app.should_quit() {
+                        protos_app.exit();
+                        elwt.exit();
+                    }
+                    let title = protos_app.title();
+                    if title != window_title {
+                        window.set_title(title.as_str());
+                        window_title = title;
+                    }
                     
                     let tris = egui_context
                         .tessellate(full_output.shapes, full_output.pixels_per_point);
@@ -218,10 +228,13 @@ pub async fn run() {
                         ..
                     },
                     ..
-                } => elwt.exit(),
-                WindowEvent::Destroyed => {
-                    #[cfg(feature = "persistence")]
-                    protos_app.save();
+                } => {
+                    // Might ask the user about unsaved changes first.
+                    protos_app.request_quit();
+                    if protos_app.should_quit() {
+                        protos_app.exit();
+                        elwt.exit();
+                    }
                 }
                 _ => (),
             }
@@ -0,0 +1,10 @@
+#![cfg_attr(not(debug_assertions), deny(warnings))] // Forbid warnings in release builds
+#![warn(clippy::all, rust_2018_idioms)]
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if let Err(err) = protos_rs::cli::run(&args) {
+        eprintln!("Error: {:#}", err);
+        std::process::exit(1);
+    }
+}
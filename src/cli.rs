@@ -0,0 +1,281 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+use crate::{gfx, graph::*};
+
+const USAGE: &str = "Usage: protos-cli <project.protos> [options]
+       protos-cli --replay <directory>
+
+Render the active backbuffer of a saved project to an image, or play a wgpu trace back.
+
+Options:
+    --user-state <path>   User state of a legacy state.json project (default: user_state.json next to the state)
+    -o, --output <path>   Output image, format deduced from extension (png or exr) (default: output.png)
+    --width <pixels>      Width the backbuffer resolution mode applies to (default: 1280)
+    --height <pixels>     Height the backbuffer resolution mode applies to (default: 720)
+    --time <seconds>      Time of the rendered frame (default: 0)
+    --frames <count>      Frames to render up to time, for graphs relying on feedback (default: 1)
+    --fallback            Force the use of a fallback (software) adapter
+    --trace <directory>   Record a wgpu API trace of the render into directory (requires the trace feature)
+    --from-capture <dir>  Render the project of a capture made from the app, with its settings (requires the trace feature)
+    --replay <directory>  Play the wgpu trace of directory back headlessly, on the backend it was recorded with (requires the replay feature)
+    -h, --help            Print this message";
+
+// Delta time used between frames when rendering multiple frames.
+const FRAME_DELTA_TIME: f32 = 1.0 / 60.0;
+
+// What the command line asks for.
+pub enum Command {
+    Render(RenderOptions),
+    Replay(PathBuf), // Trace directory.
+}
+
+impl Command {
+    pub fn from_args(args: &[String]) -> anyhow::Result<Option<Self>> {
+        match args {
+            [option, directory] if option == "--replay" => Ok(Some(Command::Replay(PathBuf::from(directory)))),
+            [option, ..] if option == "--replay" => anyhow::bail!("--replay takes a trace directory & no other option"),
+            _ => Ok(RenderOptions::from_args(args)?.map(Command::Render)),
+        }
+    }
+}
+
+pub struct RenderOptions {
+    pub project_path: PathBuf,
+    pub user_state_path: Option<PathBuf>, // Only for legacy projects.
+    pub output_path: PathBuf,
+    pub width: u32,
+    pub height: u32,
+    pub time: f32,
+    pub frames: u32,
+    pub fallback_adapter: bool,
+    pub trace_path: Option<PathBuf>,
+}
+
+impl RenderOptions {
+    pub fn from_args(args: &[String]) -> anyhow::Result<Option<Self>> {
+        let mut project_path = None;
+        let mut user_state_path = None;
+        let mut output_path = PathBuf::from("output.png");
+        let mut width = 1280;
+        let mut height = 720;
+        let mut time = 0.0;
+        let mut frames = 1;
+        let mut fallback_adapter = false;
+        let mut trace_path = None;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| args.next().ok_or(anyhow::anyhow!("Missing value for {}", name)).cloned();
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(None),
+                "--user-state" => user_state_path = Some(PathBuf::from(value(arg.as_str())?)),
+                "-o" | "--output" => output_path = PathBuf::from(value(arg.as_str())?),
+                "--width" => width = value(arg.as_str())?.parse()?,
+                "--height" => height = value(arg.as_str())?.parse()?,
+                "--time" => time = value(arg.as_str())?.parse()?,
+                "--frames" => frames = value(arg.as_str())?.parse()?,
+                "--fallback" => fallback_adapter = true,
+                "--trace" => trace_path = Some(PathBuf::from(value(arg.as_str())?)),
+                #[cfg(feature = "trace")]
+                "--from-capture" => {
+                    let (path, info) = crate::trace::load_capture(Path::new(&value(arg.as_str())?))?;
+                    project_path = Some(path);
+                    width = info.width;
+                    height = info.height;
+                    time = info.time;
+                    frames = info.frames;
+                }
+                #[cfg(not(feature = "trace"))]
+                "--from-capture" => anyhow::bail!("Rendering a capture requires the trace feature"),
+                _ if arg.starts_with('-') => anyhow::bail!("Unknown option {}", arg),
+                _ => project_path = Some(PathBuf::from(arg)),
+            }
+        }
+        let project_path = match project_path {
+            Some(path) => path,
+            None => anyhow::bail!("No project given"),
+        };
+        if width == 0 || height == 0 {
+            anyhow::bail!("Invalid resolution {}x{}", width, height);
+        }
+        if frames == 0 {
+            anyhow::bail!("At least one frame should be rendered");
+        }
+        Ok(Some(Self {
+            project_path,
+            user_state_path,
+            output_path,
+            width,
+            height,
+            time,
+            frames,
+            fallback_adapter,
+            trace_path,
+        }))
+    }
+}
+
+// Load a project file, or a legacy state.json with its user state.
+#[cfg(feature = "persistence")]
+pub fn load_project(project_path: &Path, user_state_path: Option<&Path>) -> anyhow::Result<(ProtosEditorState, ProtosGraphState)> {
+    use crate::project::Project;
+    let is_legacy = project_path.extension().map_or(false, |extension| extension == "json");
+    let project = if is_legacy {
+        let user_state_path = user_state_path.map(Path::to_path_buf).unwrap_or_else(|| project_path.with_file_name("user_state.json"));
+        Project::load_legacy(project_path, &user_state_path)?
+    } else {
+        Project::load(project_path)?
+    };
+    Ok((project.state, project.user_state))
+}
+
+#[cfg(not(feature = "persistence"))]
+pub fn load_project(_project_path: &Path, _user_state_path: Option<&Path>) -> anyhow::Result<(ProtosEditorState, ProtosGraphState)> {
+    anyhow::bail!("Loading a project requires the persistence feature")
+}
+
+pub fn create_device(fallback_adapter: bool) -> anyhow::Result<(wgpu::Adapter, wgpu::Device, wgpu::Queue)> {
+    create_traced_device(fallback_adapter, None)
+}
+
+// Device recording every call to the wgpu API into trace_path, if any.
+pub fn create_traced_device(fallback_adapter: bool, trace_path: Option<&Path>) -> anyhow::Result<(wgpu::Adapter, wgpu::Device, wgpu::Queue)> {
+    if let Some(trace_path) = trace_path {
+        if !cfg!(feature = "trace") {
+            anyhow::bail!("Recording a trace requires the trace feature");
+        }
+        // wgpu only logs a failure to create the trace.
+        std::fs::create_dir_all(trace_path).with_context(|| format!("Failed to create {}", trace_path.display()))?;
+    }
+    let backend = wgpu::util::backend_bits_from_env().unwrap_or_else(wgpu::Backends::all);
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: backend,
+        flags: wgpu::InstanceFlags::default(),
+        dx12_shader_compiler: wgpu::Dx12Compiler::Fxc,
+        gles_minor_version: wgpu::Gles3MinorVersion::Automatic,
+    });
+    let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+        power_preference: wgpu::PowerPreference::default(),
+        force_fallback_adapter: fallback_adapter,
+        compatible_surface: None,
+    })).ok_or(anyhow::anyhow!("No suitable GPU adapters found on the system!"))?;
+    // Software adapters might miss some features, only request what is available.
+    let features = adapter.features() & wgpu::Features::VERTEX_WRITABLE_STORAGE;
+    let (device, queue) = pollster::block_on(adapter.request_device(
+        &wgpu::DeviceDescriptor {
+            features,
+            limits: wgpu::Limits::default(),
+            label: None,
+        },
+        trace_path,
+    ))?;
+    Ok((adapter, device, queue))
+}
+
+// Evaluate & record the active backbuffer of the graph for a single frame.
+pub fn render_frame(device: &wgpu::Device, queue: &wgpu::Queue, state: &ProtosEditorState, user_state: &ProtosGraphState, width: u32, height: u32, frame: &FrameInfo) -> anyhow::Result<()> {
+    let node_id = user_state.backbuffer_node.ok_or(anyhow::anyhow!("No active backbuffer in project"))?;
+    let ctx = EvaluationContext {
+        device,
+        queue,
+        frame: *frame,
+        target_size: (width, height),
+    };
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("encoder"),
+    });
+    render_backbuffer(&ctx, &mut encoder, &state.graph, node_id).map_err(|err| {
+        // Name the node which failed, as ids mean nothing to the user.
+        match err.downcast_ref::<ProtosError>().map(ProtosError::path) {
+            Some(path) if !path.is_empty() => {
+                let labels = node_labels(&state.graph, &path).join(" > ");
+                err.context(format!("Node {} failed", labels))
+            }
+            _ => err,
+        }
+    })?;
+    queue.submit(std::iter::once(encoder.finish()));
+    Ok(())
+}
+
+// Render frames up to time, for graphs relying on feedback.
+pub fn render_frames(device: &wgpu::Device, queue: &wgpu::Queue, state: &ProtosEditorState, user_state: &ProtosGraphState, size: (u32, u32), time: f32, frames: u32) -> anyhow::Result<()> {
+    for index in 0..frames {
+        let remaining = (frames - 1 - index) as f32;
+        let frame = FrameInfo {
+            time: (time - remaining * FRAME_DELTA_TIME).max(0.0),
+            delta_time: FRAME_DELTA_TIME,
+            index,
+        };
+        render_frame(device, queue, state, user_state, size.0, size.1, &frame)?;
+    }
+    Ok(())
+}
+
+// Read the content of the active backbuffer, must be called after render_frame.
+pub fn read_backbuffer(device: &wgpu::Device, queue: &wgpu::Queue, state: &ProtosEditorState, user_state: &ProtosGraphState) -> anyhow::Result<image::RgbaImage> {
+    let node_id = user_state.backbuffer_node.ok_or(anyhow::anyhow!("No active backbuffer in project"))?;
+    match &state.graph.nodes[node_id].user_data.template {
+        ProtosNodeTemplate::BackbufferPass(node) => {
+            let pass = node.handle.lock().unwrap();
+            gfx::read_texture_rgba8(device, queue, pass.get_texture_handle()?)
+        }
+        _ => anyhow::bail!("Active backbuffer is not a backbuffer pass"),
+    }
+}
+
+pub fn save_image(image: &image::RgbaImage, path: &Path) -> anyhow::Result<()> {
+    let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or_default().to_lowercase();
+    match extension.as_str() {
+        "png" => image.save(path)?,
+        "exr" => {
+            // Backbuffer is stored in sRGB, EXR expect linear values.
+            let linear = image::Rgba32FImage::from_fn(image.width(), image.height(), |x, y| {
+                let pixel = image.get_pixel(x, y).0;
+                let to_linear = |value: u8| {
+                    let value = value as f32 / 255.0;
+                    if value <= 0.04045 { value / 12.92 } else { ((value + 0.055) / 1.055).powf(2.4) }
+                };
+                image::Rgba([to_linear(pixel[0]), to_linear(pixel[1]), to_linear(pixel[2]), pixel[3] as f32 / 255.0])
+            });
+            image::DynamicImage::ImageRgba32F(linear).save(path)?
+        }
+        _ => anyhow::bail!("Unsupported output format {:?}, expected png or exr", path),
+    }
+    Ok(())
+}
+
+pub fn render(options: &RenderOptions) -> anyhow::Result<()> {
+    let (state, user_state) = load_project(&options.project_path, options.user_state_path.as_deref())?;
+    let (adapter, device, queue) = create_traced_device(options.fallback_adapter, options.trace_path.as_deref())?;
+    println!("Rendering on {}", adapter.get_info().name);
+    render_frames(&device, &queue, &state, &user_state, (options.width, options.height), options.time, options.frames)?;
+    let image = read_backbuffer(&device, &queue, &state, &user_state)?;
+    save_image(&image, &options.output_path)?;
+    println!("Saved {}", options.output_path.display());
+    Ok(())
+}
+
+#[cfg(feature = "replay")]
+pub fn replay(directory: &Path) -> anyhow::Result<()> {
+    let info = crate::replay::replay(directory)?;
+    println!("Replayed {} actions on {}", info.actions, info.adapter);
+    Ok(())
+}
+
+#[cfg(not(feature = "replay"))]
+pub fn replay(_directory: &Path) -> anyhow::Result<()> {
+    anyhow::bail!("Replaying a trace requires the replay feature")
+}
+
+pub fn run(args: &[String]) -> anyhow::Result<()> {
+    match Command::from_args(args)? {
+        Some(Command::Render(options)) => render(&options),
+        Some(Command::Replay(directory)) => replay(&directory),
+        None => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+    }
+}
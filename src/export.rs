@@ -0,0 +1,165 @@
+use std::{fs, io, path::{Path, PathBuf}};
+
+use anyhow::Context;
+use egui_node_graph::NodeId;
+
+use crate::{cli, graph::*, project::{Project, ProjectMetadata}};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportKind {
+    Frame, // PNG or EXR, from the extension.
+    Sequence, // Numbered PNGs.
+    Gif,
+    Apng,
+}
+
+impl ExportKind {
+    pub const ALL: [ExportKind; 4] = [ExportKind::Frame, ExportKind::Sequence, ExportKind::Gif, ExportKind::Apng];
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportKind::Frame => "Frame",
+            ExportKind::Sequence => "Image sequence",
+            ExportKind::Gif => "Animated GIF",
+            ExportKind::Apng => "Animated PNG",
+        }
+    }
+    pub fn default_path(&self) -> &'static str {
+        match self {
+            ExportKind::Frame => "frame.png",
+            ExportKind::Sequence => "sequence/frame.png",
+            ExportKind::Gif => "animation.gif",
+            ExportKind::Apng => "animation.png",
+        }
+    }
+    pub fn is_animated(&self) -> bool {
+        !matches!(self, ExportKind::Frame)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportSettings {
+    pub kind: ExportKind,
+    pub size: (u32, u32), // Resolving the resolution of the backbuffer, independent of the panel.
+    pub time: f32, // Of the frame, or of the first frame of animations.
+    pub frames: u32,
+    pub frame_rate: u32, // Fixed timestep of animations.
+}
+
+// Encoder of the file being exported.
+enum Output {
+    Frame,
+    Sequence,
+    Gif(image::codecs::gif::GifEncoder<io::BufWriter<fs::File>>),
+    Apng(png::Writer<io::BufWriter<fs::File>>),
+}
+
+// Export of the backbuffer pass node of a copy of the project, rendering a few frames at each step so that the UI stays responsive.
+// The copy is rendered from its first frame, so that the live graph & its feedbacks are left untouched.
+pub struct ExportJob {
+    project: Project,
+    settings: ExportSettings,
+    path: PathBuf,
+    output: Option<Output>, // None once finished.
+    frames: u32,
+    rendered: u32,
+}
+
+impl ExportJob {
+    pub fn new(state: &ProtosEditorState, user_state: &ProtosGraphState, node_id: NodeId, settings: &ExportSettings, path: &Path) -> anyhow::Result<Self> {
+        if settings.size.0 == 0 || settings.size.1 == 0 {
+            anyhow::bail!("Invalid resolution {}x{}", settings.size.0, settings.size.1);
+        }
+        let json = Project::to_json(&ProjectMetadata::default(), state, user_state)?;
+        let mut project = Project::from_json(json.as_str())?;
+        project.user_state.backbuffer_node = Some(node_id);
+        // Frames have the resolution of the backbuffer, resolved from the size of the export.
+        let (width, height) = match &project.state.graph.nodes[node_id].user_data.template {
+            ProtosNodeTemplate::BackbufferPass(node) => node.handle.lock().unwrap().get_mode().resolve(settings.size),
+            _ => anyhow::bail!("Only backbuffer passes can be exported"),
+        };
+        let frames = if settings.kind.is_animated() { settings.frames.max(1) } else { 1 };
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let create = || fs::File::create(path).map(io::BufWriter::new).with_context(|| format!("Failed to create {}", path.display()));
+        let output = match settings.kind {
+            ExportKind::Frame => Output::Frame,
+            ExportKind::Sequence => Output::Sequence,
+            ExportKind::Gif => {
+                let mut encoder = image::codecs::gif::GifEncoder::new(create()?);
+                encoder.set_repeat(image::codecs::gif::Repeat::Infinite)?;
+                Output::Gif(encoder)
+            }
+            ExportKind::Apng => {
+                // The image crate does not encode animations in PNG, the png crate it relies on does.
+                let mut encoder = png::Encoder::new(create()?, width, height);
+                encoder.set_color(png::ColorType::Rgba);
+                encoder.set_depth(png::BitDepth::Eight);
+                encoder.set_animated(frames, 0)?; // Loop forever.
+                encoder.set_frame_delay(1, settings.frame_rate.clamp(1, u16::MAX as u32) as u16)?;
+                Output::Apng(encoder.write_header()?)
+            }
+        };
+        Ok(Self {
+            project,
+            settings: settings.clone(),
+            path: path.to_path_buf(),
+            output: Some(output),
+            frames,
+            rendered: 0,
+        })
+    }
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    // Frames rendered & total.
+    pub fn progress(&self) -> (u32, u32) {
+        (self.rendered, self.frames)
+    }
+    pub fn is_finished(&self) -> bool {
+        self.output.is_none()
+    }
+    // Render & save up to max_frames frames, finishing the file after the last one.
+    pub fn step(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, max_frames: u32) -> anyhow::Result<()> {
+        let delta_time = 1.0 / self.settings.frame_rate.max(1) as f32;
+        let end = self.frames.min(self.rendered + max_frames);
+        while self.rendered < end {
+            let index = self.rendered;
+            let frame = FrameInfo {
+                time: self.settings.time + index as f32 * delta_time,
+                delta_time,
+                index,
+            };
+            cli::render_frame(device, queue, &self.project.state, &self.project.user_state, self.settings.size.0, self.settings.size.1, &frame)?;
+            let image = cli::read_backbuffer(device, queue, &self.project.state, &self.project.user_state)?;
+            match self.output.as_mut() {
+                Some(Output::Frame) => cli::save_image(&image, &self.path)?,
+                Some(Output::Sequence) => {
+                    // Numbered after the file name, frame.png giving frame_0000.png, frame_0001.png...
+                    let stem = self.path.file_stem().and_then(|stem| stem.to_str()).unwrap_or("frame");
+                    cli::save_image(&image, &self.path.with_file_name(format!("{}_{:04}.png", stem, index)))?;
+                }
+                Some(Output::Gif(encoder)) => {
+                    let delay = image::Delay::from_numer_denom_ms(1000, self.settings.frame_rate.max(1));
+                    encoder.encode_frame(image::Frame::from_parts(image, 0, 0, delay))?;
+                }
+                Some(Output::Apng(writer)) => writer.write_image_data(image.as_raw())?,
+                None => anyhow::bail!("Export already finished"),
+            }
+            self.rendered += 1;
+        }
+        if self.rendered == self.frames {
+            // The GIF encoder writes its trailer when dropped.
+            if let Some(Output::Apng(writer)) = self.output.take() {
+                writer.finish()?;
+            }
+        }
+        Ok(())
+    }
+    // Stop the export, removing the incomplete animation. Frames of a sequence already saved are kept.
+    pub fn cancel(mut self) {
+        if let Some(Output::Gif(_) | Output::Apng(_)) = self.output.take() {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use egui_node_graph::{CategoryTrait, DataTypeTrait};
+
+use crate::graph::{ProtosCategoryType, ProtosDataType, ProtosNodeTemplate};
+
+// Search window listing the nodes which can be added, opened where the node will be placed.
+pub struct NodeFinder {
+    query: String,
+    position: egui::Pos2, // Screen position the finder was opened at.
+    focused: bool,
+}
+
+impl NodeFinder {
+    pub fn new(position: egui::Pos2) -> Self {
+        Self {
+            query: String::new(),
+            position,
+            focused: false,
+        }
+    }
+    pub fn position(&self) -> egui::Pos2 {
+        self.position
+    }
+    // Returns the template picked by the user, if any. Open is cleared when the finder should close.
+    pub fn ui(&mut self, ctx: &egui::Context, templates: &[ProtosNodeTemplate], open: &mut bool) -> Option<ProtosNodeTemplate> {
+        let mut picked = None;
+        egui::Window::new("Add node")
+            .fixed_pos(self.position)
+            .collapsible(false)
+            .resizable(false)
+            .open(open)
+            .show(ctx, |ui| {
+                let response = ui.add(egui::TextEdit::singleline(&mut self.query).hint_text("Search..."));
+                if !self.focused {
+                    response.request_focus();
+                    self.focused = true;
+                }
+                let query = self.query.trim();
+                let mut results: Vec<(u32, &ProtosNodeTemplate)> = templates.iter()
+                    .filter_map(|template| score(query, template).map(|score| (score, template)))
+                    .collect();
+                // Stable sort, so that equal scores keep the template order.
+                results.sort_by(|a, b| b.0.cmp(&a.0));
+                if response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter)) {
+                    picked = results.first().map(|(_, template)| (*template).clone());
+                }
+                egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                    if results.is_empty() {
+                        ui.label("No node found.");
+                    } else if query.is_empty() {
+                        for category in ProtosCategoryType::ALL {
+                            let templates: Vec<&ProtosNodeTemplate> = results.iter()
+                                .map(|(_, template)| *template)
+                                .filter(|template| category_of(template) == category)
+                                .collect();
+                            if templates.is_empty() {
+                                continue;
+                            }
+                            ui.label(egui::RichText::new(category.name()).strong());
+                            for template in templates {
+                                if entry_ui(ui, template) {
+                                    picked = Some(template.clone());
+                                }
+                            }
+                        }
+                    } else {
+                        for (_, template) in &results {
+                            if entry_ui(ui, template) {
+                                picked = Some((*template).clone());
+                            }
+                        }
+                    }
+                });
+            });
+        if picked.is_some() || ctx.input(|input| input.key_pressed(egui::Key::Escape)) {
+            *open = false;
+        }
+        picked
+    }
+}
+
+fn name_of(template: &ProtosNodeTemplate) -> String {
+    template.visit_node(|node| Ok(node.get_name().to_string())).expect("Should not fail.")
+}
+
+fn category_of(template: &ProtosNodeTemplate) -> ProtosCategoryType {
+    template.visit_node(|node| Ok(node.get_category())).expect("Should not fail.")
+}
+
+fn description_of(template: &ProtosNodeTemplate) -> String {
+    template.visit_node(|node| Ok(node.get_description().to_string())).expect("Should not fail.")
+}
+
+// Returns true if the entry was clicked.
+fn entry_ui(ui: &mut egui::Ui, template: &ProtosNodeTemplate) -> bool {
+    ui.selectable_label(false, name_of(template))
+        .on_hover_ui(|ui| tooltip_ui(ui, template))
+        .clicked()
+}
+
+fn tooltip_ui(ui: &mut egui::Ui, template: &ProtosNodeTemplate) {
+    ui.label(description_of(template));
+    let (inputs, outputs) = template.ports();
+    ports_ui(ui, "Inputs", &inputs);
+    ports_ui(ui, "Outputs", &outputs);
+}
+
+fn ports_ui(ui: &mut egui::Ui, label: &str, ports: &[(String, ProtosDataType)]) {
+    if ports.is_empty() {
+        return;
+    }
+    ui.separator();
+    ui.label(egui::RichText::new(label).strong());
+    for (name, typ) in ports {
+        ui.label(format!("{} : {}", name, typ.name()));
+    }
+}
+
+// Match of the query against the node, its name weighting more than its category or description.
+fn score(query: &str, template: &ProtosNodeTemplate) -> Option<u32> {
+    if query.is_empty() {
+        return Some(0);
+    }
+    let name = fuzzy_score(query, name_of(template).as_str()).map(|score| 3 * score);
+    let category = fuzzy_score(query, category_of(template).name().as_str());
+    let description = fuzzy_score(query, description_of(template).as_str());
+    name.max(category).max(description)
+}
+
+// Score of the pattern as a subsequence of the text, favoring consecutive characters & word starts.
+fn fuzzy_score(pattern: &str, text: &str) -> Option<u32> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut start = 0;
+    let mut previous = None;
+    for c in pattern.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = start + text[start..].iter().position(|t| *t == c)?;
+        score += 1;
+        if found > 0 && previous == Some(found - 1) {
+            score += 4;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 2;
+        }
+        previous = Some(found);
+        start = found + 1;
+    }
+    Some(score)
+}
@@ -1,15 +1,55 @@
-use std::sync::Arc;
+use super::{resource::{Resource, ResourceDataTrait, ResourceDescTrait,}, ResourceHandle, Texture};
 
-use super::{resource::{Resource, ResourceDataTrait, ResourceDescTrait,}, visit_resource, ResourceHandle, Texture};
 
+// How the resolution of the backbuffer is chosen from the size of the area displaying it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
+pub enum BackbufferMode {
+    Scale { percent: u32 }, // Of the displayed size.
+    Fixed { width: u32, height: u32 },
+    Aspect { width: u32, height: u32 }, // Largest size with this aspect ratio fitting the displayed size.
+}
 
-#[derive(Debug, Default)]
+impl Default for BackbufferMode {
+    fn default() -> Self {
+        BackbufferMode::Scale { percent: 100 }
+    }
+}
+
+impl BackbufferMode {
+    pub fn get_mode_name(&self) -> &'static str {
+        match self {
+            BackbufferMode::Scale { .. } => "Render scale",
+            BackbufferMode::Fixed { .. } => "Fixed resolution",
+            BackbufferMode::Aspect { .. } => "Locked aspect ratio",
+        }
+    }
+    pub fn resolve(&self, displayed_size: (u32, u32)) -> (u32, u32) {
+        let (width, height) = match *self {
+            BackbufferMode::Scale { percent } => (
+                (displayed_size.0 as u64 * percent as u64 / 100) as u32,
+                (displayed_size.1 as u64 * percent as u64 / 100) as u32,
+            ),
+            BackbufferMode::Fixed { width, height } => (width, height),
+            BackbufferMode::Aspect { width, height } => {
+                let scale = (displayed_size.0 as f32 / width.max(1) as f32).min(displayed_size.1 as f32 / height.max(1) as f32);
+                ((width as f32 * scale) as u32, (height as f32 * scale) as u32)
+            }
+        };
+        (width.max(1), height.max(1))
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
 #[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
 pub struct BackbufferPassDescription {
+    // Set on evaluation from the connected pass, which owns it.
     #[cfg_attr(feature = "persistence", serde(skip))]
-    origin : Option<ResourceHandle<Texture>>, // TODO: serialize this ?
+    origin : Option<ResourceHandle<Texture>>,
     width: u32,
     height: u32,
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub mode: BackbufferMode,
 }
 #[derive(Debug, Default)]
 pub struct BackbufferPassData {
@@ -31,31 +71,11 @@ impl ResourceDataTrait<BackbufferPassDescription> for BackbufferPassData {
             target: Some(texture),
         })
     }
-    fn record_data(&self, _device: &wgpu::Device, cmd: &mut wgpu::CommandEncoder, desc: &BackbufferPassDescription) -> anyhow::Result<()> {
-        if let Some(origin_locked) = &desc.origin {
+    fn record_data(&self, device: &wgpu::Device, cmd: &mut wgpu::CommandEncoder, desc: &BackbufferPassDescription) -> anyhow::Result<()> {
+        if let Some(origin) = &desc.origin {
             if let Some(target) = &self.target {
-                visit_resource(origin_locked, |origin| {
-                    // Copy target to final storage.
-                    let src = wgpu::ImageCopyTexture{ 
-                        texture: origin.get_handle().expect("Origin not loaded"),
-                        mip_level: 0,
-                        origin: wgpu::Origin3d::ZERO,
-                        aspect: wgpu::TextureAspect::All,
-                    };
-                    let dst = wgpu::ImageCopyTexture{ 
-                        texture: target.get_handle().expect("Target not loaded"),
-                        mip_level: 0,
-                        origin: wgpu::Origin3d::ZERO,
-                        aspect: wgpu::TextureAspect::All,
-                    };
-                    
-                    cmd.copy_texture_to_texture(src, dst, wgpu::Extent3d {
-                        width: desc.width,
-                        height: desc.height,
-                        depth_or_array_layers:1,
-                    });
-                });
-                Ok(())
+                // Draw origin into final storage, resampled as passes might be sized independently.
+                target.record_blit_from(device, cmd, &origin.lock().unwrap())
             } else {
                 anyhow::bail!("No target in backbuffer");
             }
@@ -68,7 +88,7 @@ impl ResourceDataTrait<BackbufferPassDescription> for BackbufferPassData {
 impl BackbufferPass {
     pub fn set_origin(&mut self, origin: ResourceHandle<Texture>) {
         if let Some(old_origin) = &self.desc.origin {
-            if !Arc::ptr_eq(old_origin, &origin) {
+            if !ResourceHandle::ptr_eq(old_origin, &origin) {
                 self.desc.origin = Some(origin);
                 self.dirty = true;
             }
@@ -84,12 +104,26 @@ impl BackbufferPass {
             self.dirty = true;
         }
     }
+    pub fn get_mode(&self) -> BackbufferMode {
+        self.desc.mode
+    }
     pub fn get_width(&self) -> u32 {
         self.desc.width
     }
     pub fn get_height(&self) -> u32 {
         self.desc.height
     }
+    pub fn get_texture_handle(&self) -> anyhow::Result<&wgpu::Texture> {
+        if let Some(data) = &self.data {
+            if let Some(target) = &data.target {
+                target.get_handle()
+            } else {
+                anyhow::bail!("No data")
+            }
+        } else {
+            anyhow::bail!("No data")
+        }
+    }
     pub fn get_view_handle(&self) -> anyhow::Result<&wgpu::TextureView> {
         if let Some(data) = &self.data {
             if let Some(target) = &data.target {
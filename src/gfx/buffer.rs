@@ -1,15 +1,15 @@
 use super::resource::{Resource, ResourceDataTrait, ResourceDescTrait};
 
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, PartialEq)]
 #[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
 pub struct BufferDescription {
     size: u32,
     format: u32, // TODO enum here
 }
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct BufferData {
-
+    buffer: wgpu::Buffer,
 }
 
 pub type Buffer = Resource<BufferDescription, BufferData>;
@@ -19,9 +19,17 @@ impl ResourceDescTrait for BufferDescription {
 }
 
 impl ResourceDataTrait<BufferDescription> for BufferData {
-    fn new(_device: &wgpu::Device, _queue: &wgpu::Queue, _desc: &BufferDescription) -> anyhow::Result<Self> {
+    fn new(device: &wgpu::Device, _queue: &wgpu::Queue, desc: &BufferDescription) -> anyhow::Result<Self> {
+        // Copies require sizes aligned to 4 bytes.
+        let alignment = wgpu::COPY_BUFFER_ALIGNMENT as u32;
+        let size = desc.size.max(1).div_ceil(alignment) * alignment;
         Ok(Self {
-            
+            buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Buffer"),
+                size: size as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
         })
     }
     fn record_data(&self, _device: &wgpu::Device, _cmd: &mut wgpu::CommandEncoder, _desc: &BufferDescription) -> anyhow::Result<()> {
@@ -31,9 +39,18 @@ impl ResourceDataTrait<BufferDescription> for BufferData {
 
 impl Buffer {
     pub fn set_size(&mut self, size: u32) {
-        self.desc.size = size;
+        if self.desc.size != size {
+            self.desc.size = size;
+            self.dirty = true;
+        }
     }
     pub fn set_format(&mut self, format: u32) {
         self.desc.format = format;
     }
+    pub fn get_handle(&self) -> anyhow::Result<&wgpu::Buffer> {
+        match &self.data {
+            Some(data) => Ok(&data.buffer),
+            None => Err(anyhow::anyhow!("No data")),
+        }
+    }
 }
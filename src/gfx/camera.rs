@@ -1,7 +1,7 @@
 use super::resource::{Resource, ResourceDataTrait, ResourceDescTrait};
 
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, PartialEq)]
 #[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
 pub struct CameraDescription {
 
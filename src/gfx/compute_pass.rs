@@ -1,7 +1,7 @@
-use super::resource::{Resource, ResourceDataTrait, ResourceDescTrait};
+use super::{resource::{Resource, ResourceDataTrait, ResourceDescTrait}, GfxError};
 
 
-#[derive(Default)]
+#[derive(Default, PartialEq)]
 #[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
 pub struct ComputePassDescription {
     pub bind_group : Vec<Vec<wgpu::BindGroupLayoutEntry>>,
@@ -54,7 +54,7 @@ impl ResourceDataTrait<ComputePassDescription> for ComputePassData {
             Some(err)
         });
         if let Some(v) = validation {
-            anyhow::bail!(v.to_string())
+            anyhow::bail!(GfxError::Validation(v.to_string()))
         } else {
             Ok(Self {
                 compute_pipeline,
@@ -0,0 +1,22 @@
+use std::{fmt, ops::Range};
+
+// Errors raised by resources which the graph reports with their own kind, along with the node they belong to.
+#[derive(Debug, Clone)]
+pub enum GfxError {
+    // Message is the full report, spans the ranges of the source it points to.
+    ShaderCompilation { message: String, spans: Vec<Range<usize>> },
+    Validation(String),
+    Unsupported(String),
+}
+
+impl fmt::Display for GfxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GfxError::ShaderCompilation { message, .. } => write!(f, "Shader failed to compile : {}", message),
+            GfxError::Validation(message) => write!(f, "Validation error : {}", message),
+            GfxError::Unsupported(message) => write!(f, "Unsupported : {}", message),
+        }
+    }
+}
+
+impl std::error::Error for GfxError {}
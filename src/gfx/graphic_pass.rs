@@ -1,6 +1,4 @@
-use std::borrow::Cow;
-use std::sync::Arc;
-use std::sync::Mutex;
+use std::mem;
 
 use wgpu::RenderPassDescriptor;
 
@@ -12,19 +10,21 @@ use super::resource::ResourceDescTrait;
 use super::Mesh;
 use super::ResourceHandle;
 use super::texture::*;
+use super::GfxError;
+use super::RenderPassQueries;
 use super::Shader;
+use super::size_expression::evaluate_size_expression;
+use super::ShaderStage;
 
-fn default_bind_group_entry(index : u32) -> wgpu::BindGroupLayoutEntry {
-    wgpu::BindGroupLayoutEntry {
-        binding: index,
-        visibility:wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
-        ty: wgpu::BindingType::Texture { 
-            sample_type: wgpu::TextureSampleType::Float { filterable: true }, 
-            view_dimension: wgpu::TextureViewDimension::D2, 
-            multisampled: false
-        },
-        count: None
-    }
+// Constants available to every graphic pass shaders at @group(2) @binding(0).
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GraphicPassConstants {
+    resolution: [f32; 4],
+    time: f32,
+    time_delta: f32,
+    frame: i32,
+    padding: f32,
 }
 
 #[derive(Clone, PartialEq, Eq, Default)]
@@ -41,61 +41,154 @@ impl AttachmentDescription {
     }
 }
 
-#[derive(Default)]
+// Size of the render targets of a pass, from the resolution of the backbuffer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
+pub enum RenderTargetSize {
+    Relative { percent: u32 }, // Of the backbuffer, for downsampled or supersampled passes.
+    Fixed { width: u32, height: u32 },
+    Expression { width: String, height: String }, // Such as bb.w / 2 or min(bb.w, 1024), see size_expression.
+}
+
+impl Default for RenderTargetSize {
+    fn default() -> Self {
+        RenderTargetSize::Relative { percent: 100 }
+    }
+}
+
+impl RenderTargetSize {
+    pub fn get_size_name(&self) -> &'static str {
+        match self {
+            RenderTargetSize::Relative { .. } => "Relative",
+            RenderTargetSize::Fixed { .. } => "Fixed",
+            RenderTargetSize::Expression { .. } => "Expression",
+        }
+    }
+    pub fn resolve(&self, backbuffer_size: (u32, u32)) -> anyhow::Result<(u32, u32)> {
+        let (width, height) = match self {
+            RenderTargetSize::Relative { percent } => (
+                (backbuffer_size.0 as u64 * *percent as u64 / 100) as u32,
+                (backbuffer_size.1 as u64 * *percent as u64 / 100) as u32,
+            ),
+            RenderTargetSize::Fixed { width, height } => (*width, *height),
+            RenderTargetSize::Expression { width, height } => (
+                evaluate_size_expression(width, backbuffer_size)?,
+                evaluate_size_expression(height, backbuffer_size)?,
+            ),
+        };
+        Ok((width.max(1), height.max(1)))
+    }
+}
+
+#[derive(Default, PartialEq)]
 #[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
 pub struct GraphicPassDescription {
     geometry: Option<ResourceHandle<Mesh>>,
-    bind_group : Vec<Vec<wgpu::BindGroupLayoutEntry>>,
     render_target_desc: Vec<AttachmentDescription>,
     shader_resource_view: Vec<Option<ResourceHandle<Texture>>>,
     vertex_shader: Option<ResourceHandle<Shader>>,
     fragment_shader: Option<ResourceHandle<Shader>>,
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub render_target_size: RenderTargetSize,
 }
+// What a pass draws, counted on the CPU.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DrawCounters {
+    pub vertices: u32,
+    pub indices: u32,
+    pub triangles: u32,
+    pub instances: u32,
+}
+
 pub struct GraphicPassData {
     render_pipeline: wgpu::RenderPipeline,
     bind_group : wgpu::BindGroup,
+    sampler_bind_group : wgpu::BindGroup,
+    constants_bind_group : wgpu::BindGroup,
+    constants_buffer: wgpu::Buffer,
     render_targets: Vec<ResourceHandle<Texture>>,
+    // Generations of the resources this data was built with.
+    dependencies: Vec<u64>,
 }
 
 pub type GraphicPass = Resource<GraphicPassDescription, GraphicPassData>;
 
 impl ResourceDescTrait for GraphicPassDescription {
-    
+
+}
+
+impl GraphicPassDescription {
+    // Resources that require the pipeline to be rebuilt when their data is rebuilt.
+    fn get_dependencies(&self) -> Vec<u64> {
+        // Lock them one at a time as the same resource might be used multiple times.
+        let mut dependencies = Vec::new();
+        for shader in [&self.vertex_shader, &self.fragment_shader].into_iter().flatten() {
+            dependencies.push(shader.lock().unwrap().get_generation());
+        }
+        for srv in self.shader_resource_view.iter().flatten() {
+            dependencies.push(srv.lock().unwrap().get_generation());
+        }
+        dependencies
+    }
 }
 
 impl ResourceDataTrait<GraphicPassDescription> for GraphicPassData {
     fn new<'a>(device: &wgpu::Device, queue: &wgpu::Queue, desc: &GraphicPassDescription) -> anyhow::Result<Self> {
-        // TODO: handle multiple bind group
         // TODO: handle other types that texture with an enum.
-        // Create bind groups.
-        let mut binding = 0;
+        let dependencies = desc.get_dependencies();
+        // Unbound resources are replaced by a black texture.
+        let mut default_texture = Texture::from_desc(TextureDescription::default_black_texture()?);
+        default_texture.update_data(device, queue)?;
+        // Store locks to keep their lifetime for create_bind_group.
+        // Same resource might be bound multiple times, so only lock it once.
+        let mut unique_resources: Vec<&ResourceHandle<Texture>> = Vec::new();
+        let mut resource_indices = Vec::new();
+        for resource in &desc.shader_resource_view {
+            resource_indices.push(resource.as_ref().map(|resource| {
+                match unique_resources.iter().position(|unique| ResourceHandle::ptr_eq(unique, resource)) {
+                    Some(index) => index,
+                    None => {
+                        unique_resources.push(resource);
+                        unique_resources.len() - 1
+                    }
+                }
+            }));
+        }
+        let resources_locked = unique_resources.iter().map(|value| value.lock().unwrap()).collect::<Vec<_>>();
+        // Create bind groups : textures in group 0, their samplers in group 1 & constants in group 2.
 		let mut bind_group_layout_entry = Vec::new();
 		let mut bind_group_entry = Vec::new();
-        // Check inputs.
-		for resource in &desc.shader_resource_view {
-            if resource.is_none() {
-                anyhow::bail!("Resource binding not set")
-            }
-        }
-        // Store locks to keep their lifetime for create_bind_group
-        let resources = desc.shader_resource_view.iter().map(|value| value.as_ref().unwrap()).collect::<Vec<_>>();
-        let resources_locked = resources.iter().map(|value| value.lock().unwrap()).collect::<Vec<_>>();
-		for resource_locked in &resources_locked {
+		let mut sampler_bind_group_layout_entry = Vec::new();
+		let mut sampler_bind_group_entry = Vec::new();
+		for (binding, resource_index) in resource_indices.iter().enumerate() {
+            let texture = match resource_index {
+                Some(index) => &*resources_locked[*index],
+                None => &default_texture,
+            };
 			bind_group_layout_entry.push(wgpu::BindGroupLayoutEntry {
-				binding: binding,
-				visibility: wgpu::ShaderStages::COMPUTE | wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
-				ty: wgpu::BindingType::Texture { 
-                    sample_type: wgpu::TextureSampleType::Float { filterable: true }, 
-                    view_dimension: wgpu::TextureViewDimension::D2, 
+				binding: binding as u32,
+				visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+				ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
                     multisampled: false
                 },
 				count: None,
-			});            
+			});
             bind_group_entry.push(wgpu::BindGroupEntry {
-                binding: binding,
-                resource: wgpu::BindingResource::TextureView(resource_locked.get_view_handle()?) 
+                binding: binding as u32,
+                resource: wgpu::BindingResource::TextureView(texture.get_view_handle()?)
+            });
+			sampler_bind_group_layout_entry.push(wgpu::BindGroupLayoutEntry {
+				binding: binding as u32,
+				visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+				ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+				count: None,
+			});
+            sampler_bind_group_entry.push(wgpu::BindGroupEntry {
+                binding: binding as u32,
+                resource: wgpu::BindingResource::Sampler(texture.get_sampler_handle()?)
             });
-            binding += 1;
 		}
 		let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
 			label: Some("GraphicPassBindGroupLayout"),
@@ -106,6 +199,42 @@ impl ResourceDataTrait<GraphicPassDescription> for GraphicPassData {
 			layout: &bind_group_layout,
 			entries: bind_group_entry.as_slice(),
 		});
+		let sampler_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+			label: Some("GraphicPassSamplerBindGroupLayout"),
+			entries: sampler_bind_group_layout_entry.as_slice(),
+		});
+		let sampler_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+			label: Some("GraphicPassSamplerBindGroup"),
+			layout: &sampler_bind_group_layout,
+			entries: sampler_bind_group_entry.as_slice(),
+		});
+        let constants_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("GraphicPassConstants"),
+            size: mem::size_of::<GraphicPassConstants>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+		let constants_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+			label: Some("GraphicPassConstantsBindGroupLayout"),
+			entries: &[wgpu::BindGroupLayoutEntry {
+				binding: 0,
+				visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+				ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+				count: None,
+			}],
+		});
+		let constants_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+			label: Some("GraphicPassConstantsBindGroup"),
+			layout: &constants_bind_group_layout,
+			entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: constants_buffer.as_entire_binding(),
+            }],
+		});
 
         // Create attachments
         let mut render_targets = Vec::new();
@@ -119,7 +248,7 @@ impl ResourceDataTrait<GraphicPassDescription> for GraphicPassData {
             let mut attachment = Texture::default();
             attachment.set_size(render_target.width, render_target.height);
             attachment.update_data(device, queue).expect("Should not fail here");
-            render_targets.push(Arc::new(Mutex::new(attachment)));
+            render_targets.push(ResourceHandle::new(attachment));
         }
         if desc.vertex_shader.is_none() {
             anyhow::bail!("No vertex shader")
@@ -128,28 +257,16 @@ impl ResourceDataTrait<GraphicPassDescription> for GraphicPassData {
             anyhow::bail!("No fragment shader")
         }
         // Create shaders
+        // Vertex & fragment might share the same shader, lock them one at a time.
+        let vertex_entry_point = desc.vertex_shader.as_ref().unwrap().lock().unwrap().get_entry_point(ShaderStage::Vertex)?.to_string();
+        let fragment_entry_point = desc.fragment_shader.as_ref().unwrap().lock().unwrap().get_entry_point(ShaderStage::Fragment)?.to_string();
         device.push_error_scope(wgpu::ErrorFilter::Validation);
-        let (vertex_shader, fragment_shader) = {
-            ({
-                let vertex_shader_source_locked = desc.vertex_shader.as_ref().unwrap();
-                let vertex_shader_source = vertex_shader_source_locked.lock().unwrap();
-                device.create_shader_module(wgpu::ShaderModuleDescriptor {
-                    label: Some("VertexShader"),
-                    source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(vertex_shader_source.desc.shader.as_str())),
-                })
-            }, {
-                let fragment_shader_source_locked = desc.fragment_shader.as_ref().unwrap();
-                let fragment_shader_source = fragment_shader_source_locked.lock().unwrap();
-                device.create_shader_module(wgpu::ShaderModuleDescriptor {
-                    label: Some("FragmentShader"),
-                    source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(fragment_shader_source.desc.shader.as_str())),
-                })
-            })
-        };
+        let vertex_shader = desc.vertex_shader.as_ref().unwrap().lock().unwrap().create_shader_module(device, "VertexShader")?;
+        let fragment_shader = desc.fragment_shader.as_ref().unwrap().lock().unwrap().create_shader_module(device, "FragmentShader")?;
         // Create pipeline
         let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("Render Pipeline Layout"),
-            bind_group_layouts: &[&bind_group_layout],
+            bind_group_layouts: &[&bind_group_layout, &sampler_bind_group_layout, &constants_bind_group_layout],
             push_constant_ranges: &[], // TODO: push constant
         });
         let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
@@ -157,7 +274,7 @@ impl ResourceDataTrait<GraphicPassDescription> for GraphicPassData {
             layout: Some(&render_pipeline_layout),
             vertex: wgpu::VertexState {
                 module: &vertex_shader,
-                entry_point: "vs_main",
+                entry_point: vertex_entry_point.as_str(),
                 buffers: &[
                     StaticVertex::desc(),
                     //Instance::desc() // TODO: get this from rust-engine
@@ -165,7 +282,7 @@ impl ResourceDataTrait<GraphicPassDescription> for GraphicPassData {
             },
             fragment: Some(wgpu::FragmentState {
                 module: &fragment_shader,
-                entry_point: "fs_main",
+                entry_point: fragment_entry_point.as_str(),
                 targets: render_targets_state.as_ref(),
             }),
             primitive: wgpu::PrimitiveState {
@@ -199,18 +316,27 @@ impl ResourceDataTrait<GraphicPassDescription> for GraphicPassData {
             Some(err)
         });
         if let Some(v) = validation {
-            anyhow::bail!(v.to_string())
+            anyhow::bail!(GfxError::Validation(v.to_string()))
         } else {
-            Ok(Self { 
-                render_pipeline, 
+            Ok(Self {
+                render_pipeline,
                 render_targets,
-                bind_group
+                bind_group,
+                sampler_bind_group,
+                constants_bind_group,
+                constants_buffer,
+                dependencies,
             })
         }
-        
+
     }
     fn record_data(&self, _device : &wgpu::Device, cmd: &mut wgpu::CommandEncoder, desc: &GraphicPassDescription) -> anyhow::Result<()> {
+        self.record(cmd, desc, RenderPassQueries::default())
+    }
+}
 
+impl GraphicPassData {
+    fn record(&self, cmd: &mut wgpu::CommandEncoder, desc: &GraphicPassDescription, queries: RenderPassQueries<'_>) -> anyhow::Result<()> {
         // Store locks to keep their lifetime for create_bind_group
         let mut color_attachments = Vec::new();
         let resources_locked = self.render_targets.iter().map(|value| value.lock().unwrap()).collect::<Vec<_>>();
@@ -245,13 +371,21 @@ impl ResourceDataTrait<GraphicPassDescription> for GraphicPassData {
                         stencil_ops: None,
                     }),*/
                     occlusion_query_set: None,
-                    timestamp_writes: None,
+                    timestamp_writes: queries.timestamp_writes,
                 });
                 render_pass.set_index_buffer(data.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
                 render_pass.set_vertex_buffer(0, data.vertex_buffer.slice(..));
                 render_pass.set_pipeline(&self.render_pipeline);
                 render_pass.set_bind_group(0, &self.bind_group, &[]);
+                render_pass.set_bind_group(1, &self.sampler_bind_group, &[]);
+                render_pass.set_bind_group(2, &self.constants_bind_group, &[]);
+                if let Some((query_set, index)) = queries.statistics {
+                    render_pass.begin_pipeline_statistics_query(query_set, index);
+                }
                 render_pass.draw_indexed(0..data.index_count, 0, 0..1);
+                if queries.statistics.is_some() {
+                    render_pass.end_pipeline_statistics_query();
+                }
                 Ok(())
             } else {
                 anyhow::bail!("No geometry data")
@@ -262,33 +396,52 @@ impl ResourceDataTrait<GraphicPassDescription> for GraphicPassData {
     }
 }
 
+fn is_same_resource<Type>(lhs: &Option<ResourceHandle<Type>>, rhs: &Option<ResourceHandle<Type>>) -> bool {
+    match (lhs, rhs) {
+        (Some(lhs), Some(rhs)) => ResourceHandle::ptr_eq(lhs, rhs),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
 impl GraphicPass {
+    // Record the pass, writing the queries of the profiler.
+    pub fn record_data_profiled(&self, cmd: &mut wgpu::CommandEncoder, queries: RenderPassQueries<'_>) -> anyhow::Result<()> {
+        match &self.data {
+            Some(data) => data.record(cmd, &self.desc, queries),
+            None => anyhow::bail!("No data"),
+        }
+    }
+    // Counts of what is drawn by the pass, known without querying the GPU.
+    pub fn get_draw_counters(&self) -> Option<DrawCounters> {
+        let geometry = self.desc.geometry.as_ref()?.lock().unwrap();
+        let data = geometry.data.as_ref()?;
+        let instances = 1; // Passes are not instanced yet.
+        Some(DrawCounters {
+            vertices: data.vertex_count,
+            indices: data.index_count,
+            triangles: data.index_count / 3 * instances,
+            instances,
+        })
+    }
     pub fn set_shader_resource_view(&mut self, index: u32, srv : Option<ResourceHandle<Texture>>) {
-        // TODO: resize should be done by desc data ? or data is built by shader resource view that are set ? 
         if index as usize >= self.desc.shader_resource_view.len() {
             self.desc.shader_resource_view.resize(index as usize + 1, None);
-            self.desc.bind_group.resize(1, Vec::new());
-            self.desc.bind_group[0].resize(index as usize + 1, default_bind_group_entry(index));
             self.dirty = true;
         }
-        if srv.is_some() {          
-            self.desc.bind_group[0][index as usize].binding = index;
-            self.desc.bind_group[0][index as usize].count = None;
-            self.desc.bind_group[0][index as usize].ty = wgpu::BindingType::Texture { 
-                sample_type: wgpu::TextureSampleType::Float { filterable: true }, 
-                view_dimension: wgpu::TextureViewDimension::D2, // TODO: desc fitting texture 
-                multisampled: false
-            };
-            self.desc.bind_group[0][index as usize].visibility = wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT;
-            //self.dirty = true; // TODO: set dirty accordingly.
-        } else {
-            let default = default_bind_group_entry(index);
-            if self.desc.bind_group[0][index as usize] != default {
-                self.desc.bind_group[0][index as usize] = default;
-                self.dirty = true;
-            }
+        if !is_same_resource(&self.desc.shader_resource_view[index as usize], &srv) {
+            self.desc.shader_resource_view[index as usize] = srv;
+            self.dirty = true;
         }
-        self.desc.shader_resource_view[index as usize] = srv;
+    }
+    pub fn set_shader_resource_view_count(&mut self, count: u32) {
+        if self.desc.shader_resource_view.len() > count as usize {
+            self.desc.shader_resource_view.truncate(count as usize);
+            self.dirty = true;
+        }
+    }
+    pub fn get_render_target_size(&self) -> &RenderTargetSize {
+        &self.desc.render_target_size
     }
     pub fn set_render_target(&mut self, index: u32, width : u32, height: u32) {
         let mut rt = AttachmentDescription::default();
@@ -303,22 +456,49 @@ impl GraphicPass {
         }
     }
     pub fn set_geometry(&mut self, geometry: ResourceHandle<Mesh>) {
-        if self.desc.geometry.is_some() && Arc::ptr_eq(&self.desc.geometry.as_ref().unwrap(), &geometry)  {
+        let geometry = Some(geometry);
+        if !is_same_resource(&self.desc.geometry, &geometry) {
+            self.desc.geometry = geometry;
             self.dirty = true;
         }
-        self.desc.geometry = Some(geometry);
     }
     pub fn set_vertex_shader(&mut self, vertex_shader: ResourceHandle<Shader>) {
-        if self.desc.vertex_shader.is_some() && Arc::ptr_eq(&self.desc.vertex_shader.as_ref().unwrap(), &vertex_shader)  {
+        let vertex_shader = Some(vertex_shader);
+        if !is_same_resource(&self.desc.vertex_shader, &vertex_shader) {
+            self.desc.vertex_shader = vertex_shader;
             self.dirty = true;
         }
-        self.desc.vertex_shader = Some(vertex_shader);
     }
     pub fn set_fragment_shader(&mut self, fragment_shader: ResourceHandle<Shader>) {
-        if self.desc.fragment_shader.is_some() && Arc::ptr_eq(&self.desc.fragment_shader.as_ref().unwrap(), &fragment_shader)  {
+        let fragment_shader = Some(fragment_shader);
+        if !is_same_resource(&self.desc.fragment_shader, &fragment_shader) {
+            self.desc.fragment_shader = fragment_shader;
             self.dirty = true;
         }
-        self.desc.fragment_shader = Some(fragment_shader);
+    }
+    // Mark the pass dirty if a shader or a texture it was built with has been rebuilt since.
+    pub fn update_dependencies(&mut self) {
+        if let Some(data) = &self.data {
+            if data.dependencies != self.desc.get_dependencies() {
+                self.dirty = true;
+            }
+        }
+    }
+    pub fn update_constants(&self, queue: &wgpu::Queue, time: f32, time_delta: f32, frame: u32) {
+        if let Some(data) = &self.data {
+            let (width, height) = match self.desc.render_target_desc.first() {
+                Some(rt) => (rt.width as f32, rt.height as f32),
+                None => (0.0, 0.0),
+            };
+            let constants = GraphicPassConstants {
+                resolution: [width, height, 1.0, 0.0],
+                time,
+                time_delta,
+                frame: frame as i32,
+                padding: 0.0,
+            };
+            queue.write_buffer(&data.constants_buffer, 0, bytemuck::bytes_of(&constants));
+        }
     }
     pub fn get_render_target(&self, index: u32) -> Option<ResourceHandle<Texture>> {
         if self.data.is_some() {
@@ -327,4 +507,4 @@ impl GraphicPass {
             None
         }
     }
-}
\ No newline at end of file
+}
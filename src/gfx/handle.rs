@@ -0,0 +1,214 @@
+use std::sync::{Arc, LockResult, Mutex, MutexGuard};
+
+// Shared reference to a resource. Nodes & passes referencing the same resource hold the same handle.
+pub struct ResourceHandle<Type>(Arc<Mutex<Type>>);
+
+impl<Type> ResourceHandle<Type> {
+    pub fn new(resource: Type) -> Self {
+        Self(Arc::new(Mutex::new(resource)))
+    }
+    pub fn lock(&self) -> LockResult<MutexGuard<'_, Type>> {
+        self.0.lock()
+    }
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        Arc::ptr_eq(&this.0, &other.0)
+    }
+}
+
+impl<Type> Clone for ResourceHandle<Type> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+// Handles are equal when they reference the same resource.
+impl<Type> PartialEq for ResourceHandle<Type> {
+    fn eq(&self, other: &Self) -> bool {
+        Self::ptr_eq(self, other)
+    }
+}
+
+impl<Type: Default> Default for ResourceHandle<Type> {
+    fn default() -> Self {
+        Self::new(Type::default())
+    }
+}
+
+impl<Type: std::fmt::Debug> std::fmt::Debug for ResourceHandle<Type> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+// Handles are serialized as ids so that shared resources are still shared once loaded.
+// The first occurrence of a resource holds its content, the following ones only its id.
+// Sharing only works within a scope, outside of it every handle is serialized with its content.
+#[cfg(feature = "persistence")]
+mod persistence {
+    use std::{any::Any, cell::RefCell, collections::HashMap, rc::Rc, sync::{Arc, Mutex}};
+
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::ResourceHandle;
+
+    // Update a resource in place from its deserialized content, keeping what is not serialized (GPU data).
+    pub trait Restore {
+        fn restore(&mut self, snapshot: Self);
+    }
+
+    // Handles serialized in a scope, indexed by id. Deserializing with them restores the content of these handles
+    // instead of creating new ones, so that an undo keeps GPU resources & the contents of feedback textures.
+    #[derive(Clone, Default)]
+    pub struct ResourceCapture(Rc<Vec<Box<dyn Any>>>);
+
+    impl ResourceCapture {
+        pub fn is_empty(&self) -> bool {
+            self.0.is_empty()
+        }
+    }
+
+    #[derive(Default)]
+    struct Scope {
+        ids: HashMap<usize, u64>, // Address of serialized resources to their id.
+        handles: HashMap<u64, Box<dyn Any>>, // Id of deserialized resources to their handle.
+        capture: Option<Vec<Box<dyn Any>>>, // Serialized handles, if captured.
+        restored: ResourceCapture, // Handles to restore when deserializing.
+    }
+
+    thread_local! {
+        static SCOPE: RefCell<Option<Scope>> = RefCell::new(None);
+    }
+
+    fn with_scope<Result>(scope: Scope, f: impl FnOnce() -> Result) -> (Result, Scope) {
+        let previous = SCOPE.with(|current| current.replace(Some(scope)));
+        let result = f();
+        let scope = SCOPE.with(|current| current.replace(previous)).unwrap_or_default();
+        (result, scope)
+    }
+
+    // Serialize or deserialize a whole project within f, so that handles are matched across the project.
+    pub fn with_resource_scope<Result>(f: impl FnOnce() -> Result) -> Result {
+        with_scope(Scope::default(), f).0
+    }
+
+    // Serialize within f & keep the serialized handles, to restore them with with_restored_resources.
+    pub fn with_resource_capture<Result>(f: impl FnOnce() -> Result) -> (Result, ResourceCapture) {
+        let scope = Scope {
+            capture: Some(Vec::new()),
+            ..Default::default()
+        };
+        let (result, scope) = with_scope(scope, f);
+        (result, ResourceCapture(Rc::new(scope.capture.unwrap_or_default())))
+    }
+
+    // Deserialize what was serialized with with_resource_capture, onto the captured handles.
+    pub fn with_restored_resources<Result>(resources: &ResourceCapture, f: impl FnOnce() -> Result) -> Result {
+        let scope = Scope {
+            restored: resources.clone(),
+            ..Default::default()
+        };
+        with_scope(scope, f).0
+    }
+
+    #[derive(Serialize)]
+    struct SerializedHandle<'a, Type> {
+        id: u64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        resource: Option<&'a Type>,
+    }
+
+    #[derive(Deserialize)]
+    struct DeserializedHandle<Type> {
+        id: u64,
+        resource: Option<Type>,
+    }
+
+    impl<Type: Serialize + 'static> Serialize for ResourceHandle<Type> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let address = Arc::as_ptr(&self.0) as *const () as usize;
+            // Returns the id & whether it was already serialized.
+            let (id, known) = SCOPE.with(|scope| match scope.borrow_mut().as_mut() {
+                Some(scope) => {
+                    let next_id = scope.ids.len() as u64;
+                    match scope.ids.get(&address) {
+                        Some(id) => (*id, true),
+                        None => {
+                            scope.ids.insert(address, next_id);
+                            if let Some(capture) = &mut scope.capture {
+                                capture.push(Box::new(self.clone()));
+                            }
+                            (next_id, false)
+                        }
+                    }
+                }
+                None => (0, false),
+            });
+            if known {
+                SerializedHandle::<Type> { id, resource: None }.serialize(serializer)
+            } else {
+                let resource = self.0.lock().map_err(|_| serde::ser::Error::custom("Resource lock is poisoned"))?;
+                SerializedHandle { id, resource: Some(&*resource) }.serialize(serializer)
+            }
+        }
+    }
+
+    impl<'de, Type: Deserialize<'de> + Restore + 'static> Deserialize<'de> for ResourceHandle<Type> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            match DeserializedHandle::<Type>::deserialize(deserializer)? {
+                DeserializedHandle { id, resource: Some(resource) } => {
+                    let restored = SCOPE.with(|scope| {
+                        scope.borrow().as_ref()
+                            .and_then(|scope| scope.restored.0.get(id as usize))
+                            .and_then(|handle| handle.downcast_ref::<ResourceHandle<Type>>())
+                            .cloned()
+                    });
+                    let handle = match restored {
+                        Some(handle) => {
+                            handle.lock().map_err(|_| D::Error::custom("Resource lock is poisoned"))?.restore(resource);
+                            handle
+                        }
+                        None => ResourceHandle(Arc::new(Mutex::new(resource))),
+                    };
+                    SCOPE.with(|scope| {
+                        if let Some(scope) = scope.borrow_mut().as_mut() {
+                            scope.handles.insert(id, Box::new(handle.clone()));
+                        }
+                    });
+                    Ok(handle)
+                }
+                DeserializedHandle { id, resource: None } => {
+                    SCOPE.with(|scope| match scope.borrow().as_ref() {
+                        Some(scope) => match scope.handles.get(&id) {
+                            Some(handle) => handle
+                                .downcast_ref::<ResourceHandle<Type>>()
+                                .cloned()
+                                .ok_or_else(|| D::Error::custom(format!("Resource {} has an unexpected type", id))),
+                            None => Err(D::Error::custom(format!("Resource {} is referenced before being defined", id))),
+                        },
+                        None => Err(D::Error::custom("Shared resources must be deserialized within a resource scope")),
+                    })
+                }
+            }
+        }
+    }
+
+    // For shared values which are not resources, used with serde(with), matched within a scope like handles.
+    pub mod shared_resource {
+        use std::sync::{Arc, Mutex};
+
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+        use super::{super::ResourceHandle, Restore};
+
+        pub fn serialize<Type: Serialize + 'static, S: Serializer>(value: &Arc<Mutex<Type>>, serializer: S) -> Result<S::Ok, S::Error> {
+            ResourceHandle(value.clone()).serialize(serializer)
+        }
+
+        pub fn deserialize<'de, Type: Deserialize<'de> + Restore + 'static, D: Deserializer<'de>>(deserializer: D) -> Result<Arc<Mutex<Type>>, D::Error> {
+            ResourceHandle::deserialize(deserializer).map(|handle| handle.0)
+        }
+    }
+}
+
+#[cfg(feature = "persistence")]
+pub use persistence::{shared_resource, with_resource_capture, with_resource_scope, with_restored_resources, ResourceCapture, Restore};
@@ -0,0 +1,236 @@
+use std::fmt;
+
+use super::GfxError;
+
+// Beyond this, elements are too big to be displayed as a table.
+const MAX_COLUMNS: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BufferValue {
+    Float(f32),
+    Int(i32),
+    Uint(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ScalarType {
+    Float,
+    Int,
+    Uint,
+}
+
+#[derive(Debug, Clone)]
+pub struct BufferColumn {
+    pub name: String,
+    offset: u32, // From the start of the element.
+    scalar: ScalarType,
+}
+
+// Layout of the elements of a buffer, as a list of scalar columns.
+#[derive(Debug, Clone)]
+pub struct BufferLayout {
+    pub stride: u32,
+    pub columns: Vec<BufferColumn>,
+}
+
+impl BufferLayout {
+    // Parse a WGSL type (f32, vec4<f32>...) or struct declarations, the last struct being the element.
+    // Offsets & stride follow the WGSL memory layout rules, as for storage buffers.
+    pub fn parse(source: &str) -> anyhow::Result<Self> {
+        let is_struct = source.contains("struct");
+        let wgsl = if is_struct {
+            source.to_string()
+        } else {
+            format!("struct Element {{ value: {} }}", source.trim())
+        };
+        let module = naga::front::wgsl::parse_str(&wgsl).map_err(|err| GfxError::ShaderCompilation {
+            message: err.emit_to_string(&wgsl),
+            spans: Vec::new(),
+        })?;
+        let mut layouter = naga::proc::Layouter::default();
+        layouter.update(module.to_ctx())?;
+        let element = module.types.iter()
+            .filter(|(_, ty)| matches!(ty.inner, naga::TypeInner::Struct { .. }))
+            .last()
+            .map(|(handle, _)| handle)
+            .ok_or(anyhow::anyhow!("No struct declared"))?;
+        let mut columns = Vec::new();
+        match &module.types[element].inner {
+            // Wrapped type is the element itself.
+            naga::TypeInner::Struct { members, .. } if !is_struct => add_columns(&module, members[0].ty, String::new(), 0, &mut columns)?,
+            _ => add_columns(&module, element, String::new(), 0, &mut columns)?,
+        }
+        let stride = layouter[element].to_stride();
+        Ok(Self { stride, columns })
+    }
+    pub fn element_count(&self, bytes: &[u8]) -> usize {
+        bytes.len() / self.stride.max(1) as usize
+    }
+    pub fn value(&self, bytes: &[u8], element: usize, column: usize) -> Option<BufferValue> {
+        let column = self.columns.get(column)?;
+        let offset = element * self.stride as usize + column.offset as usize;
+        let bytes: [u8; 4] = bytes.get(offset..offset + 4)?.try_into().ok()?;
+        Some(match column.scalar {
+            ScalarType::Float => BufferValue::Float(f32::from_le_bytes(bytes)),
+            ScalarType::Int => BufferValue::Int(i32::from_le_bytes(bytes)),
+            ScalarType::Uint => BufferValue::Uint(u32::from_le_bytes(bytes)),
+        })
+    }
+}
+
+fn join(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", prefix, name)
+    }
+}
+
+fn scalar_type(kind: naga::ScalarKind, width: naga::Bytes) -> anyhow::Result<ScalarType> {
+    match (kind, width) {
+        (naga::ScalarKind::Float, 4) => Ok(ScalarType::Float),
+        (naga::ScalarKind::Sint, 4) => Ok(ScalarType::Int),
+        (naga::ScalarKind::Uint, 4) => Ok(ScalarType::Uint),
+        (kind, width) => anyhow::bail!(GfxError::Unsupported(format!("Scalar {:?} of {} bytes can't be stored in a buffer", kind, width))),
+    }
+}
+
+fn add_columns(module: &naga::Module, ty: naga::Handle<naga::Type>, name: String, offset: u32, columns: &mut Vec<BufferColumn>) -> anyhow::Result<()> {
+    const COMPONENTS: [&str; 4] = ["x", "y", "z", "w"];
+    if columns.len() > MAX_COLUMNS {
+        anyhow::bail!(GfxError::Unsupported(format!("Elements have more than {} values", MAX_COLUMNS)));
+    }
+    match &module.types[ty].inner {
+        naga::TypeInner::Scalar { kind, width } | naga::TypeInner::Atomic { kind, width } => {
+            let name = if name.is_empty() { String::from("value") } else { name };
+            columns.push(BufferColumn { name, offset, scalar: scalar_type(*kind, *width)? });
+        }
+        naga::TypeInner::Vector { size, kind, width } => {
+            let scalar = scalar_type(*kind, *width)?;
+            for (component, component_name) in COMPONENTS.iter().enumerate().take(*size as usize) {
+                columns.push(BufferColumn { name: join(&name, component_name), offset: offset + component as u32 * *width as u32, scalar });
+            }
+        }
+        naga::TypeInner::Matrix { columns: matrix_columns, rows, width } => {
+            let scalar = scalar_type(naga::ScalarKind::Float, *width)?;
+            // Columns are aligned as vectors, vec3 being aligned as vec4.
+            let column_stride = match rows {
+                naga::VectorSize::Bi => 2,
+                _ => 4,
+            } * *width as u32;
+            for column in 0..*matrix_columns as u32 {
+                for (row, row_name) in COMPONENTS.iter().enumerate().take(*rows as usize) {
+                    columns.push(BufferColumn {
+                        name: format!("{}[{}].{}", name, column, row_name),
+                        offset: offset + column * column_stride + row as u32 * *width as u32,
+                        scalar,
+                    });
+                }
+            }
+        }
+        naga::TypeInner::Array { size: naga::ArraySize::Dynamic, .. } => {
+            let name = if name.is_empty() { "The element" } else { name.as_str() };
+            anyhow::bail!(GfxError::Unsupported(format!("{} is a runtime-sized array, give it a fixed size such as array<f32, 16>", name)))
+        }
+        naga::TypeInner::Array { base, size: naga::ArraySize::Constant(size), stride } => {
+            for index in 0..size.get() {
+                add_columns(module, *base, format!("{}[{}]", name, index), offset + index * stride, columns)?;
+            }
+        }
+        naga::TypeInner::Struct { members, .. } => {
+            for member in members {
+                let member_name = member.name.clone().unwrap_or_default();
+                add_columns(module, member.ty, join(&name, &member_name), offset + member.offset, columns)?;
+            }
+        }
+        _ => anyhow::bail!(GfxError::Unsupported(format!("Type {} can't be decoded from a buffer", module.types[ty].name.as_deref().unwrap_or("?")))),
+    }
+    Ok(())
+}
+
+impl BufferValue {
+    pub fn to_f64(self) -> f64 {
+        match self {
+            BufferValue::Float(value) => value as f64,
+            BufferValue::Int(value) => value as f64,
+            BufferValue::Uint(value) => value as f64,
+        }
+    }
+}
+
+impl fmt::Display for BufferValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BufferValue::Float(value) => write!(f, "{}", value),
+            BufferValue::Int(value) => write!(f, "{}", value),
+            BufferValue::Uint(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn columns(layout: &BufferLayout) -> Vec<(&str, u32)> {
+        layout.columns.iter().map(|column| (column.name.as_str(), column.offset)).collect()
+    }
+
+    #[test]
+    fn scalar() {
+        let layout = BufferLayout::parse("u32").unwrap();
+        assert_eq!(layout.stride, 4);
+        assert_eq!(columns(&layout), [("value", 0)]);
+        assert_eq!(layout.columns[0].scalar, ScalarType::Uint);
+    }
+
+    #[test]
+    fn vec3_padding() {
+        // vec3 is aligned as vec4, both within the struct & for the stride.
+        let layout = BufferLayout::parse("struct Particle { mass: f32, velocity: vec3<f32> }").unwrap();
+        assert_eq!(layout.stride, 32);
+        assert_eq!(columns(&layout), [("mass", 0), ("velocity.x", 16), ("velocity.y", 20), ("velocity.z", 24)]);
+    }
+
+    #[test]
+    fn mat3x3() {
+        let layout = BufferLayout::parse("mat3x3<f32>").unwrap();
+        assert_eq!(layout.stride, 48);
+        assert_eq!(layout.columns.len(), 9);
+        assert_eq!(columns(&layout)[3], ("[1].x", 16));
+        assert_eq!(columns(&layout)[8], ("[2].z", 40));
+    }
+
+    #[test]
+    fn fixed_array() {
+        let layout = BufferLayout::parse("array<vec2<f32>, 3>").unwrap();
+        assert_eq!(layout.stride, 24);
+        assert_eq!(columns(&layout), [("[0].x", 0), ("[0].y", 4), ("[1].x", 8), ("[1].y", 12), ("[2].x", 16), ("[2].y", 20)]);
+    }
+
+    #[test]
+    fn nested_structs() {
+        let layout = BufferLayout::parse("struct Inner { a: f32, b: vec2<f32> }\nstruct Outer { id: i32, inner: Inner }").unwrap();
+        assert_eq!(layout.stride, 24);
+        assert_eq!(columns(&layout), [("id", 0), ("inner.a", 8), ("inner.b.x", 16), ("inner.b.y", 20)]);
+        assert_eq!(layout.columns[0].scalar, ScalarType::Int);
+    }
+
+    #[test]
+    fn values() {
+        let layout = BufferLayout::parse("struct Element { id: u32, value: f32 }").unwrap();
+        let bytes: Vec<u8> = [7u32.to_le_bytes(), 1.5f32.to_le_bytes(), 8u32.to_le_bytes(), 2.5f32.to_le_bytes()].concat();
+        assert_eq!(layout.element_count(&bytes), 2);
+        assert_eq!(layout.value(&bytes, 1, 0), Some(BufferValue::Uint(8)));
+        assert_eq!(layout.value(&bytes, 1, 1), Some(BufferValue::Float(2.5)));
+        assert_eq!(layout.value(&bytes, 2, 0), None);
+    }
+
+    #[test]
+    fn runtime_sized_array() {
+        let err = BufferLayout::parse("array<f32>").unwrap_err();
+        assert!(format!("{:#}", err).contains("runtime-sized array"), "{:#}", err);
+        let err = BufferLayout::parse("struct Element { count: u32, values: array<f32> }").unwrap_err();
+        assert!(format!("{:#}", err).contains("values is a runtime-sized array"), "{:#}", err);
+    }
+}
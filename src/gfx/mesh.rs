@@ -1,6 +1,6 @@
 use std::{f32::consts::PI, mem, path::PathBuf};
 
-use super::resource::{Resource, ResourceDataTrait, ResourceDescTrait};
+use super::{resource::{Resource, ResourceDataTrait, ResourceDescTrait}, GfxError};
 
 
 pub enum VertexFactory {
@@ -71,7 +71,8 @@ impl Vertex for StaticVertex {
 #[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
 pub enum MeshShape {
     Sphere{ segment_count: u32, ring_count: u32, radius: f32 },
-    Cube{ size: f32 }
+    Cube{ size: f32 },
+    Quad{ size: f32 }, // Facing Z, a size of 2 cover the whole screen without transform.
 }
 impl MeshShape {
     pub fn default_sphere() -> Self {
@@ -80,10 +81,17 @@ impl MeshShape {
     pub fn default_cube() -> Self {
         MeshShape::Cube { size: 1.0, }
     }
+    pub fn default_quad() -> Self {
+        MeshShape::Quad { size: 1.0, }
+    }
+    pub fn fullscreen_quad() -> Self {
+        MeshShape::Quad { size: 2.0, }
+    }
     pub fn get_shape_name(&self) -> &str {
         match self {
             MeshShape::Sphere { .. } => "Sphere",
             MeshShape::Cube { .. } => "Cube",
+            MeshShape::Quad { .. } => "Quad",
         }
     }
 }
@@ -120,6 +128,7 @@ pub struct MeshData {
     pub(crate) vertex_buffer: wgpu::Buffer,
     pub(crate) index_buffer: wgpu::Buffer,
     pub(crate) index_count: u32,
+    pub(crate) vertex_count: u32,
 }
 
 pub type Mesh = Resource<MeshDescription, MeshData>;
@@ -241,10 +250,30 @@ impl ResourceDataTrait<MeshDescription> for MeshData {
                         // TODO create sphere
                         (vertices, indices)
                     }
+                    MeshShape::Quad{ size } => {
+                        let l = size / 2.0;
+                        let corners = [
+                            ([-l, -l], [0.0, 1.0]),
+                            ([ l, -l], [1.0, 1.0]),
+                            ([ l,  l], [1.0, 0.0]),
+                            ([-l,  l], [0.0, 0.0]),
+                        ];
+                        let vertices = corners.iter().map(|(position, tex_coords)| StaticVertex {
+                            position: [position[0], position[1], 0.0],
+                            normal: [0.0, 0.0, 1.0],
+                            tangent: [1.0, 0.0, 0.0],
+                            bitangent: [0.0, 1.0, 0.0],
+                            tex_coords: *tex_coords,
+                            color:[1.0, 1.0, 1.0, 1.0],
+                        }).collect::<Vec<_>>();
+                        // Counter clockwise
+                        let indices = vec![0, 1, 2, 0, 2, 3];
+                        (vertices, indices)
+                    }
                 }
             },
             MeshSource::Path(_) => {
-                anyhow::bail!("Path in mesh not yet supported.")
+                anyhow::bail!(GfxError::Unsupported(String::from("Path in mesh not yet supported.")))
             } 
             _ => { anyhow::bail!("Invalid mesh source") }
         };
@@ -267,6 +296,7 @@ impl ResourceDataTrait<MeshDescription> for MeshData {
             vertex_buffer,
             index_buffer,
             index_count: indices.len() as u32,
+            vertex_count: vertices.len() as u32,
         })
     }
     fn record_data(&self, _device: &wgpu::Device, _cmd: &mut wgpu::CommandEncoder, _desc: &MeshDescription) -> anyhow::Result<()> {
@@ -275,5 +305,7 @@ impl ResourceDataTrait<MeshDescription> for MeshData {
 }
 
 impl Mesh {
-    
+    pub fn from_shape(shape: MeshShape) -> Self {
+        Self::from_desc(MeshDescription { source: MeshSource::Shape(shape) })
+    }
 }
\ No newline at end of file
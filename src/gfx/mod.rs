@@ -1,31 +1,42 @@
 mod resource;
+mod handle;
 mod texture;
 mod buffer;
 mod shader;
 mod graphic_pass;
 mod compute_pass;
 mod backbuffer;
+mod blit;
 
 mod camera;
 mod mesh;
+mod error;
 
-use std::sync::Arc;
-use std::sync::Mutex;
+mod readback;
+mod layout;
+mod profiler;
+mod size_expression;
 
-pub type ResourceHandle<Type> = Arc<Mutex<Type>>;
+pub use self::handle::ResourceHandle;
+pub use self::error::GfxError;
+#[cfg(feature = "persistence")]
+pub use self::handle::{shared_resource, with_resource_capture, with_resource_scope, with_restored_resources, ResourceCapture, Restore};
 
-// TODO: should be custom struct instead instead.
 pub fn visit_resource<Type>(data: &ResourceHandle<Type>, f: impl FnOnce(&Type)) {
     let resource = &mut data.lock().expect("Could not lock resource");
     f(resource)
 }
-pub fn visit_resource_mut<Type>(data: &ResourceHandle<Type>, f: impl FnOnce(&mut Type)) {
+pub fn visit_resource_mut<Type, Result>(data: &ResourceHandle<Type>, f: impl FnOnce(&mut Type) -> Result) -> Result {
     let resource = &mut data.lock().expect("Could not lock resource");
     f(resource)
 }
 
 pub use self::backbuffer::BackbufferPass;
+pub use self::backbuffer::BackbufferMode;
 pub use self::graphic_pass::GraphicPass;
+pub use self::graphic_pass::DrawCounters;
+pub use self::graphic_pass::RenderTargetSize;
+pub use self::size_expression::{check_size_expression, evaluate_size_expression};
 pub use self::compute_pass::ComputePass;
 pub use self::buffer::Buffer;
 pub use self::texture::Texture;
@@ -34,4 +45,14 @@ pub use self::mesh::Mesh;
 pub use self::shader::Shader;
 
 pub use self::mesh::MeshSource;
-pub use self::mesh::MeshShape;
\ No newline at end of file
+pub use self::mesh::MeshShape;
+pub use self::shader::ShaderLanguage;
+pub use self::shader::ShaderStage;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use self::readback::read_texture_rgba8; // Blocking, not available on web.
+pub use self::readback::TextureReadback;
+pub use self::readback::TexturePixels;
+pub use self::readback::BufferReadback;
+pub use self::layout::{BufferLayout, BufferColumn, BufferValue};
+pub use self::profiler::{GpuProfiler, PassProfile, PipelineStatistics, RenderPassQueries};
\ No newline at end of file
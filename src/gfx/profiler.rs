@@ -0,0 +1,251 @@
+use super::readback::Mapping;
+
+// Passes profiled in a frame.
+const MAX_PASSES: u32 = 256;
+// Statistics queried on render passes, values are resolved in the order of their bits.
+const STATISTICS: wgpu::PipelineStatisticsTypes = wgpu::PipelineStatisticsTypes::VERTEX_SHADER_INVOCATIONS
+    .union(wgpu::PipelineStatisticsTypes::CLIPPER_PRIMITIVES_OUT)
+    .union(wgpu::PipelineStatisticsTypes::FRAGMENT_SHADER_INVOCATIONS);
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PipelineStatistics {
+    pub vertex_invocations: u64,
+    pub clipper_primitives: u64,
+    pub fragment_invocations: u64,
+}
+
+impl std::ops::AddAssign for PipelineStatistics {
+    fn add_assign(&mut self, other: Self) {
+        self.vertex_invocations += other.vertex_invocations;
+        self.clipper_primitives += other.clipper_primitives;
+        self.fragment_invocations += other.fragment_invocations;
+    }
+}
+
+// What the GPU spent on a pass. Times are in milliseconds from the beginning of the first pass of the frame,
+// zero if timestamps are not supported.
+#[derive(Debug, Clone)]
+pub struct PassProfile<K> {
+    pub key: K,
+    pub start: f32,
+    pub end: f32,
+    pub statistics: Option<PipelineStatistics>,
+}
+
+impl<K> PassProfile<K> {
+    pub fn duration(&self) -> f32 {
+        self.end - self.start
+    }
+}
+
+// Queries a render pass should write.
+#[derive(Default)]
+pub struct RenderPassQueries<'a> {
+    pub timestamp_writes: Option<wgpu::RenderPassTimestampWrites<'a>>,
+    pub statistics: Option<(&'a wgpu::QuerySet, u32)>, // Query set & index of the pipeline statistics query.
+}
+
+enum ReadbackState {
+    Idle,
+    Copied(u32), // Copy of the queries of some passes recorded, waiting for submission.
+    Mapping(Mapping, u32),
+    Read(Vec<u64>),
+}
+
+// Query set whose results are read back without blocking.
+struct QueryReadback {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    queries_per_pass: u32,
+    values_per_query: u32,
+    state: ReadbackState,
+}
+
+impl QueryReadback {
+    fn new(device: &wgpu::Device, ty: wgpu::QueryType, queries_per_pass: u32, values_per_query: u32) -> Self {
+        let size = (MAX_PASSES * queries_per_pass * values_per_query) as wgpu::BufferAddress * wgpu::QUERY_SIZE as wgpu::BufferAddress;
+        Self {
+            query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                label: Some("ProfilerQuerySet"),
+                ty,
+                count: MAX_PASSES * queries_per_pass,
+            }),
+            resolve_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("ProfilerResolveBuffer"),
+                size,
+                usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }),
+            readback_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("ProfilerReadbackBuffer"),
+                size,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            }),
+            queries_per_pass,
+            values_per_query,
+            state: ReadbackState::Idle,
+        }
+    }
+    fn is_idle(&self) -> bool {
+        matches!(self.state, ReadbackState::Idle)
+    }
+    fn is_pending(&self) -> bool {
+        matches!(self.state, ReadbackState::Copied(_) | ReadbackState::Mapping(..))
+    }
+    // Map the copy once submitted, & read it once mapped.
+    fn update(&mut self, device: &wgpu::Device) {
+        self.state = match std::mem::replace(&mut self.state, ReadbackState::Idle) {
+            ReadbackState::Copied(count) => ReadbackState::Mapping(Mapping::new(&self.readback_buffer), count),
+            ReadbackState::Mapping(mapping, count) => match mapping.take(device) {
+                None => ReadbackState::Mapping(mapping, count),
+                Some(Err(err)) => {
+                    log::warn!("Failed to read queries back : {:#}", err);
+                    ReadbackState::Idle
+                }
+                Some(Ok(())) => {
+                    let values: Vec<u64> = {
+                        let data = self.readback_buffer.slice(..).get_mapped_range();
+                        data.chunks_exact(8)
+                            .take((count * self.queries_per_pass * self.values_per_query) as usize)
+                            .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+                            .collect()
+                    };
+                    self.readback_buffer.unmap();
+                    ReadbackState::Read(values)
+                }
+            },
+            state => state,
+        };
+    }
+    fn take_values(&mut self) -> Option<Vec<u64>> {
+        match std::mem::replace(&mut self.state, ReadbackState::Idle) {
+            ReadbackState::Read(values) => Some(values),
+            state => {
+                self.state = state;
+                None
+            }
+        }
+    }
+    fn copy(&mut self, cmd: &mut wgpu::CommandEncoder, count: u32) {
+        let queries = count * self.queries_per_pass;
+        let size = (queries * self.values_per_query) as wgpu::BufferAddress * wgpu::QUERY_SIZE as wgpu::BufferAddress;
+        cmd.resolve_query_set(&self.query_set, 0..queries, &self.resolve_buffer, 0);
+        cmd.copy_buffer_to_buffer(&self.resolve_buffer, 0, &self.readback_buffer, 0, size);
+        self.state = ReadbackState::Copied(count);
+    }
+}
+
+// Profile passes on the GPU with timestamp & pipeline statistics queries, identifying them with keys.
+// Results are read back without blocking, a few frames later. Queries not supported by the device are skipped.
+pub struct GpuProfiler<K> {
+    timestamps: Option<QueryReadback>,
+    period: f32, // Nanoseconds per timestamp tick.
+    statistics: Option<QueryReadback>,
+    keys: Vec<K>, // Passes profiled in the current frame.
+    read_keys: Vec<K>, // Passes being read back.
+}
+
+impl<K> GpuProfiler<K> {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let features = device.features();
+        Self {
+            timestamps: features.contains(wgpu::Features::TIMESTAMP_QUERY)
+                .then(|| QueryReadback::new(device, wgpu::QueryType::Timestamp, 2, 1)),
+            period: queue.get_timestamp_period(),
+            statistics: features.contains(wgpu::Features::PIPELINE_STATISTICS_QUERY)
+                .then(|| QueryReadback::new(device, wgpu::QueryType::PipelineStatistics(STATISTICS), 1, STATISTICS.bits().count_ones())),
+            keys: Vec::new(),
+            read_keys: Vec::new(),
+        }
+    }
+    pub fn has_timestamps(&self) -> bool {
+        self.timestamps.is_some()
+    }
+    pub fn has_statistics(&self) -> bool {
+        self.statistics.is_some()
+    }
+    fn readbacks(&mut self) -> impl Iterator<Item = &mut QueryReadback> {
+        self.timestamps.iter_mut().chain(self.statistics.iter_mut())
+    }
+    // Start profiling a new frame. Commands of the previous one must have been submitted.
+    // Returns the profiles of a previous frame once every query is read back.
+    pub fn begin_frame(&mut self, device: &wgpu::Device) -> Option<Vec<PassProfile<K>>> {
+        self.keys.clear();
+        let mut pending = false;
+        for readback in self.readbacks() {
+            readback.update(device);
+            pending |= readback.is_pending();
+        }
+        if pending {
+            return None;
+        }
+        let ticks = self.timestamps.as_mut().and_then(QueryReadback::take_values);
+        let statistics = self.statistics.as_mut().and_then(QueryReadback::take_values);
+        if ticks.is_none() && statistics.is_none() {
+            return None;
+        }
+        let first = ticks.as_ref().and_then(|ticks| ticks.iter().step_by(2).copied().min()).unwrap_or(0);
+        let period = self.period as f64;
+        let to_ms = |tick: u64| (tick.saturating_sub(first) as f64 * period / 1_000_000.0) as f32;
+        let values_per_pass = STATISTICS.bits().count_ones() as usize;
+        Some(self.read_keys.drain(..).enumerate().map(|(index, key)| {
+            let (start, end) = match ticks.as_ref() {
+                Some(ticks) => (to_ms(ticks[index * 2]), to_ms(ticks[index * 2 + 1].max(ticks[index * 2]))),
+                None => (0.0, 0.0),
+            };
+            let statistics = statistics.as_ref().map(|values| {
+                let values = &values[index * values_per_pass..(index + 1) * values_per_pass];
+                PipelineStatistics {
+                    vertex_invocations: values[0],
+                    clipper_primitives: values[1],
+                    fragment_invocations: values[2],
+                }
+            });
+            PassProfile { key, start, end, statistics }
+        }).collect())
+    }
+    // Reserve queries for a render pass, which must write all of them. Empty if queries are not supported, or too many passes are profiled.
+    pub fn render_pass_queries(&mut self, key: K) -> RenderPassQueries<'_> {
+        let index = match self.reserve(key) {
+            Some(index) => index,
+            None => return RenderPassQueries::default(),
+        };
+        RenderPassQueries {
+            timestamp_writes: self.timestamps.as_ref().map(|timestamps| wgpu::RenderPassTimestampWrites {
+                query_set: &timestamps.query_set,
+                beginning_of_pass_write_index: Some(index * 2),
+                end_of_pass_write_index: Some(index * 2 + 1),
+            }),
+            statistics: self.statistics.as_ref().map(|statistics| (&statistics.query_set, index)),
+        }
+    }
+    // Release the queries of a pass which failed to record, so that no unwritten query is resolved.
+    pub fn release(&mut self, key: &K) where K: PartialEq {
+        if self.keys.last() == Some(key) {
+            self.keys.pop();
+        }
+    }
+    fn reserve(&mut self, key: K) -> Option<u32> {
+        let index = self.keys.len() as u32;
+        if (self.timestamps.is_none() && self.statistics.is_none()) || index >= MAX_PASSES {
+            return None;
+        }
+        self.keys.push(key);
+        Some(index)
+    }
+    // Resolve the queries of the frame, which are read back unless a previous frame is still being read.
+    // Returns true if they will be.
+    pub fn end_frame(&mut self, cmd: &mut wgpu::CommandEncoder) -> bool {
+        let count = self.keys.len() as u32;
+        if count == 0 || !self.readbacks().all(|readback| readback.is_idle()) {
+            return false;
+        }
+        for readback in self.readbacks() {
+            readback.copy(cmd, count);
+        }
+        self.read_keys = std::mem::take(&mut self.keys);
+        true
+    }
+}
@@ -0,0 +1,247 @@
+use std::iter;
+use std::sync::{Arc, Mutex};
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::mpsc;
+
+use super::GfxError;
+
+// Buffer holding a copy of a texture, with rows padded to the required alignment.
+struct ReadbackBuffer {
+    buffer: wgpu::Buffer,
+    unpadded_bytes_per_row: u32,
+    padded_bytes_per_row: u32,
+}
+
+impl ReadbackBuffer {
+    fn new(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture, bytes_per_pixel: u32) -> Self {
+        let width = texture.width();
+        let height = texture.height();
+        // Rows of the buffer need to be aligned.
+        let unpadded_bytes_per_row = bytes_per_pixel * width;
+        let alignment = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = (unpadded_bytes_per_row + alignment - 1) / alignment * alignment;
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("ReadbackBuffer"),
+            size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("ReadbackEncoder"),
+        });
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        queue.submit(iter::once(encoder.finish()));
+        Self {
+            buffer,
+            unpadded_bytes_per_row,
+            padded_bytes_per_row,
+        }
+    }
+    // Content of the mapped buffer, without row padding.
+    fn read(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        {
+            let data = self.buffer.slice(..).get_mapped_range();
+            for row in data.chunks(self.padded_bytes_per_row as usize) {
+                bytes.extend_from_slice(&row[..self.unpadded_bytes_per_row as usize]);
+            }
+        }
+        self.buffer.unmap();
+        bytes
+    }
+}
+
+// Copy a texture content back to the CPU, blocking until the GPU is done.
+// Only 8 bits RGBA formats are supported for now.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_texture_rgba8(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture) -> anyhow::Result<image::RgbaImage> {
+    match texture.format() {
+        wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => {}
+        format => anyhow::bail!(GfxError::Unsupported(format!("Readback of format {:?} is not supported", format))),
+    }
+    let readback = ReadbackBuffer::new(device, queue, texture, 4);
+    let (sender, receiver) = mpsc::channel();
+    readback.buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    device.poll(wgpu::Maintain::Wait);
+    receiver.recv()??;
+    image::RgbaImage::from_raw(texture.width(), texture.height(), readback.read()).ok_or(anyhow::anyhow!("Readback size mismatch"))
+}
+
+// Map a buffer for reading, which is done once the GPU is done with it.
+pub(super) struct Mapping(Arc<Mutex<Option<Result<(), wgpu::BufferAsyncError>>>>);
+
+impl Mapping {
+    pub(super) fn new(buffer: &wgpu::Buffer) -> Self {
+        let mapped = Arc::new(Mutex::new(None));
+        let result = mapped.clone();
+        buffer.slice(..).map_async(wgpu::MapMode::Read, move |mapped| {
+            *result.lock().unwrap() = Some(mapped);
+        });
+        Self(mapped)
+    }
+    pub(super) fn take(&self, device: &wgpu::Device) -> Option<anyhow::Result<()>> {
+        device.poll(wgpu::Maintain::Poll);
+        let mapped = self.0.lock().unwrap().take()?;
+        Some(mapped.map_err(anyhow::Error::from))
+    }
+}
+
+// Texture content read back on the CPU, as floats whatever its format.
+// Normalized formats are in [0, 1], values of sRGB formats are the stored ones, not linearized.
+pub struct TexturePixels {
+    pub width: u32,
+    pub height: u32,
+    pub format: wgpu::TextureFormat,
+    pub pixels: Vec<[f32; 4]>,
+}
+
+impl TexturePixels {
+    pub fn get(&self, x: u32, y: u32) -> Option<[f32; 4]> {
+        if x < self.width && y < self.height {
+            Some(self.pixels[(y * self.width + x) as usize])
+        } else {
+            None
+        }
+    }
+    // Formats storing values out of [0, 1].
+    pub fn is_hdr(&self) -> bool {
+        matches!(self.format, wgpu::TextureFormat::Rgba16Float | wgpu::TextureFormat::Rgba32Float | wgpu::TextureFormat::R32Float)
+    }
+}
+
+// Copy of a texture content to the CPU, which does not block. Works on web too.
+pub struct TextureReadback {
+    readback: ReadbackBuffer,
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+    mapping: Mapping,
+}
+
+impl TextureReadback {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture) -> anyhow::Result<Self> {
+        let format = texture.format();
+        let bytes_per_pixel = match format {
+            wgpu::TextureFormat::R8Unorm => 1,
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb
+            | wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+            | wgpu::TextureFormat::R32Float => 4,
+            wgpu::TextureFormat::Rgba16Float => 8,
+            wgpu::TextureFormat::Rgba32Float => 16,
+            format => anyhow::bail!(GfxError::Unsupported(format!("Readback of format {:?} is not supported", format))),
+        };
+        let readback = ReadbackBuffer::new(device, queue, texture, bytes_per_pixel);
+        let mapping = Mapping::new(&readback.buffer);
+        Ok(Self {
+            readback,
+            width: texture.width(),
+            height: texture.height(),
+            format,
+            mapping,
+        })
+    }
+    // Pixels of the texture if the copy is done.
+    pub fn try_read(&self, device: &wgpu::Device) -> Option<anyhow::Result<TexturePixels>> {
+        Some(self.mapping.take(device)?.map(|_| {
+            let bytes = self.readback.read();
+            TexturePixels {
+                width: self.width,
+                height: self.height,
+                format: self.format,
+                pixels: decode_pixels(self.format, &bytes),
+            }
+        }))
+    }
+}
+
+fn decode_pixels(format: wgpu::TextureFormat, bytes: &[u8]) -> Vec<[f32; 4]> {
+    let unorm = |value: u8| value as f32 / 255.0;
+    let float = |bytes: &[u8]| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    let half = |bytes: &[u8]| f16_to_f32(u16::from_le_bytes([bytes[0], bytes[1]]));
+    match format {
+        wgpu::TextureFormat::R8Unorm => bytes.iter().map(|r| [unorm(*r), 0.0, 0.0, 1.0]).collect(),
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => bytes.chunks_exact(4)
+            .map(|p| [unorm(p[2]), unorm(p[1]), unorm(p[0]), unorm(p[3])])
+            .collect(),
+        wgpu::TextureFormat::R32Float => bytes.chunks_exact(4).map(|p| [float(p), 0.0, 0.0, 1.0]).collect(),
+        wgpu::TextureFormat::Rgba16Float => bytes.chunks_exact(8)
+            .map(|p| [half(&p[0..2]), half(&p[2..4]), half(&p[4..6]), half(&p[6..8])])
+            .collect(),
+        wgpu::TextureFormat::Rgba32Float => bytes.chunks_exact(16)
+            .map(|p| [float(&p[0..4]), float(&p[4..8]), float(&p[8..12]), float(&p[12..16])])
+            .collect(),
+        _ => bytes.chunks_exact(4)
+            .map(|p| [unorm(p[0]), unorm(p[1]), unorm(p[2]), unorm(p[3])])
+            .collect(),
+    }
+}
+
+// IEEE 754 half precision float to single precision.
+fn f16_to_f32(half: u16) -> f32 {
+    let sign = if half & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((half >> 10) & 0x1f) as i32;
+    let mantissa = (half & 0x3ff) as f32;
+    match exponent {
+        0 => sign * mantissa * 2f32.powi(-24), // Subnormal
+        0x1f if mantissa == 0.0 => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => sign * (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+// Copy of a buffer content to the CPU, which does not block. Works on web too.
+pub struct BufferReadback {
+    buffer: wgpu::Buffer,
+    mapping: Mapping,
+}
+
+impl BufferReadback {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, source: &wgpu::Buffer) -> Self {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("ReadbackBuffer"),
+            size: source.size(),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("ReadbackEncoder"),
+        });
+        encoder.copy_buffer_to_buffer(source, 0, &buffer, 0, source.size());
+        queue.submit(iter::once(encoder.finish()));
+        let mapping = Mapping::new(&buffer);
+        Self {
+            buffer,
+            mapping,
+        }
+    }
+    // Bytes of the buffer if the copy is done.
+    pub fn try_read(&self, device: &wgpu::Device) -> Option<anyhow::Result<Vec<u8>>> {
+        Some(self.mapping.take(device)?.map(|_| {
+            let bytes = self.buffer.slice(..).get_mapped_range().to_vec();
+            self.buffer.unmap();
+            bytes
+        }))
+    }
+}
@@ -18,7 +18,9 @@ fn default_data<Desc: ResourceDescTrait + Default, Data : ResourceDataTrait<Desc
 pub struct Resource<Desc: ResourceDescTrait + Default, Data : ResourceDataTrait<Desc>> {
     pub(super) desc: Desc,
     #[cfg_attr(feature = "persistence", serde(skip, default="default_data"))] pub(super) data: Option<Data>,
-    #[cfg_attr(feature = "persistence", serde(skip))] pub(super) dirty: bool
+    #[cfg_attr(feature = "persistence", serde(skip))] pub(super) dirty: bool,
+    // Incremented each time data is rebuilt so that dependent resources can detect it.
+    #[cfg_attr(feature = "persistence", serde(skip))] pub(super) generation: u64,
 }
 
 /*#[derive(serde::Deserialize)]
@@ -50,6 +52,7 @@ where
             desc: Default::default(),
             data: None,
             dirty: false,
+            generation: 0,
         }
     }
 }
@@ -58,10 +61,20 @@ where
     Desc: ResourceDescTrait + Default,
     Data: ResourceDataTrait<Desc>,
 {
+    pub fn from_desc(desc: Desc) -> Self {
+        Self {
+            desc,
+            data: None,
+            dirty: false,
+            generation: 0,
+        }
+    }
     pub fn update_data(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) -> anyhow::Result<()> {
         if self.data.is_none() || self.dirty {
-            self.dirty = false;
+            // Keep dirty until creation succeed so that errors are reported until fixed.
             self.data = Some(Data::new(device, queue, &self.desc)?);
+            self.dirty = false;
+            self.generation += 1;
             Ok(())
         } else {
             Ok(())
@@ -74,14 +87,20 @@ where
             anyhow::bail!("No data")
         }
     }
+    pub fn get_generation(&self) -> u64 {
+        self.generation
+    }
     #[allow(dead_code)] // TODO remove ?
     pub fn visit_desc(&self, f: impl FnOnce(&Desc)) {
         f(&self.desc)
     }
-    pub fn visit_desc_mut(&mut self, f: impl FnOnce(&mut Desc) -> bool) {
-        if f(&mut self.desc) {
+    // Returns true if the description was changed by the visitor.
+    pub fn visit_desc_mut(&mut self, f: impl FnOnce(&mut Desc) -> bool) -> bool {
+        let changed = f(&mut self.desc);
+        if changed {
             self.dirty = true;
         }
+        changed
     }
     #[allow(dead_code)] // TODO remove ?
     pub fn visit_data(&self, f: impl FnOnce(&Data)) -> bool {
@@ -92,4 +111,19 @@ where
             false
         }
     }
-}
\ No newline at end of file
+}
+
+// Only the description is serialized, GPU data is kept unless it changed.
+#[cfg(feature = "persistence")]
+impl<Desc, Data> super::Restore for Resource<Desc, Data>
+where
+    Desc: ResourceDescTrait + Default + PartialEq,
+    Data: ResourceDataTrait<Desc>,
+{
+    fn restore(&mut self, snapshot: Self) {
+        if self.desc != snapshot.desc {
+            self.desc = snapshot.desc;
+            self.dirty = true;
+        }
+    }
+}
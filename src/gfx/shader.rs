@@ -1,32 +1,176 @@
-use super::resource::{Resource, ResourceDataTrait, ResourceDescTrait};
+use std::{borrow::Cow, fs, path::PathBuf};
 
-#[derive(Debug, Default)]
+use super::{resource::{Resource, ResourceDataTrait, ResourceDescTrait}, GfxError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
+pub enum ShaderStage {
+    Vertex,
+    Fragment,
+    Compute,
+}
+
+impl ShaderStage {
+    pub fn get_stage_name(&self) -> &str {
+        match self {
+            ShaderStage::Vertex => "Vertex",
+            ShaderStage::Fragment => "Fragment",
+            ShaderStage::Compute => "Compute",
+        }
+    }
+    fn to_naga(self) -> naga::ShaderStage {
+        match self {
+            ShaderStage::Vertex => naga::ShaderStage::Vertex,
+            ShaderStage::Fragment => naga::ShaderStage::Fragment,
+            ShaderStage::Compute => naga::ShaderStage::Compute,
+        }
+    }
+    // Entry point we look for first when a module has several entry points for the same stage.
+    fn default_entry_point(&self) -> &str {
+        match self {
+            ShaderStage::Vertex => "vs_main",
+            ShaderStage::Fragment => "fs_main",
+            ShaderStage::Compute => "cs_main",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
+pub enum ShaderLanguage {
+    Wgsl,
+    Glsl(ShaderStage), // GLSL module can only hold a single stage.
+    SpirV(PathBuf), // Binary module loaded from file.
+}
+
+impl Default for ShaderLanguage {
+    fn default() -> Self {
+        ShaderLanguage::Wgsl
+    }
+}
+
+impl ShaderLanguage {
+    pub fn get_language_name(&self) -> &str {
+        match self {
+            ShaderLanguage::Wgsl => "WGSL",
+            ShaderLanguage::Glsl(..) => "GLSL",
+            ShaderLanguage::SpirV(..) => "SPIR-V",
+        }
+    }
+    // Syntax used by the code editor highlighter. There is no WGSL syntax available, rust is close enough.
+    pub fn get_syntax_name(&self) -> &str {
+        match self {
+            ShaderLanguage::Wgsl => "rs",
+            ShaderLanguage::Glsl(..) => "c",
+            ShaderLanguage::SpirV(..) => "txt",
+        }
+    }
+    pub fn has_source(&self) -> bool {
+        !matches!(self, ShaderLanguage::SpirV(..))
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
 #[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
 pub struct ShaderDescription {
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub(crate) language: ShaderLanguage,
     pub(crate) shader: String,
 }
 #[derive(Debug)]
 pub struct ShaderData {
-    //shader: wgpu::ShaderModule,
+    module: naga::Module,
 }
 
 pub type Shader = Resource<ShaderDescription, ShaderData>;
 
 impl ResourceDescTrait for ShaderDescription {
-    
+
 }
 
 impl ResourceDataTrait<ShaderDescription> for ShaderData {
-    fn new(_device: &wgpu::Device, _queue: &wgpu::Queue, _desc: &ShaderDescription) -> anyhow::Result<Self> {
-        /*let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Shader"),
-            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(desc.shader.as_str())),
-        });*/
+    fn new(_device: &wgpu::Device, _queue: &wgpu::Queue, desc: &ShaderDescription) -> anyhow::Result<Self> {
+        // Every language goes through naga frontends so that we get a validated module & readable errors
+        // before reaching the pipeline creation.
+        let module = match &desc.language {
+            ShaderLanguage::Wgsl => {
+                naga::front::wgsl::parse_str(desc.shader.as_str()).map_err(|err| GfxError::ShaderCompilation {
+                    message: err.emit_to_string(desc.shader.as_str()),
+                    spans: err.labels().filter_map(|(span, _)| span.to_range()).collect(),
+                })?
+            }
+            ShaderLanguage::Glsl(stage) => {
+                let mut frontend = naga::front::glsl::Frontend::default();
+                frontend.parse(&naga::front::glsl::Options::from(stage.to_naga()), desc.shader.as_str())
+                    .map_err(|errors| GfxError::ShaderCompilation {
+                        message: format_glsl_errors(desc.shader.as_str(), &errors),
+                        spans: errors.iter().filter_map(|error| error.meta.to_range()).collect(),
+                    })?
+            }
+            ShaderLanguage::SpirV(path) => {
+                let bytes = fs::read(path)?;
+                naga::front::spv::parse_u8_slice(&bytes, &naga::front::spv::Options::default())?
+            }
+        };
+        let mut validator = naga::valid::Validator::new(naga::valid::ValidationFlags::all(), naga::valid::Capabilities::all());
+        if let Err(err) = validator.validate(&module) {
+            let message = if desc.language.has_source() {
+                err.emit_to_string(desc.shader.as_str())
+            } else {
+                err.to_string()
+            };
+            anyhow::bail!(GfxError::ShaderCompilation {
+                message,
+                spans: err.spans().filter_map(|(span, _)| span.to_range()).collect(),
+            })
+        }
         Ok(Self {
-            //shader, // This is unused...
+            module,
         })
     }
     fn record_data(&self, _device: &wgpu::Device, _cmd: &mut wgpu::CommandEncoder, _desc: &ShaderDescription) -> anyhow::Result<()> {
         Ok(()) // Nothing to do here
     }
-}
\ No newline at end of file
+}
+
+fn format_glsl_errors(source: &str, errors: &[naga::front::glsl::Error]) -> String {
+    errors.iter().map(|error| {
+        match error.meta.to_range() {
+            Some(range) => {
+                let location = error.meta.location(source);
+                format!("{}:{}: {} ({})", location.line_number, location.line_position, error.kind, &source[range])
+            }
+            None => error.kind.to_string()
+        }
+    }).collect::<Vec<_>>().join("\n")
+}
+
+impl Shader {
+    pub fn from_source(language: ShaderLanguage, shader: String) -> Self {
+        Self::from_desc(ShaderDescription { language, shader })
+    }
+    pub fn get_language(&self) -> &ShaderLanguage {
+        &self.desc.language
+    }
+    pub fn get_entry_point(&self, stage: ShaderStage) -> anyhow::Result<&str> {
+        if let Some(data) = &self.data {
+            let entry_points = data.module.entry_points.iter().filter(|entry_point| entry_point.stage == stage.to_naga()).collect::<Vec<_>>();
+            match entry_points.iter().find(|entry_point| entry_point.name == stage.default_entry_point()).or(entry_points.first()) {
+                Some(entry_point) => Ok(entry_point.name.as_str()),
+                None => anyhow::bail!("No {} entry point in shader", stage.get_stage_name())
+            }
+        } else {
+            anyhow::bail!("No data")
+        }
+    }
+    pub fn create_shader_module(&self, device: &wgpu::Device, label: &str) -> anyhow::Result<wgpu::ShaderModule> {
+        if let Some(data) = &self.data {
+            Ok(device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some(label),
+                source: wgpu::ShaderSource::Naga(Cow::Owned(data.module.clone())),
+            }))
+        } else {
+            anyhow::bail!("No data")
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use anyhow::Context;
+
+// Largest dimension an expression may resolve to, beyond it the expression is most likely wrong.
+const MAX_SIZE: f64 = 16384.0;
+
+// Size of a render target dimension computed from the backbuffer size, such as `bb.w / 2` or `min(bb.w, 1024)`.
+// Supports numbers, bb.w & bb.h, + - * / with parentheses, negation, min & max.
+#[derive(Debug, Clone, PartialEq)]
+enum Expression {
+    Number(f64),
+    Width,
+    Height,
+    Negate(Box<Expression>),
+    Binary(char, Box<Expression>, Box<Expression>),
+    Min(Vec<Expression>),
+    Max(Vec<Expression>),
+}
+
+impl Expression {
+    fn evaluate(&self, backbuffer_size: (u32, u32)) -> f64 {
+        match self {
+            Expression::Number(value) => *value,
+            Expression::Width => backbuffer_size.0 as f64,
+            Expression::Height => backbuffer_size.1 as f64,
+            Expression::Negate(value) => -value.evaluate(backbuffer_size),
+            Expression::Binary(operator, left, right) => {
+                let (left, right) = (left.evaluate(backbuffer_size), right.evaluate(backbuffer_size));
+                match operator {
+                    '+' => left + right,
+                    '-' => left - right,
+                    '*' => left * right,
+                    _ => left / right,
+                }
+            }
+            Expression::Min(values) => values.iter().map(|value| value.evaluate(backbuffer_size)).fold(f64::INFINITY, f64::min),
+            Expression::Max(values) => values.iter().map(|value| value.evaluate(backbuffer_size)).fold(f64::NEG_INFINITY, f64::max),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Symbol(char),
+}
+
+fn tokenize(expression: &str) -> anyhow::Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut rest = expression.trim_start();
+    while let Some(c) = rest.chars().next() {
+        let length = if c.is_ascii_digit() || c == '.' {
+            let length = rest.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(rest.len());
+            let number = &rest[..length];
+            tokens.push(Token::Number(number.parse().with_context(|| format!("Invalid number {}", number))?));
+            length
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            // Names include their dots, so that bb.w is a single token.
+            let length = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.')).unwrap_or(rest.len());
+            tokens.push(Token::Name(rest[..length].to_string()));
+            length
+        } else if "+-*/(),".contains(c) {
+            tokens.push(Token::Symbol(c));
+            1
+        } else {
+            anyhow::bail!("Unexpected character {:?}", c);
+        };
+        rest = rest[length..].trim_start();
+    }
+    Ok(tokens)
+}
+
+// Recursive descent parser, one method per precedence level.
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+    fn eat(&mut self, symbol: char) -> bool {
+        let found = self.peek() == Some(&Token::Symbol(symbol));
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+    fn expect(&mut self, symbol: char) -> anyhow::Result<()> {
+        if !self.eat(symbol) {
+            anyhow::bail!("Expected '{}'", symbol);
+        }
+        Ok(())
+    }
+    // sum := product (('+' | '-') product)*
+    fn sum(&mut self) -> anyhow::Result<Expression> {
+        let mut expression = self.product()?;
+        loop {
+            let operator = match self.peek() {
+                Some(Token::Symbol(operator)) if *operator == '+' || *operator == '-' => *operator,
+                _ => return Ok(expression),
+            };
+            self.position += 1;
+            expression = Expression::Binary(operator, Box::new(expression), Box::new(self.product()?));
+        }
+    }
+    // product := factor (('*' | '/') factor)*
+    fn product(&mut self) -> anyhow::Result<Expression> {
+        let mut expression = self.factor()?;
+        loop {
+            let operator = match self.peek() {
+                Some(Token::Symbol(operator)) if *operator == '*' || *operator == '/' => *operator,
+                _ => return Ok(expression),
+            };
+            self.position += 1;
+            expression = Expression::Binary(operator, Box::new(expression), Box::new(self.factor()?));
+        }
+    }
+    // factor := number | bb.w | bb.h | '-' factor | '(' sum ')' | function '(' sum (',' sum)* ')'
+    fn factor(&mut self) -> anyhow::Result<Expression> {
+        let token = self.peek().cloned().ok_or(anyhow::anyhow!("Unexpected end of expression"))?;
+        self.position += 1;
+        match token {
+            Token::Number(value) => Ok(Expression::Number(value)),
+            Token::Symbol('-') => Ok(Expression::Negate(Box::new(self.factor()?))),
+            Token::Symbol('(') => {
+                let expression = self.sum()?;
+                self.expect(')')?;
+                Ok(expression)
+            }
+            Token::Symbol(symbol) => anyhow::bail!("Unexpected '{}'", symbol),
+            Token::Name(name) => match name.as_str() {
+                "bb.w" => Ok(Expression::Width),
+                "bb.h" => Ok(Expression::Height),
+                "min" | "max" => {
+                    self.expect('(')?;
+                    let mut arguments = vec![self.sum()?];
+                    while self.eat(',') {
+                        arguments.push(self.sum()?);
+                    }
+                    self.expect(')')?;
+                    Ok(if name == "min" { Expression::Min(arguments) } else { Expression::Max(arguments) })
+                }
+                _ => anyhow::bail!("Unknown name {}, expected bb.w, bb.h, min or max", name),
+            },
+        }
+    }
+}
+
+fn parse(expression: &str) -> anyhow::Result<Expression> {
+    let mut parser = Parser {
+        tokens: tokenize(expression)?,
+        position: 0,
+    };
+    let parsed = parser.sum()?;
+    if let Some(token) = parser.peek() {
+        anyhow::bail!("Unexpected {:?} after expression", token);
+    }
+    Ok(parsed)
+}
+
+// Whether the expression is valid, whatever the backbuffer size.
+pub fn check_size_expression(expression: &str) -> anyhow::Result<()> {
+    parse(expression).map(|_| ())
+}
+
+// Size in pixels, rounded down.
+pub fn evaluate_size_expression(expression: &str, backbuffer_size: (u32, u32)) -> anyhow::Result<u32> {
+    let value = parse(expression).with_context(|| format!("Invalid size expression {:?}", expression))?.evaluate(backbuffer_size);
+    if !(0.0..=MAX_SIZE).contains(&value) {
+        anyhow::bail!("Size expression {:?} gives {} for a {}x{} backbuffer", expression, value, backbuffer_size.0, backbuffer_size.1);
+    }
+    Ok(value as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BACKBUFFER: (u32, u32) = (1280, 720);
+
+    #[test]
+    fn backbuffer_dimensions() {
+        assert_eq!(evaluate_size_expression("bb.w", BACKBUFFER).unwrap(), 1280);
+        assert_eq!(evaluate_size_expression(" bb.h ", BACKBUFFER).unwrap(), 720);
+        assert_eq!(evaluate_size_expression("bb.w/2", BACKBUFFER).unwrap(), 640);
+        assert_eq!(evaluate_size_expression("bb.h / 7", BACKBUFFER).unwrap(), 102);
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(evaluate_size_expression("256 + bb.w / 4 * 2", BACKBUFFER).unwrap(), 896);
+        assert_eq!(evaluate_size_expression("(256 + bb.w) / 4", BACKBUFFER).unwrap(), 384);
+        assert_eq!(evaluate_size_expression("bb.w - bb.h - 60", BACKBUFFER).unwrap(), 500);
+        assert_eq!(evaluate_size_expression("-bb.h + bb.w", BACKBUFFER).unwrap(), 560);
+    }
+
+    #[test]
+    fn functions() {
+        assert_eq!(evaluate_size_expression("min(bb.w,1024)", BACKBUFFER).unwrap(), 1024);
+        assert_eq!(evaluate_size_expression("min(bb.h, 1024)", BACKBUFFER).unwrap(), 720);
+        assert_eq!(evaluate_size_expression("max(bb.w, bb.h, 2048) / 2", BACKBUFFER).unwrap(), 1024);
+    }
+
+    #[test]
+    fn invalid_expressions() {
+        for expression in ["", "bb.w /", "bb.x", "min bb.w", "min(bb.w", "bb.w)", "bb.w bb.h", "1..2", "bb.w % 2"] {
+            assert!(check_size_expression(expression).is_err(), "{:?} should not parse", expression);
+        }
+        assert!(evaluate_size_expression("bb.w / 0", BACKBUFFER).is_err());
+        assert!(evaluate_size_expression("bb.h - bb.w", BACKBUFFER).is_err());
+        assert!(evaluate_size_expression("bb.w * bb.h", BACKBUFFER).is_err());
+    }
+}
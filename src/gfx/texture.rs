@@ -1,16 +1,47 @@
-use std::{fs, path::PathBuf};
+use std::path::PathBuf;
 
 use image::GenericImageView;
 use anyhow::*;
 
-use super::resource::{ResourceDataTrait, ResourceDescTrait, Resource};
+use super::{blit::Blit, resource::{ResourceDataTrait, ResourceDescTrait, Resource}, GfxError};
 
 #[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
 enum TextureSource {
     None,
-    Bytes(Vec<u8>),
-    Path(PathBuf)
+    Bytes(#[cfg_attr(feature = "persistence", serde(with = "base64_bytes"))] Vec<u8>),
+    Path(PathBuf),
+    File {
+        name: String,
+        #[cfg_attr(feature = "persistence", serde(with = "base64_bytes"))]
+        bytes: Vec<u8>,
+    }, // Encoded image embedded in the project.
+}
+
+// Bytes are written as a base64 string rather than an array of numbers, which is several times larger.
+// Arrays written by version 1 projects are still read.
+#[cfg(feature = "persistence")]
+mod base64_bytes {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Bytes {
+        Encoded(String),
+        Array(Vec<u8>),
+    }
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(STANDARD.encode(bytes).as_str())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        match Bytes::deserialize(deserializer)? {
+            Bytes::Encoded(encoded) => STANDARD.decode(encoded.as_str()).map_err(serde::de::Error::custom),
+            Bytes::Array(bytes) => Ok(bytes),
+        }
+    }
 }
 impl Default for TextureSource {
     fn default() -> Self {
@@ -18,7 +49,7 @@ impl Default for TextureSource {
     }
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq)]
 #[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
 pub struct TextureDescription {
     source: TextureSource,
@@ -33,6 +64,7 @@ pub struct TextureData {
     texture: wgpu::Texture,
     view: wgpu::TextureView,
     sampler: wgpu::Sampler,
+    blit: std::cell::OnceCell<Blit>, // Drawing other textures into this one, created on first use.
 }
 
 pub type Texture = Resource<TextureDescription, TextureData>;
@@ -53,10 +85,18 @@ impl ResourceDataTrait<TextureDescription> for TextureData {
                 assert!((desc.width * desc.height * 4) as usize == bytes.len());
                 desc.clone()
             }
+            #[cfg(not(target_arch = "wasm32"))]
             TextureSource::Path(path) => {
-                let bytes = fs::read(path)?;
+                let bytes = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
                 TextureDescription::from_bytes(&bytes, "", false)?
             }
+            #[cfg(target_arch = "wasm32")]
+            TextureSource::Path(path) => {
+                anyhow::bail!(GfxError::Unsupported(format!("Reading {} is not supported on web, upload the file instead", path.display())))
+            }
+            TextureSource::File { name, bytes } => {
+                TextureDescription::from_bytes(bytes, name, false).with_context(|| format!("Failed to decode {}", name))?
+            }
         };
         let size = wgpu::Extent3d {
             width: desc_from_src.width,
@@ -98,13 +138,14 @@ impl ResourceDataTrait<TextureDescription> for TextureData {
             Some(err)
         });
         if validation.is_some() {
-            anyhow::bail!(validation.unwrap().to_string())
+            anyhow::bail!(GfxError::Validation(validation.unwrap().to_string()))
         } else {
             if let TextureSource::None = desc.source {
                 Ok(Self {
                     texture,
                     view,
                     sampler,
+                    blit: Default::default(),
                 })
             } else {
                 let size = wgpu::Extent3d {
@@ -134,12 +175,12 @@ impl ResourceDataTrait<TextureDescription> for TextureData {
                     texture,
                     view,
                     sampler,
+                    blit: Default::default(),
                 })
             }
         }
     }
     fn record_data(&self, _device: &wgpu::Device, _cmd: &mut wgpu::CommandEncoder, _desc: &TextureDescription) -> anyhow::Result<()> {
-        let _ = self.sampler;
         Ok(()) // Not recordable...
     }
 }
@@ -159,6 +200,25 @@ impl Texture {
             Err(anyhow!("No data"))
         }
     }
+    pub fn get_sampler_handle(&self) -> anyhow::Result<&wgpu::Sampler> {
+        if self.data.is_some() {
+            Ok(&self.data.as_ref().unwrap().sampler)
+        } else {
+            Err(anyhow!("No data"))
+        }
+    }
+    // Size of the created texture, which might differ from description for file textures.
+    pub fn get_size(&self) -> anyhow::Result<(u32, u32)> {
+        let texture = self.get_handle()?;
+        Ok((texture.width(), texture.height()))
+    }
+    // Draw source over this texture, resampling it if their sizes differ.
+    pub fn record_blit_from(&self, device: &wgpu::Device, cmd: &mut wgpu::CommandEncoder, source: &Texture) -> anyhow::Result<()> {
+        let data = self.data.as_ref().ok_or(anyhow!("No data"))?;
+        let blit = data.blit.get_or_init(|| Blit::new(device, data.texture.format()));
+        blit.record(device, cmd, source.get_view_handle()?, &data.view);
+        Ok(())
+    }
     pub fn set_size(&mut self, width: u32, height: u32) {
         self.set_width(width);
         self.set_height(height);
@@ -182,6 +242,25 @@ impl Texture {
             self.dirty = true;
         }
     }
+    pub fn set_file(&mut self, name: String, bytes: Vec<u8>) {
+        let src = TextureSource::File { name, bytes };
+        if self.desc.source != src {
+            self.desc.source = src;
+            self.dirty = true;
+        }
+    }
+    pub fn clear_file(&mut self) {
+        if let TextureSource::File { .. } = self.desc.source {
+            self.desc.source = TextureSource::None;
+            self.dirty = true;
+        }
+    }
+    pub fn get_file_name(&self) -> Option<&str> {
+        match &self.desc.source {
+            TextureSource::File { name, .. } => Some(name.as_str()),
+            _ => None,
+        }
+    }
     #[allow(dead_code)] // TODO remove ?
     pub fn set_bytes(&mut self, bytes: Vec<u8>) {
         let src = TextureSource::Bytes(bytes);
@@ -225,7 +304,6 @@ impl TextureDescription {
             srgb,
         })
     }
-    #[allow(dead_code)] // TODO remove ?
     pub fn default_black_texture() -> Result<Self> {
         // TODO cache output.
         let rgba = vec![0, 0, 0, 255];
@@ -256,4 +334,22 @@ impl TextureDescription {
         let dimensions = (1, 1);
         Self::from_raw_memory(&rgba[..], dimensions, "DefaultNormalTexture".into(), false)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bytes_are_base64() {
+        let source = TextureSource::File { name: String::from("pixel.png"), bytes: vec![0, 127, 255] };
+        let json = serde_json::to_value(&source).unwrap();
+        assert_eq!(json, serde_json::json!({ "File": { "name": "pixel.png", "bytes": "AH//" } }));
+        assert_eq!(serde_json::from_value::<TextureSource>(json).unwrap(), source);
+    }
+
+    #[test]
+    fn version_1_byte_arrays_are_read() {
+        let json = serde_json::json!({ "Bytes": [0, 127, 255] });
+        assert_eq!(serde_json::from_value::<TextureSource>(json).unwrap(), TextureSource::Bytes(vec![0, 127, 255]));
+    }
+}
@@ -0,0 +1,57 @@
+use egui_node_graph::{InputId, NodeId, NodeTemplateTrait, OutputId};
+
+use super::{ProtosEditorState, ProtosGraphState, ProtosNodeTemplate, ProtosValueType};
+
+// Add a node at the given position of the graph, as the node finder does.
+pub fn add_node(state: &mut ProtosEditorState, user_state: &mut ProtosGraphState, template: &ProtosNodeTemplate, position: egui::Pos2) -> NodeId {
+    let label = template.node_graph_label(user_state);
+    let user_data = template.user_data(user_state);
+    let node_id = state.graph.add_node(label, user_data, |graph, node_id| {
+        template.build_node(graph, user_state, node_id)
+    });
+    state.node_positions.insert(node_id, position);
+    state.node_order.push(node_id);
+    node_id
+}
+
+/// Build a graph from code, as the editor would do, for importers & tests.
+#[derive(Default)]
+pub struct GraphBuilder {
+    state: ProtosEditorState,
+    user_state: ProtosGraphState,
+}
+
+impl GraphBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    // Add a node, placed on a grid for readability once opened in the editor.
+    pub fn add_node(&mut self, template: ProtosNodeTemplate, column: usize, row: usize) -> NodeId {
+        let position = egui::pos2(50.0 + 300.0 * column as f32, 50.0 + 250.0 * row as f32);
+        add_node(&mut self.state, &mut self.user_state, &template, position)
+    }
+    pub fn output(&self, node_id: NodeId, name: &str) -> anyhow::Result<OutputId> {
+        Ok(self.state.graph[node_id].get_output(name)?)
+    }
+    pub fn input(&self, node_id: NodeId, name: &str) -> anyhow::Result<InputId> {
+        Ok(self.state.graph[node_id].get_input(name)?)
+    }
+    pub fn connect(&mut self, output_node: NodeId, output_name: &str, input_node: NodeId, input_name: &str) -> anyhow::Result<()> {
+        let output = self.output(output_node, output_name)?;
+        let input = self.input(input_node, input_name)?;
+        self.state.graph.add_connection(output, input);
+        Ok(())
+    }
+    // Set the inline value of an input, used when nothing is connected.
+    pub fn set_value(&mut self, node_id: NodeId, input_name: &str, value: ProtosValueType) -> anyhow::Result<()> {
+        let input = self.input(node_id, input_name)?;
+        self.state.graph[input].value = value;
+        Ok(())
+    }
+    pub fn set_backbuffer(&mut self, node_id: NodeId) {
+        self.user_state.backbuffer_node = Some(node_id);
+    }
+    pub fn build(self) -> (ProtosEditorState, ProtosGraphState) {
+        (self.state, self.user_state)
+    }
+}
@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+
+use egui_node_graph::NodeId;
+
+use crate::gfx;
+
+use super::{subgraph::copy_node, ProtosEditorState, ProtosNodeTemplate};
+
+// Prefix of copied nodes, to tell them apart from any other text pasted in the graph.
+pub const NODES_CLIPBOARD_HEADER: &str = "protos-nodes:";
+// Offset of duplicated nodes, so that they do not hide the original ones.
+const DUPLICATE_OFFSET: egui::Vec2 = egui::vec2(30.0, 30.0);
+
+// Serialize the given nodes & the connections between them as text, along with their resources.
+pub fn copy_nodes(state: &ProtosEditorState, node_ids: &[NodeId]) -> anyhow::Result<String> {
+    // Group nodes belong to their subgraph.
+    let node_ids: Vec<NodeId> = node_ids.iter()
+        .copied()
+        .filter(|node_id| !matches!(state.graph[*node_id].user_data.template, ProtosNodeTemplate::GroupInput(_) | ProtosNodeTemplate::GroupOutput(_)))
+        .collect();
+    if node_ids.is_empty() {
+        anyhow::bail!("Select the nodes to copy first");
+    }
+    let mut copy = ProtosEditorState::default();
+    let mut copies = HashMap::new();
+    for node_id in &node_ids {
+        let copied = copy_node(&state.graph, *node_id, &mut copy.graph);
+        let position = state.node_positions.get(*node_id).copied().unwrap_or_default();
+        copy.node_positions.insert(copied.node_id, position);
+        copy.node_order.push(copied.node_id);
+        copies.insert(*node_id, copied);
+    }
+    for (input_id, output_id) in state.graph.iter_connections() {
+        let input_node = state.graph[input_id].node;
+        let output_node = state.graph[output_id].node;
+        if let (Some(input_copy), Some(output_copy)) = (copies.get(&input_node), copies.get(&output_node)) {
+            copy.graph.add_connection(output_copy.outputs[&output_id], input_copy.inputs[&input_id]);
+        }
+    }
+    let json = gfx::with_resource_scope(|| serde_json::to_string(&copy))?;
+    Ok(format!("{}{}", NODES_CLIPBOARD_HEADER, json))
+}
+
+// Add copied nodes to the graph & select them. Resources are deserialized in new handles, so that copies do not share anything.
+// Nodes are placed at the given position if any, next to the copied ones otherwise.
+pub fn paste_nodes(state: &mut ProtosEditorState, text: &str, position: Option<egui::Pos2>) -> anyhow::Result<Vec<NodeId>> {
+    let json = text.strip_prefix(NODES_CLIPBOARD_HEADER).ok_or(anyhow::anyhow!("Clipboard does not hold nodes"))?;
+    let copy: ProtosEditorState = gfx::with_resource_scope(|| serde_json::from_str(json))?;
+    let min = copy.node_positions.values().fold(egui::pos2(f32::INFINITY, f32::INFINITY), |min, position| min.min(*position));
+    let offset = match position {
+        Some(position) if min.is_finite() => position - min,
+        _ => DUPLICATE_OFFSET,
+    };
+    let mut copies = HashMap::new();
+    for node_id in copy.node_order.iter().copied() {
+        let copied = copy_node(&copy.graph, node_id, &mut state.graph);
+        let position = copy.node_positions.get(node_id).copied().unwrap_or_default();
+        state.node_positions.insert(copied.node_id, position + offset);
+        state.node_order.push(copied.node_id);
+        copies.insert(node_id, copied);
+    }
+    for (input_id, output_id) in copy.graph.iter_connections() {
+        let input_copy = &copies[&copy.graph[input_id].node];
+        let output_copy = &copies[&copy.graph[output_id].node];
+        state.graph.add_connection(output_copy.outputs[&output_id], input_copy.inputs[&input_id]);
+    }
+    let pasted: Vec<NodeId> = copy.node_order.iter().map(|node_id| copies[node_id].node_id).collect();
+    state.selected_nodes = pasted.clone();
+    Ok(pasted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{gfx::{ResourceHandle, ShaderLanguage}, graph::{nodes::{BackbufferPassNode, GraphicPassNode, ShaderNode}, GraphBuilder, ProtosGraph}};
+
+    fn backbuffer_handle(graph: &ProtosGraph, node_id: NodeId) -> ResourceHandle<crate::gfx::BackbufferPass> {
+        match &graph[node_id].user_data.template {
+            ProtosNodeTemplate::BackbufferPass(node) => node.handle.clone(),
+            _ => panic!("Not a backbuffer pass"),
+        }
+    }
+
+    #[test]
+    fn paste_keeps_internal_connections() {
+        let mut builder = GraphBuilder::new();
+        let shader = builder.add_node(ProtosNodeTemplate::Shader(ShaderNode::new(ShaderLanguage::Wgsl, String::new())), 0, 0);
+        let pass = builder.add_node(ProtosNodeTemplate::GraphicPass(GraphicPassNode::default()), 1, 0);
+        let backbuffer = builder.add_node(ProtosNodeTemplate::BackbufferPass(BackbufferPassNode::default()), 2, 0);
+        builder.connect(shader, "VertexShader", pass, "VertexShader").unwrap();
+        builder.connect(pass, "RT0", backbuffer, "input").unwrap();
+        let (mut state, _) = builder.build();
+
+        // The shader is not copied, its connection is dropped.
+        let text = copy_nodes(&state, &[pass, backbuffer]).unwrap();
+        let pasted = paste_nodes(&mut state, text.as_str(), None).unwrap();
+        assert_eq!(pasted.len(), 2);
+        assert_eq!(state.selected_nodes, pasted);
+        let (pass_copy, backbuffer_copy) = (pasted[0], pasted[1]);
+        let graph = &state.graph;
+        assert!(matches!(graph[pass_copy].user_data.template, ProtosNodeTemplate::GraphicPass(_)));
+        assert_eq!(graph.connection(graph[backbuffer_copy].get_input("input").unwrap()), Some(graph[pass_copy].get_output("RT0").unwrap()));
+        assert_eq!(graph.connection(graph[pass_copy].get_input("VertexShader").unwrap()), None);
+        // Originals are untouched.
+        assert_eq!(graph.connection(graph[backbuffer].get_input("input").unwrap()), Some(graph[pass].get_output("RT0").unwrap()));
+        assert!(!ResourceHandle::ptr_eq(&backbuffer_handle(graph, backbuffer), &backbuffer_handle(graph, backbuffer_copy)));
+        assert_eq!(state.node_positions.get(backbuffer_copy).copied(), state.node_positions.get(backbuffer).map(|position| *position + DUPLICATE_OFFSET));
+    }
+}
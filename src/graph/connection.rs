@@ -1,13 +1,13 @@
 use std::borrow::Cow;
 
 use egui::DragValue;
-use egui_node_graph::{DataTypeTrait, NodeId, WidgetValueTrait};
+use egui_node_graph::{DataTypeTrait, InputParamKind, NodeId, WidgetValueTrait};
 use crate::gfx::{self, ResourceHandle};
 
-use super::{ProtosResponse, ProtosGraphState, ProtosNodeData};
+use super::{ProtosError, ProtosResponse, ProtosGraphState, ProtosNodeData};
 
 // TODO: could we work out with no DataType ? By using ValueType in place ?
-#[derive(PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
 pub enum ProtosDataType {
     // gpu node
@@ -78,47 +78,84 @@ impl DataTypeTrait<ProtosGraphState> for ProtosDataType {
     }
 }
 
+impl ProtosDataType {
+    // Value of an input of this type with nothing connected.
+    pub fn default_value(&self) -> ProtosValueType {
+        match self {
+            ProtosDataType::Unknown => ProtosValueType::Unknown,
+            ProtosDataType::Texture => ProtosValueType::Texture(None),
+            ProtosDataType::Buffer => ProtosValueType::Buffer(None),
+            ProtosDataType::Mesh => ProtosValueType::Mesh(None),
+            ProtosDataType::Shader => ProtosValueType::Shader(None),
+            ProtosDataType::Scalar => ProtosValueType::Scalar(0.0),
+            ProtosDataType::Vec2 => ProtosValueType::Vec2([0.0; 2]),
+            ProtosDataType::Vec3 => ProtosValueType::Vec3([0.0; 3]),
+            ProtosDataType::String => ProtosValueType::String(String::new()),
+        }
+    }
+    // Resources can only come from other nodes.
+    pub fn input_kind(&self) -> InputParamKind {
+        match self {
+            ProtosDataType::Unknown | ProtosDataType::Texture | ProtosDataType::Buffer | ProtosDataType::Mesh | ProtosDataType::Shader => InputParamKind::ConnectionOnly,
+            ProtosDataType::Scalar | ProtosDataType::Vec2 | ProtosDataType::Vec3 | ProtosDataType::String => InputParamKind::ConnectionOrConstant,
+        }
+    }
+}
+
 impl ProtosValueType {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            ProtosValueType::Unknown => "unknown",
+            ProtosValueType::Texture(_) => "texture",
+            ProtosValueType::Buffer(_) => "buffer",
+            ProtosValueType::Mesh(_) => "mesh",
+            ProtosValueType::Shader(_) => "shader",
+            ProtosValueType::Scalar(_) => "scalar",
+            ProtosValueType::Vec2(_) => "vec2",
+            ProtosValueType::Vec3(_) => "vec3",
+            ProtosValueType::String(_) => "string",
+        }
+    }
     pub fn try_to_texture(self) -> anyhow::Result<Option<ResourceHandle<gfx::Texture>>> {
         if let ProtosValueType::Texture(value) = self {
             Ok(value)
         } else {
-            anyhow::bail!("Invalid cast to texture")
+            anyhow::bail!(ProtosError::type_mismatch("texture", self.type_name()))
         }
     }
     pub fn try_to_geometry(self) -> anyhow::Result<Option<ResourceHandle<gfx::Mesh>>> {
         if let ProtosValueType::Mesh(value) = self {
             Ok(value)
         } else {
-            anyhow::bail!("Invalid cast to mesh")
+            anyhow::bail!(ProtosError::type_mismatch("mesh", self.type_name()))
         }
     }
     pub fn try_to_shader(self) -> anyhow::Result<Option<ResourceHandle<gfx::Shader>>> {
         if let ProtosValueType::Shader(value) = self {
             Ok(value)
         } else {
-            anyhow::bail!("Invalid cast to scalar")
+            anyhow::bail!(ProtosError::type_mismatch("shader", self.type_name()))
         }
     }
     pub fn try_to_scalar(self) -> anyhow::Result<f32> {
         if let ProtosValueType::Scalar(value) = self {
             Ok(value)
         } else {
-            anyhow::bail!("Invalid cast to scalar")
+            anyhow::bail!(ProtosError::type_mismatch("scalar", self.type_name()))
         }
     }
     pub fn try_to_vec2(self) -> anyhow::Result<[f32;2]> {
         if let ProtosValueType::Vec2(value) = self {
             Ok(value)
         } else {
-            anyhow::bail!("Invalid cast to scalar")
+            anyhow::bail!(ProtosError::type_mismatch("vec2", self.type_name()))
         }
     }
     pub fn try_to_string(self) -> anyhow::Result<String> {
         if let ProtosValueType::String(value) = self {
             Ok(value)
         } else {
-            anyhow::bail!("Invalid cast to scalar")
+            anyhow::bail!(ProtosError::type_mismatch("string", self.type_name()))
         }
     }
 }
@@ -130,13 +167,14 @@ impl WidgetValueTrait for ProtosValueType {
     fn value_widget(
         &mut self,
         param_name: &str,
-        _node_id: NodeId,
+        node_id: NodeId,
         ui: &mut egui::Ui,
         _user_state: &mut ProtosGraphState,
         _node_data: &ProtosNodeData,
     ) -> Vec<ProtosResponse> {
         // This trait is used to tell the library which UI to display for the
         // inline parameter widgets.
+        let mut changed = false;
         match self {
             ProtosValueType::Texture(value) => {
                 let _ = value;
@@ -157,32 +195,36 @@ impl WidgetValueTrait for ProtosValueType {
             ProtosValueType::Scalar(value) => {
                 ui.horizontal(|ui| {
                     ui.label(param_name);
-                    ui.add(DragValue::new(value));
+                    changed |= ui.add(DragValue::new(value)).changed();
                 });
             }
             ProtosValueType::Vec2(value) => {
                 ui.horizontal(|ui| {
                     ui.label(param_name);
-                    ui.add(DragValue::new(&mut value[0]));
-                    ui.add(DragValue::new(&mut value[1]));
+                    changed |= ui.add(DragValue::new(&mut value[0])).changed();
+                    changed |= ui.add(DragValue::new(&mut value[1])).changed();
                 });
             }
             ProtosValueType::Vec3(value) => {
                 ui.horizontal(|ui| {
                     ui.label(param_name);
-                    ui.add(DragValue::new(&mut value[0]));
-                    ui.add(DragValue::new(&mut value[1]));
-                    ui.add(DragValue::new(&mut value[2]));
+                    changed |= ui.add(DragValue::new(&mut value[0])).changed();
+                    changed |= ui.add(DragValue::new(&mut value[1])).changed();
+                    changed |= ui.add(DragValue::new(&mut value[2])).changed();
                 });
             }
             ProtosValueType::String(value)  => {
-                ui.add(egui::TextEdit::singleline(value));
+                changed |= ui.add(egui::TextEdit::singleline(value)).changed();
             }
             _  => {
                 ui.label("Unknown");
             }
         }
         // This allows you to return your responses from the inline widgets.
-        Vec::new()
+        if changed {
+            vec![ProtosResponse::ParameterChanged(node_id)]
+        } else {
+            Vec::new()
+        }
     }
 }
\ No newline at end of file
@@ -11,12 +11,39 @@ pub struct ProtosNodeData {
     pub(crate) template: ProtosNodeTemplate,
 }
 
-pub struct ProtosCategoryType {
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ProtosCategoryType {
+    Passes,
+    Resources,
+    Geometry,
+    Shaders,
+    Math,
+    Inputs,
+    Utility,
+}
 
+impl ProtosCategoryType {
+    pub const ALL: [ProtosCategoryType; 7] = [
+        ProtosCategoryType::Passes,
+        ProtosCategoryType::Resources,
+        ProtosCategoryType::Geometry,
+        ProtosCategoryType::Shaders,
+        ProtosCategoryType::Math,
+        ProtosCategoryType::Inputs,
+        ProtosCategoryType::Utility,
+    ];
 }
 
 impl CategoryTrait for ProtosCategoryType {
     fn name(&self) -> String {
-        String::from("Hello there")
+        String::from(match self {
+            ProtosCategoryType::Passes => "Passes",
+            ProtosCategoryType::Resources => "Resources",
+            ProtosCategoryType::Geometry => "Geometry",
+            ProtosCategoryType::Shaders => "Shaders",
+            ProtosCategoryType::Math => "Math",
+            ProtosCategoryType::Inputs => "Inputs",
+            ProtosCategoryType::Utility => "Utility",
+        })
     }
 }
\ No newline at end of file
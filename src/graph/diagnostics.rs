@@ -0,0 +1,74 @@
+use std::collections::HashSet;
+
+use egui_node_graph::NodeId;
+
+use super::{core::ProtosGraph, subgraph::FlatGraph};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub path: Vec<NodeId>, // Subgraph nodes from the top level graph, then the node. Empty if the whole graph is concerned.
+    pub severity: Severity,
+    pub message: String,
+}
+
+// Problems found by the last evaluation of the graph.
+#[derive(Default)]
+pub struct Diagnostics {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn clear(&mut self) {
+        self.diagnostics.clear();
+    }
+    pub fn push(&mut self, path: Vec<NodeId>, severity: Severity, message: String) {
+        self.diagnostics.push(Diagnostic { path, severity, message });
+    }
+    // Report an error of the flattened graph on the node which raised it.
+    pub fn push_error(&mut self, flat_graph: &FlatGraph, err: &anyhow::Error) {
+        let error = flat_graph.locate_error(err);
+        self.push(error.path(), Severity::Error, error.to_string());
+    }
+    // Warn about nodes of the flattened graph which do not contribute to the given node.
+    pub fn push_unused(&mut self, flat_graph: &FlatGraph, node_id: NodeId) {
+        let used = used_nodes(&flat_graph.graph, node_id);
+        for node_id in flat_graph.graph.iter_nodes().filter(|node_id| !used.contains(node_id)) {
+            if let Some(path) = flat_graph.origin(node_id) {
+                self.push(path.to_vec(), Severity::Warning, String::from("Not used to render the active backbuffer"));
+            }
+        }
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics.iter()
+    }
+    // Diagnostics of the node at the given path, including the ones of their content for subgraph nodes.
+    pub fn of_node<'a>(&'a self, path: &'a [NodeId]) -> impl Iterator<Item = &'a Diagnostic> {
+        self.diagnostics.iter().filter(move |diagnostic| diagnostic.path.starts_with(path))
+    }
+    pub fn severity(&self, path: &[NodeId]) -> Option<Severity> {
+        self.of_node(path).map(|diagnostic| diagnostic.severity).max()
+    }
+}
+
+// Nodes the given node depends on, including itself.
+fn used_nodes(graph: &ProtosGraph, node_id: NodeId) -> HashSet<NodeId> {
+    let mut used = HashSet::new();
+    let mut stack = vec![node_id];
+    while let Some(node_id) = stack.pop() {
+        if !used.insert(node_id) {
+            continue;
+        }
+        for (_, input_id) in &graph[node_id].inputs {
+            if let Some(output_id) = graph.connection(*input_id) {
+                stack.push(graph[output_id].node);
+            }
+        }
+    }
+    used
+}
@@ -0,0 +1,107 @@
+use std::{fmt, ops::Range};
+
+use egui_node_graph::NodeId;
+
+use crate::gfx::GfxError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProtosErrorKind {
+    MissingInput, // Nothing is connected to a required input.
+    TypeMismatch { expected: String, found: String },
+    ShaderCompilation { message: String, spans: Vec<Range<usize>> }, // Spans are ranges of the shader source.
+    GpuValidation(String),
+    Io(String),
+    Unsupported(String),
+    Other(String),
+}
+
+// Error raised while evaluating or recording the graph, with the node & port it came from when known.
+#[derive(Debug, Clone)]
+pub struct ProtosError {
+    pub kind: ProtosErrorKind,
+    pub node: Option<NodeId>,
+    pub subgraphs: Vec<NodeId>, // Subgraph nodes containing the node, from the top level graph.
+    pub port: Option<String>,
+}
+
+impl ProtosError {
+    pub fn new(kind: ProtosErrorKind) -> Self {
+        Self {
+            kind,
+            node: None,
+            subgraphs: Vec::new(),
+            port: None,
+        }
+    }
+    pub fn missing_input(port: impl ToString) -> Self {
+        Self::new(ProtosErrorKind::MissingInput).with_port(port)
+    }
+    pub fn type_mismatch(expected: impl ToString, found: impl ToString) -> Self {
+        Self::new(ProtosErrorKind::TypeMismatch { expected: expected.to_string(), found: found.to_string() })
+    }
+    pub fn with_port(mut self, port: impl ToString) -> Self {
+        self.port = Some(port.to_string());
+        self
+    }
+    // Subgraph nodes & the node itself, from the top level graph.
+    pub fn path(&self) -> Vec<NodeId> {
+        self.subgraphs.iter().copied().chain(self.node).collect()
+    }
+    // Convert any error to a protos error, keeping its kind when it is known.
+    pub fn from_error(err: &anyhow::Error) -> Self {
+        if let Some(error) = err.downcast_ref::<ProtosError>() {
+            return error.clone();
+        }
+        let kind = match err.chain().find_map(|cause| cause.downcast_ref::<GfxError>()) {
+            Some(GfxError::ShaderCompilation { message, spans }) => ProtosErrorKind::ShaderCompilation { message: message.clone(), spans: spans.clone() },
+            Some(GfxError::Validation(message)) => ProtosErrorKind::GpuValidation(message.clone()),
+            Some(GfxError::Unsupported(message)) => ProtosErrorKind::Unsupported(message.clone()),
+            None if err.chain().any(|cause| cause.is::<std::io::Error>()) => ProtosErrorKind::Io(format!("{:#}", err)),
+            None => ProtosErrorKind::Other(format!("{:#}", err)),
+        };
+        Self::new(kind)
+    }
+}
+
+impl fmt::Display for ProtosErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProtosErrorKind::MissingInput => write!(f, "Nothing is connected"),
+            ProtosErrorKind::TypeMismatch { expected, found } => write!(f, "Expected {}, found {}", expected, found),
+            ProtosErrorKind::ShaderCompilation { message, .. } => write!(f, "Shader failed to compile : {}", message),
+            ProtosErrorKind::GpuValidation(message) => write!(f, "Validation error : {}", message),
+            ProtosErrorKind::Io(message) => write!(f, "{}", message),
+            ProtosErrorKind::Unsupported(message) => write!(f, "Unsupported : {}", message),
+            ProtosErrorKind::Other(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl fmt::Display for ProtosError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.port {
+            Some(port) => write!(f, "{} : {}", port, self.kind),
+            None => write!(f, "{}", self.kind),
+        }
+    }
+}
+
+impl std::error::Error for ProtosError {}
+
+// Attach the error to the node, unless it was raised by a node it depends on.
+pub fn node_error(err: anyhow::Error, node_id: NodeId) -> anyhow::Error {
+    let mut error = ProtosError::from_error(&err);
+    if error.node.is_none() {
+        error.node = Some(node_id);
+    }
+    error.into()
+}
+
+// Attach the error to the input it was raised for, unless it already points to a port.
+pub fn port_error(err: anyhow::Error, port: impl ToString) -> anyhow::Error {
+    let error = ProtosError::from_error(&err);
+    match error.port {
+        Some(_) => error.into(),
+        None => error.with_port(port).into(),
+    }
+}
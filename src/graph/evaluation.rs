@@ -0,0 +1,130 @@
+use std::collections::{HashMap, HashSet};
+
+use egui_node_graph::{NodeId, OutputId};
+
+use crate::gfx;
+
+use super::{core::ProtosGraph, error::node_error, node::OutputsCache, subgraph::flatten_graph, ProtosNodeTemplate};
+
+// Timing of the frame being evaluated, exposed to shaders.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FrameInfo {
+    pub time: f32, // Seconds since start.
+    pub delta_time: f32, // Seconds since previous frame.
+    pub index: u32,
+}
+
+// Everything nodes need to evaluate, independent of the frontend driving the graph.
+pub struct EvaluationContext<'a> {
+    pub device: &'a wgpu::Device,
+    pub queue: &'a wgpu::Queue,
+    pub frame: FrameInfo,
+    pub target_size: (u32, u32), // Size the graph is displayed or saved at, replaced by the resolution of the backbuffer for the passes it evaluates.
+}
+
+pub struct RecordContext<'a> {
+    pub device: &'a wgpu::Device,
+    pub cmd: &'a mut wgpu::CommandEncoder,
+    pub profiler: Option<&'a mut gfx::GpuProfiler<NodeId>>, // Times passes by node when profiling.
+    pub recorded: HashSet<NodeId>, // Nodes already recorded this frame, passes read by several inputs are recorded once.
+}
+
+impl<'a> RecordContext<'a> {
+    pub fn new(device: &'a wgpu::Device, cmd: &'a mut wgpu::CommandEncoder, profiler: Option<&'a mut gfx::GpuProfiler<NodeId>>) -> Self {
+        Self {
+            device,
+            cmd,
+            profiler,
+            recorded: HashSet::new(),
+        }
+    }
+}
+
+fn evaluate_node(ctx: &EvaluationContext<'_>, graph: &ProtosGraph, node_id: NodeId, outputs_cache: &mut OutputsCache) -> anyhow::Result<()> {
+    match graph.nodes.get(node_id) {
+        Some(node) => node.user_data.template.visit_node(|node| {
+            node.evaluate(ctx, graph, node_id, outputs_cache)
+        }).map_err(|err| node_error(err, node_id)),
+        None => anyhow::bail!("Node to evaluate is not in the graph"),
+    }
+}
+
+// Evaluated feedback nodes, with the output connected to their input.
+fn feedback_sources(graph: &ProtosGraph, outputs_cache: &OutputsCache) -> Vec<(NodeId, OutputId)> {
+    graph.iter_nodes().filter_map(|node_id| {
+        let node = &graph[node_id];
+        let source = match &node.user_data.template {
+            ProtosNodeTemplate::Feedback(feedback) => feedback.get_source(graph, node_id)?,
+            _ => return None,
+        };
+        let evaluated = node.outputs.iter().any(|(_, output_id)| outputs_cache.contains_key(output_id));
+        evaluated.then_some((node_id, source))
+    }).collect()
+}
+
+// Create or update resources of every node the given node depends on. Returns the outputs of all evaluated nodes.
+// Feedbacks do not evaluate their input as it usually depends on them, it is evaluated once everything else is,
+// until no new feedback is reached. Subgraphs are not evaluated, the graph must be flattened first.
+pub fn evaluate_graph(ctx: &EvaluationContext<'_>, graph: &ProtosGraph, node_id: NodeId) -> anyhow::Result<OutputsCache> {
+    let mut outputs_cache = HashMap::new();
+    evaluate_node(ctx, graph, node_id, &mut outputs_cache)?;
+    let mut deferred = HashSet::new();
+    loop {
+        let pending = feedback_sources(graph, &outputs_cache).into_iter()
+            .filter(|(_, source)| !outputs_cache.contains_key(source))
+            .map(|(_, source)| graph[source].node)
+            .filter(|source_id| deferred.insert(*source_id))
+            .collect::<Vec<_>>();
+        if pending.is_empty() {
+            return Ok(outputs_cache);
+        }
+        for source_id in pending {
+            evaluate_node(ctx, graph, source_id, &mut outputs_cache)?;
+        }
+    }
+}
+
+// Record commands of every node the given node depends on. The graph must have been evaluated first.
+pub fn record_graph(ctx: &mut RecordContext<'_>, graph: &ProtosGraph, node_id: NodeId, outputs_cache: &OutputsCache) -> anyhow::Result<()> {
+    // Already recorded through another input, recording it again would run its pass & feedbacks twice.
+    if !ctx.recorded.insert(node_id) {
+        return Ok(());
+    }
+    match graph.nodes.get(node_id) {
+        Some(node) => node.user_data.template.visit_node(|node| {
+            node.record(ctx, graph, node_id, outputs_cache)
+        }).map_err(|err| node_error(err, node_id)),
+        None => anyhow::bail!("Node to record is not in the graph"),
+    }
+}
+
+// Record every node evaluated from the given one. Feedbacks copy their input before any pass is recorded,
+// so that they hold previous frame whatever the order passes are reached in. Their inputs are recorded last.
+pub fn record_frame(ctx: &mut RecordContext<'_>, graph: &ProtosGraph, node_id: NodeId, outputs_cache: &OutputsCache) -> anyhow::Result<()> {
+    let feedbacks = feedback_sources(graph, outputs_cache);
+    for (feedback_id, source) in &feedbacks {
+        if let ProtosNodeTemplate::Feedback(feedback) = &graph[*feedback_id].user_data.template {
+            feedback.record_copy(ctx, outputs_cache.get(source)).map_err(|err| node_error(err, *feedback_id))?;
+        }
+    }
+    record_graph(ctx, graph, node_id, outputs_cache)?;
+    for (_, source) in feedbacks {
+        record_graph(ctx, graph, graph[source].node, outputs_cache)?;
+    }
+    Ok(())
+}
+
+// Evaluate & record the given backbuffer node, the usual entry point for frontends.
+pub fn render_backbuffer(ctx: &EvaluationContext<'_>, cmd: &mut wgpu::CommandEncoder, graph: &ProtosGraph, node_id: NodeId) -> anyhow::Result<()> {
+    match graph.nodes.get(node_id).map(|node| &node.user_data.template) {
+        Some(ProtosNodeTemplate::BackbufferPass(_)) => {}
+        Some(_) => anyhow::bail!("Node is not a backbuffer pass"),
+        None => anyhow::bail!("Backbuffer is not in the graph"),
+    }
+    let flat_graph = flatten_graph(graph)?;
+    let node_id = flat_graph.node(node_id).ok_or(anyhow::anyhow!("Backbuffer is not in the flattened graph"))?;
+    // Errors are located in the given graph rather than the flattened one.
+    let outputs_cache = evaluate_graph(ctx, &flat_graph.graph, node_id).map_err(|err| flat_graph.locate_error(&err))?;
+    let mut record_ctx = RecordContext::new(ctx.device, cmd, None);
+    record_frame(&mut record_ctx, &flat_graph.graph, node_id, &outputs_cache).map_err(|err| flat_graph.locate_error(&err).into())
+}
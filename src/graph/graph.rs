@@ -1,5 +1,11 @@
+use std::collections::HashMap;
+
 use egui_node_graph::NodeId;
 
+use crate::gfx;
+
+use super::{Diagnostics, Previews};
+
 /// The graph 'global' state. This state struct is passed around to the node and
 /// parameter drawing callbacks. The contents of this struct are entirely up to
 /// the user. For this example, we use it to keep track of the 'active' node.
@@ -7,4 +13,21 @@ use egui_node_graph::NodeId;
 #[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
 pub struct ProtosGraphState {
     pub backbuffer_node: Option<NodeId>,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub diagnostics: Diagnostics, // Found by the last evaluation, drawn on nodes.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub previews: Previews,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub gpu_times: HashMap<Vec<NodeId>, f32>, // Milliseconds spent by the GPU on the passes of nodes, when profiling.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub gpu_statistics: HashMap<Vec<NodeId>, gfx::PipelineStatistics>, // When pipeline statistics are supported.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub edited_path: Vec<NodeId>, // Subgraph nodes of the graph being drawn, from the top level graph.
+}
+
+impl ProtosGraphState {
+    // Path of a node of the graph being drawn, from the top level graph.
+    pub fn node_path(&self, node_id: NodeId) -> Vec<NodeId> {
+        self.edited_path.iter().copied().chain([node_id]).collect()
+    }
 }
@@ -1,21 +1,66 @@
 mod core;
 mod node;
+mod ui;
 mod connection;
 mod graph;
 mod response;
-mod nodes;
+pub mod nodes;
+mod evaluation;
+mod shadertoy;
+mod builder;
+mod subgraph;
+mod diagnostics;
+mod error;
+mod preview;
+#[cfg(feature = "persistence")]
+mod clipboard;
 
 
 pub use self::node::ProtosNode;
+pub use self::ui::ProtosNodeUi;
 pub use self::node::ProtosNodeTemplate;
 pub use self::node::AllProtosNodeTemplates;
 
+pub use self::evaluation::FrameInfo;
+pub use self::evaluation::EvaluationContext;
+pub use self::evaluation::RecordContext;
+pub use self::evaluation::evaluate_graph;
+pub use self::evaluation::record_graph;
+pub use self::evaluation::record_frame;
+pub use self::evaluation::render_backbuffer;
+
 pub use self::core::ProtosNodeData;
 pub use self::core::ProtosEditorState;
+pub use self::core::ProtosGraph;
+pub use self::core::ProtosCategoryType;
 
 pub use self::connection::ProtosDataType;
 pub use self::connection::ProtosValueType;
 
 pub use self::graph::ProtosGraphState;
 
-pub use self::response::ProtosResponse;
\ No newline at end of file
+pub use self::response::ProtosResponse;
+
+pub use self::shadertoy::import_shadertoy;
+pub use self::builder::GraphBuilder;
+pub use self::builder::add_node;
+
+pub use self::subgraph::Subgraph;
+pub use self::subgraph::FlatGraph;
+pub use self::subgraph::flatten_graph;
+pub use self::subgraph::group_nodes;
+pub use self::subgraph::node_labels;
+
+pub use self::diagnostics::Diagnostic;
+pub use self::diagnostics::Diagnostics;
+pub use self::diagnostics::Severity;
+
+pub use self::error::ProtosError;
+pub use self::error::ProtosErrorKind;
+
+pub use self::preview::Previews;
+pub use self::preview::PreviewKey;
+pub use self::preview::find_output;
+
+#[cfg(feature = "persistence")]
+pub use self::clipboard::{copy_nodes, paste_nodes, NODES_CLIPBOARD_HEADER};
\ No newline at end of file
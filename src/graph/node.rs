@@ -1,60 +1,46 @@
 use std::{borrow::Cow, collections::HashMap};
 
-use egui::Vec2;
-use egui_node_graph::{NodeTemplateIter, NodeId, NodeTemplateTrait, Graph, UserResponseTrait, NodeDataTrait, NodeResponse, OutputId};
+use egui_node_graph::{NodeTemplateIter, NodeId, NodeTemplateTrait, Graph, OutputId};
 
-use super::{core::{ProtosCategoryType, ProtosGraph}, nodes::{BackbufferPassNode, BufferNode, CameraNode, ComputePassNode, GraphicPassNode, MeshNode, ShaderNode, TextureFileNode, TextureResourceNode}, ProtosDataType, ProtosGraphState, ProtosNodeData, ProtosResponse, ProtosValueType};
+use super::{core::{ProtosCategoryType, ProtosGraph}, error::{node_error, port_error}, evaluation::{record_graph, EvaluationContext, RecordContext}, nodes::{BackbufferPassNode, BufferNode, CameraNode, ComputePassNode, FeedbackNode, GraphicPassNode, GroupInputNode, GroupOutputNode, MeshNode, ShaderNode, SubgraphNode, TextureFileNode, TextureResourceNode}, ProtosDataType, ProtosGraphState, ProtosNodeData, ProtosValueType};
 
 pub type OutputsCache = HashMap<OutputId, ProtosValueType>;
 
 pub trait ProtosNode {
     // Get node name
     fn get_name(&self) -> &str;
+    // Category & short description, displayed in the node finder.
+    fn get_category(&self) -> ProtosCategoryType;
+    fn get_description(&self) -> &str;
     // Describe the node
     fn build(&self, graph: &mut ProtosGraph, node_id: NodeId);
-    // Describe the UI
-    fn ui(&self, graph: &ProtosGraph, node_id: NodeId, ui: &mut egui::Ui);
     // Evaluate its input / output
     fn evaluate(&self,
-        device: &wgpu::Device,
-        queue: &wgpu::Queue,
+        ctx: &EvaluationContext<'_>,
         graph: &ProtosGraph,
         node_id: NodeId, // TODO: store in data & remove.
-        available_size: Vec2, // TODO: remove somehow
         outputs_cache: &mut OutputsCache) -> anyhow::Result<()>;
     // Record the node to command buffer
     fn record(&self,
-        device: &wgpu::Device,
-        cmd: &mut wgpu::CommandEncoder,
+        ctx: &mut RecordContext<'_>,
         graph: &ProtosGraph,
         node_id: NodeId,
-        outputs_cache: &mut OutputsCache) -> anyhow::Result<()>;
+        outputs_cache: &OutputsCache) -> anyhow::Result<()>;
 
     // Evaluates the input value of
     fn record_input(
         &self,
-        device: &wgpu::Device,
-        cmd: &mut wgpu::CommandEncoder,
+        ctx: &mut RecordContext<'_>,
         graph: &ProtosGraph,
         node_id: NodeId,
         input_name: String,
-        outputs_cache: &mut OutputsCache,
+        outputs_cache: &OutputsCache,
     ) -> anyhow::Result<()> {
         let input_id = graph[node_id].get_input(input_name.to_string().as_str()).unwrap();
 
-        // The output of another node is connected.
+        // The output of another node is connected, record it first.
         if let Some(other_output_id) = graph.connection(input_id) {
-            // The value was already computed due to the evaluation of some other
-            // node. We simply return value from the cache.
-            if let Some(other_value) = outputs_cache.get(&other_output_id) {
-                let _ = other_value;
-                Ok(())
-            } else {
-                // First time in this node, recurse it.
-                graph[graph[other_output_id].node].user_data.template.visit_node(|input_node| {
-                    input_node.record(device, cmd, graph, graph[other_output_id].node, outputs_cache)
-                })
-            }
+            record_graph(ctx, graph, graph[other_output_id].node, outputs_cache)
         } else {
             Ok(())
         }
@@ -73,18 +59,30 @@ pub trait ProtosNode {
         outputs_cache.insert(output_id, value.clone());
     }
 
+    // Evaluates the input & converts it, conversion errors point to the input.
+    fn evaluate_input_as<T>(
+        &self,
+        ctx: &EvaluationContext<'_>,
+        graph: &ProtosGraph,
+        node_id: NodeId,
+        param_name: String,
+        outputs_cache: &mut OutputsCache,
+        convert: impl FnOnce(ProtosValueType) -> anyhow::Result<T>,
+    ) -> anyhow::Result<T> where Self: Sized {
+        let value = self.evaluate_input(ctx, graph, node_id, param_name.clone(), outputs_cache)?;
+        convert(value).map_err(|err| port_error(err, param_name))
+    }
+
     // Evaluates the input value of
     fn evaluate_input(
         &self,
-        device: &wgpu::Device,
-        queue: &wgpu::Queue,
+        ctx: &EvaluationContext<'_>,
         graph: &ProtosGraph,
         node_id: NodeId,
-        available_size: Vec2,
         param_name: String,
         outputs_cache: &mut OutputsCache,
     ) -> anyhow::Result<ProtosValueType> {
-        let input_id = graph[node_id].get_input(param_name.as_str())?;
+        let input_id = graph[node_id].get_input(param_name.as_str()).map_err(|err| port_error(err.into(), &param_name))?;
 
         // The output of another node is connected.
         if let Some(other_output_id) = graph.connection(input_id) {
@@ -96,14 +94,15 @@ pub trait ProtosNode {
             // This is the first time encountering this node, so we need to
             // recursively evaluate it.
             else {
-                graph[graph[other_output_id].node].user_data.template.visit_node(|input_node| {
-                    match input_node.evaluate(device, queue, graph, graph[other_output_id].node, available_size, outputs_cache) {
+                let input_node_id = graph[other_output_id].node;
+                graph[input_node_id].user_data.template.visit_node(|input_node| {
+                    match input_node.evaluate(ctx, graph, input_node_id, outputs_cache) {
                         Ok(()) => {
                             Ok(outputs_cache
                             .get(&other_output_id)
                             .expect("Cache should be populated").clone())
                         }
-                        Err(err) => anyhow::bail!("Node failed to compile : {}.", err.to_string())
+                        Err(err) => Err(port_error(node_error(err, input_node_id), &param_name)),
                     }
                 })
             }
@@ -127,6 +126,10 @@ pub enum ProtosNodeTemplate {
     Camera (CameraNode), 
     Mesh (MeshNode), 
     Shader(ShaderNode),
+    Feedback(FeedbackNode),
+    Subgraph(SubgraphNode),
+    GroupInput(GroupInputNode),
+    GroupOutput(GroupOutputNode),
 }
 
 impl ProtosNodeTemplate {
@@ -141,7 +144,36 @@ impl ProtosNodeTemplate {
             ProtosNodeTemplate::Camera(handle) => { f(handle) }
             ProtosNodeTemplate::Mesh(handle) => { f(handle) }
             ProtosNodeTemplate::Shader(handle) => { f(handle) }
+            ProtosNodeTemplate::Feedback(handle) => { f(handle) }
+            ProtosNodeTemplate::Subgraph(handle) => { f(handle) }
+            ProtosNodeTemplate::GroupInput(handle) => { f(handle) }
+            ProtosNodeTemplate::GroupOutput(handle) => { f(handle) }
+        }
+    }
+    // Inputs & outputs of the node with their types, built on a scratch graph.
+    pub fn ports(&self) -> (Vec<(String, ProtosDataType)>, Vec<(String, ProtosDataType)>) {
+        let mut graph = ProtosGraph::new();
+        let node_id = graph.add_node(String::new(), ProtosNodeData { template: self.clone() }, |graph, node_id| {
+            self.visit_node(|node| {
+                node.build(graph, node_id);
+                Ok(())
+            }).expect("Should not fail.");
+        });
+        let node = &graph[node_id];
+        let inputs = node.inputs.iter().map(|(name, id)| (name.clone(), graph[*id].typ.clone())).collect();
+        let outputs = node.outputs.iter().map(|(name, id)| (name.clone(), graph[*id].typ.clone())).collect();
+        (inputs, outputs)
+    }
+    // Template of a new node, subgraphs from the library are copied so that each instance has its own resources.
+    fn instantiate(&self) -> Self {
+        #[cfg(feature = "persistence")]
+        if let ProtosNodeTemplate::Subgraph(node) = self {
+            match node.deep_copy(node.name()) {
+                Ok(copy) => return ProtosNodeTemplate::Subgraph(copy),
+                Err(err) => log::error!("Failed to instantiate subgraph : {:#}", err),
+            }
         }
+        self.clone()
     }
 }
 
@@ -163,6 +195,10 @@ impl NodeTemplateTrait for ProtosNodeTemplate {
         }
     }
 
+    fn node_finder_categories(&self, _user_state: &mut Self::UserState) -> Vec<Self::CategoryType> {
+        vec![self.visit_node(|node| Ok(node.get_category())).expect("Should not fail.")]
+    }
+
     fn node_graph_label(&self, user_state: &mut Self::UserState) -> String {
         // It's okay to delegate this to node_finder_label if you don't want to
         // show different names in the node finder and the node itself.
@@ -170,7 +206,7 @@ impl NodeTemplateTrait for ProtosNodeTemplate {
     }
 
     fn user_data(&self, _user_state: &mut Self::UserState) -> Self::NodeData {
-        ProtosNodeData { template: self.clone() }
+        ProtosNodeData { template: self.instantiate() }
     }
 
     fn build_node(
@@ -187,7 +223,10 @@ impl NodeTemplateTrait for ProtosNodeTemplate {
     }
 }
 
-pub struct AllProtosNodeTemplates;
+#[derive(Default)]
+pub struct AllProtosNodeTemplates {
+    pub library: Vec<SubgraphNode>, // Subgraphs saved by the user.
+}
 
 impl NodeTemplateIter for AllProtosNodeTemplates {
     type Item = ProtosNodeTemplate;
@@ -206,73 +245,9 @@ impl NodeTemplateIter for AllProtosNodeTemplates {
             ProtosNodeTemplate::Camera(CameraNode::default()),
             ProtosNodeTemplate::Mesh(MeshNode::default()),
             ProtosNodeTemplate::Shader(ShaderNode::default()),
-        ]
+            ProtosNodeTemplate::Feedback(FeedbackNode::default()),
+        ].into_iter()
+            .chain(self.library.iter().cloned().map(ProtosNodeTemplate::Subgraph))
+            .collect()
     }
 }
-
-
-impl NodeDataTrait for ProtosNodeData {
-    type Response = ProtosResponse;
-    type UserState = ProtosGraphState;
-    type DataType = ProtosDataType;
-    type ValueType = ProtosValueType;
-
-    // This method will be called when drawing each node. This allows adding
-    // extra ui elements inside the nodes. In this case, we create an "active"
-    // button which introduces the concept of having an active node in the
-    // graph. This is done entirely from user code with no modifications to the
-    // node graph library.
-    fn bottom_ui(
-        &self,
-        ui: &mut egui::Ui,
-        node_id: NodeId,
-        graph: &ProtosGraph,
-        user_state: &mut Self::UserState,
-    ) -> Vec<NodeResponse<ProtosResponse, ProtosNodeData>>
-    where
-        ProtosResponse: UserResponseTrait,
-    {
-        match &self.template {
-            ProtosNodeTemplate::BackbufferPass(node) => {
-                // We only want bottom UI for backbuffer pass node.
-                node.ui(graph, node_id, ui);
-
-                // This logic is entirely up to the user. In this case, we check if the
-                // current node we're drawing is the active one, by comparing against
-                // the value stored in the global user state, and draw different button
-                // UIs based on that.
-
-                let mut responses = vec![];
-                let is_active = user_state
-                    .backbuffer_node
-                    .map(|id| id == node_id)
-                    .unwrap_or(false);
-
-                // Pressing the button will emit a custom user response to either set,
-                // or clear the active node. These responses do nothing by themselves,
-                // the library only makes the responses available to you after the graph
-                // has been drawn. See below at the update method for an example.
-                if !is_active {
-                    if ui.button("👁 Set active").clicked() {
-                        responses.push(NodeResponse::User(ProtosResponse::SetCurrentBackbuffer(node_id)));
-                    }
-                } else {
-                    let button =
-                        egui::Button::new(egui::RichText::new("👁 Active").color(egui::Color32::BLACK))
-                            .fill(egui::Color32::GOLD);
-                    if ui.add(button).clicked() {
-                        responses.push(NodeResponse::User(ProtosResponse::ClearCurrentBackbuffer));
-                    }
-                }
-                responses
-            }
-            _ => { 
-                self.template.visit_node(|node| {
-                    node.ui(graph, node_id, ui);
-                    Ok(())
-                }).expect("Should not fail.");
-                return vec![]; 
-            }
-        }
-    }
-}
\ No newline at end of file
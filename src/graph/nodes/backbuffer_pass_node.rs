@@ -1,9 +1,8 @@
 use core::fmt;
 
-use egui::Vec2;
 use egui_node_graph::{InputParamKind, NodeId};
 
-use crate::{gfx, graph::{core::ProtosGraph, node::OutputsCache, ProtosDataType, ProtosNode, ProtosValueType}};
+use crate::{gfx, graph::{core::ProtosGraph, evaluation::{EvaluationContext, RecordContext}, node::OutputsCache, ProtosDataType, ProtosError, ProtosCategoryType, ProtosNode, ProtosNodeUi, ProtosValueType}};
 
 #[derive(Default, Clone)]
 #[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
@@ -26,8 +25,11 @@ impl ProtosNode for BackbufferPassNode {
     fn get_name(&self) -> &str {
         "Backbuffer pass"
     }
-    fn ui(&self, _graph: &ProtosGraph, _node_id: NodeId, _ui: &mut egui::Ui) {
-        
+    fn get_category(&self) -> ProtosCategoryType {
+        ProtosCategoryType::Passes
+    }
+    fn get_description(&self) -> &str {
+        "Final pass of the graph, its render target is displayed once set active."
     }
     fn build(&self, graph: &mut ProtosGraph, node_id: NodeId) {
         graph.add_input_param(
@@ -41,38 +43,78 @@ impl ProtosNode for BackbufferPassNode {
     }
     fn evaluate(
         &self, 
-        device: &wgpu::Device,
-        queue: &wgpu::Queue,
+        ctx: &EvaluationContext<'_>,
         graph: &ProtosGraph,
         node_id: NodeId,
-        available_size: Vec2,
         outputs_cache: &mut OutputsCache
     ) -> anyhow::Result<()> {
-        let input = self.evaluate_input(device, queue, graph, node_id, available_size, BackbufferPassNodeInput::Input.to_string(), outputs_cache)?.try_to_texture()?;
+        // Passes are sized relative to the resolution of the backbuffer, resolved before evaluating them.
+        let size = self.handle.lock().unwrap().get_mode().resolve(ctx.target_size);
+        let ctx = &EvaluationContext { target_size: size, ..*ctx };
+        let input = self.evaluate_input_as(ctx, graph, node_id, BackbufferPassNodeInput::Input.to_string(), outputs_cache, ProtosValueType::try_to_texture)?;
         // Check input is valid type.
         let mut pass = self.handle.lock().unwrap();
         if let Some(value) = input {
             pass.set_origin(value);
         } else {
-            anyhow::bail!("No input set.")
+            anyhow::bail!(ProtosError::missing_input(BackbufferPassNodeInput::Input))
         }
-        pass.set_size(available_size.x as u32, available_size.y as u32);
+        pass.set_size(size.0, size.1);
         // Will call create if not created already.
-        pass.update_data(device, queue)?;
+        pass.update_data(ctx.device, ctx.queue)?;
 
         Ok(())
     }
     fn record(
         &self,
-        device: &wgpu::Device,
-        cmd: &mut wgpu::CommandEncoder,
+        ctx: &mut RecordContext<'_>,
         graph: &ProtosGraph,
         node_id: NodeId,
-        outputs_cache: &mut OutputsCache
+        outputs_cache: &OutputsCache
     ) -> anyhow::Result<()> {
         // TODO should store inputs & set them automatically (we only need to define it at startup.)
-        self.record_input(device, cmd, graph, node_id, BackbufferPassNodeInput::Input.to_string(), outputs_cache)?;
+        self.record_input(ctx, graph, node_id, BackbufferPassNodeInput::Input.to_string(), outputs_cache)?;
         let pass = self.handle.lock().unwrap();
-        pass.record_data(device, cmd)
+        pass.record_data(ctx.device, ctx.cmd)
+    }
+}
+
+impl ProtosNodeUi for BackbufferPassNode {
+    fn ui(&self, _graph: &ProtosGraph, _node_id: NodeId, ui: &mut egui::Ui) -> bool {
+        gfx::visit_resource_mut(&self.handle, |pass| {
+            let size = (pass.get_width(), pass.get_height());
+            pass.visit_desc_mut(|desc| {
+                let mut changed = false;
+                let scale = gfx::BackbufferMode::default();
+                let fixed = gfx::BackbufferMode::Fixed { width: 1920, height: 1080 };
+                let aspect = gfx::BackbufferMode::Aspect { width: 16, height: 9 };
+                egui::ComboBox::from_label("Resolution")
+                    .selected_text(desc.mode.get_mode_name())
+                    .show_ui(ui, |ui| {
+                        for mode in [scale, fixed, aspect] {
+                            // Keep the values of the current mode when selected again.
+                            let selected = std::mem::discriminant(&mode) == std::mem::discriminant(&desc.mode);
+                            if ui.selectable_label(selected, mode.get_mode_name()).clicked() && !selected {
+                                desc.mode = mode;
+                                changed = true;
+                            }
+                        }
+                    });
+                match &mut desc.mode {
+                    gfx::BackbufferMode::Scale { percent } => {
+                        changed |= ui.add(egui::DragValue::new(percent).clamp_range(1..=400).suffix(" %")).changed();
+                    },
+                    gfx::BackbufferMode::Fixed { width, height } | gfx::BackbufferMode::Aspect { width, height } => {
+                        ui.horizontal(|ui| {
+                            changed |= ui.add(egui::DragValue::new(width).clamp_range(1..=8192)).changed();
+                            ui.label("x");
+                            changed |= ui.add(egui::DragValue::new(height).clamp_range(1..=8192)).changed();
+                        });
+                    },
+                }
+                ui.weak(format!("{}x{}", size.0, size.1));
+                changed
+            })
+        })
     }
 }
\ No newline at end of file
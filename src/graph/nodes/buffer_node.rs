@@ -1,9 +1,8 @@
 use core::fmt;
 
-use egui::Vec2;
 use egui_node_graph::{InputParamKind, NodeId};
 
-use crate::{gfx, graph::{core::ProtosGraph, node::OutputsCache, ProtosDataType, ProtosNode, ProtosValueType}};
+use crate::{gfx, graph::{core::ProtosGraph, evaluation::{EvaluationContext, RecordContext}, node::OutputsCache, ProtosDataType, ProtosCategoryType, ProtosNode, ProtosNodeUi, ProtosValueType}};
 
 #[derive(Default, Clone)]
 #[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
@@ -37,6 +36,12 @@ impl ProtosNode for BufferNode {
     fn get_name(&self) -> &str {
         "Buffer"
     }
+    fn get_category(&self) -> ProtosCategoryType {
+        ProtosCategoryType::Resources
+    }
+    fn get_description(&self) -> &str {
+        "GPU buffer which can be bound to passes."
+    }
     fn build(&self, graph: &mut ProtosGraph, node_id: NodeId) {
         graph.add_input_param(
             node_id,
@@ -59,37 +64,33 @@ impl ProtosNode for BufferNode {
             BufferNodeOutput::Buffer.to_string(),
             ProtosDataType::Buffer
         );
-    }
-    fn ui(&self, _graph: &ProtosGraph, _node_id: NodeId, _ui: &mut egui::Ui) {
-        
     }
     fn evaluate(
         &self, 
-        device: &wgpu::Device,
-        queue: &wgpu::Queue,
+        ctx: &EvaluationContext<'_>,
         graph: &ProtosGraph,
         node_id: NodeId,
-        available_size: Vec2,
         outputs_cache: &mut OutputsCache
     ) -> anyhow::Result<()> {
-        let size = self.evaluate_input(device, queue, graph, node_id, available_size, BufferNodeInput::Size.to_string(), outputs_cache)?.try_to_scalar()?;
-        let format = self.evaluate_input(device, queue, graph, node_id, available_size, BufferNodeInput::Format.to_string(), outputs_cache)?.try_to_scalar()?;
+        let size = self.evaluate_input_as(ctx, graph, node_id, BufferNodeInput::Size.to_string(), outputs_cache, ProtosValueType::try_to_scalar)?;
+        let format = self.evaluate_input_as(ctx, graph, node_id, BufferNodeInput::Format.to_string(), outputs_cache, ProtosValueType::try_to_scalar)?;
         let mut buffer = self.handle.lock().unwrap();
         buffer.set_size(size as u32);
         buffer.set_format(format as u32);
-        buffer.update_data(device, queue)?;
+        buffer.update_data(ctx.device, ctx.queue)?;
         self.populate_output(graph, node_id, BufferNodeOutput::Buffer.to_string(), ProtosValueType::Buffer(Some(self.handle.clone())), outputs_cache);
         
         Ok(())
     }
     fn record(
         &self,
-        _device: &wgpu::Device,
-        _cmd: &mut wgpu::CommandEncoder,
+        _ctx: &mut RecordContext<'_>,
         _graph: &ProtosGraph,
         _node_id: NodeId,
-        _outputs_cache: &mut OutputsCache
+        _outputs_cache: &OutputsCache
     ) -> anyhow::Result<()> {
         Ok(()) // Nothing to record here
     }
-}
\ No newline at end of file
+}
+
+impl ProtosNodeUi for BufferNode {}
\ No newline at end of file
@@ -1,7 +1,6 @@
-use egui::Vec2;
 use egui_node_graph::NodeId;
 
-use crate::{gfx, graph::{core::ProtosGraph, node::OutputsCache, ProtosNode}};
+use crate::{gfx, graph::{core::ProtosGraph, evaluation::{EvaluationContext, RecordContext}, node::OutputsCache, ProtosCategoryType, ProtosNode, ProtosNodeUi}};
 
 #[derive(Default, Clone)]
 #[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
@@ -13,31 +12,33 @@ impl ProtosNode for CameraNode {
     fn get_name(&self) -> &str {
         "Camera"
     }
-    fn build(&self, _graph: &mut ProtosGraph, _node_id: NodeId) {
+    fn get_category(&self) -> ProtosCategoryType {
+        ProtosCategoryType::Inputs
+    }
+    fn get_description(&self) -> &str {
+        "View & projection of the scene."
     }
-    fn ui(&self, _graph: &ProtosGraph, _node_id: NodeId, _ui: &mut egui::Ui) {
-        
+    fn build(&self, _graph: &mut ProtosGraph, _node_id: NodeId) {
     }
     fn evaluate(
         &self, 
-        _device: &wgpu::Device,
-        _queue: &wgpu::Queue,
+        _ctx: &EvaluationContext<'_>,
         _graph: &ProtosGraph,
         _node_id: NodeId,
-        _available_size: Vec2,
         _outputs_cache: &mut OutputsCache
     ) -> anyhow::Result<()> {
         Ok(())
     }
     fn record(
         &self,
-        _device: &wgpu::Device,
-        _cmd: &mut wgpu::CommandEncoder,
+        _ctx: &mut RecordContext<'_>,
         _graph: &ProtosGraph,
         _node_id: NodeId,
-        _outputs_cache: &mut OutputsCache
+        _outputs_cache: &OutputsCache
     ) -> anyhow::Result<()> {
         let _ = self.handle;
         Ok(()) // Nothing to record here
     }
-}
\ No newline at end of file
+}
+
+impl ProtosNodeUi for CameraNode {}
\ No newline at end of file
@@ -1,7 +1,6 @@
-use egui::Vec2;
 use egui_node_graph::{InputParamKind, NodeId};
 
-use crate::{gfx, graph::{core::ProtosGraph, node::OutputsCache, ProtosDataType, ProtosNode, ProtosValueType}};
+use crate::{gfx, graph::{core::ProtosGraph, evaluation::{EvaluationContext, RecordContext}, node::OutputsCache, ProtosDataType, ProtosCategoryType, ProtosNode, ProtosNodeUi, ProtosValueType}};
 
 #[derive(Default, Clone)]
 #[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
@@ -13,6 +12,12 @@ impl ProtosNode for ComputePassNode {
     fn get_name(&self) -> &str {
         "Compute pass"
     }
+    fn get_category(&self) -> ProtosCategoryType {
+        ProtosCategoryType::Passes
+    }
+    fn get_description(&self) -> &str {
+        "Dispatch a compute shader."
+    }
     fn build(&self, graph: &mut ProtosGraph, node_id: NodeId) {
         
         // TODO for loop
@@ -26,17 +31,12 @@ impl ProtosNode for ComputePassNode {
         );
         // TODO for loop
         graph.add_output_param(node_id, "RT0".to_string(), ProtosDataType::Texture);
-    }
-    fn ui(&self, _graph: &ProtosGraph, _node_id: NodeId, _ui: &mut egui::Ui) {
-        
     }
     fn evaluate(
         &self, 
-        _device: &wgpu::Device,
-        _queue: &wgpu::Queue,
+        _ctx: &EvaluationContext<'_>,
         _graph: &ProtosGraph,
         _node_id: NodeId,
-        _available_size: Vec2,
         _outputs_cache: &mut OutputsCache
     ) -> anyhow::Result<()> {
         
@@ -44,13 +44,14 @@ impl ProtosNode for ComputePassNode {
     }
     fn record(
         &self,
-        _device: &wgpu::Device,
-        _cmd: &mut wgpu::CommandEncoder,
+        _ctx: &mut RecordContext<'_>,
         _graph: &ProtosGraph,
         _node_id: NodeId,
-        _outputs_cache: &mut OutputsCache
+        _outputs_cache: &OutputsCache
     ) -> anyhow::Result<()> {
         let _ = self.handle;
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+impl ProtosNodeUi for ComputePassNode {}
\ No newline at end of file
@@ -0,0 +1,105 @@
+use core::fmt;
+
+use egui_node_graph::{InputParamKind, NodeId, OutputId};
+
+use crate::{gfx, graph::{core::ProtosGraph, evaluation::{EvaluationContext, RecordContext}, node::OutputsCache, ProtosDataType, ProtosCategoryType, ProtosNode, ProtosNodeUi, ProtosValueType}};
+
+// Output the content its input had on previous frame, allowing passes to read their own result.
+#[derive(Default, Clone)]
+#[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
+pub struct FeedbackNode {
+    handle: gfx::ResourceHandle<gfx::Texture>
+}
+
+pub enum FeedbackNodeInput {
+    Input,
+}
+pub enum FeedbackNodeOutput {
+    Previous,
+}
+impl fmt::Display for FeedbackNodeInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeedbackNodeInput::Input => write!(f, "Input"),
+        }
+    }
+}
+impl fmt::Display for FeedbackNodeOutput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeedbackNodeOutput::Previous => write!(f, "Previous"),
+        }
+    }
+}
+
+impl ProtosNode for FeedbackNode {
+    fn get_name(&self) -> &str {
+        "Feedback"
+    }
+    fn get_category(&self) -> ProtosCategoryType {
+        ProtosCategoryType::Utility
+    }
+    fn get_description(&self) -> &str {
+        "Content its input had on previous frame, allowing passes to read their own result."
+    }
+    fn build(&self, graph: &mut ProtosGraph, node_id: NodeId) {
+        graph.add_input_param(
+            node_id,
+            FeedbackNodeInput::Input.to_string(),
+            ProtosDataType::Texture,
+            ProtosValueType::Texture(None),
+            InputParamKind::ConnectionOnly,
+            true,
+        );
+        graph.add_output_param(node_id, FeedbackNodeOutput::Previous.to_string(), ProtosDataType::Texture);
+    }
+    fn evaluate(
+        &self,
+        ctx: &EvaluationContext<'_>,
+        graph: &ProtosGraph,
+        node_id: NodeId,
+        outputs_cache: &mut OutputsCache
+    ) -> anyhow::Result<()> {
+        // Input is not evaluated here as it usually depends on this node, evaluate_graph pulls it once the readers are.
+        let mut texture = self.handle.lock().unwrap();
+        texture.set_size(ctx.target_size.0, ctx.target_size.1);
+        texture.update_data(ctx.device, ctx.queue)?;
+        self.populate_output(graph, node_id, FeedbackNodeOutput::Previous.to_string(), ProtosValueType::Texture(Some(self.handle.clone())), outputs_cache);
+        Ok(())
+    }
+    fn record(
+        &self,
+        _ctx: &mut RecordContext<'_>,
+        _graph: &ProtosGraph,
+        _node_id: NodeId,
+        _outputs_cache: &OutputsCache
+    ) -> anyhow::Result<()> {
+        Ok(()) // Copied by record_frame before any pass is recorded, its input is recorded after the nodes reading it.
+    }
+}
+
+impl FeedbackNode {
+    // Output connected to the input, evaluated & recorded apart from the nodes reading the feedback.
+    pub fn get_source(&self, graph: &ProtosGraph, node_id: NodeId) -> Option<OutputId> {
+        let input_id = graph[node_id].get_input(FeedbackNodeInput::Input.to_string().as_str()).ok()?;
+        graph.connection(input_id)
+    }
+    // Copy the input before it is rendered for this frame, so that it still holds previous frame.
+    pub fn record_copy(&self, ctx: &mut RecordContext<'_>, source: Option<&ProtosValueType>) -> anyhow::Result<()> {
+        let source = match source {
+            Some(value) => value.clone().try_to_texture()?,
+            None => None, // Input not evaluated this frame, keep the last content.
+        };
+        match source {
+            Some(source) if gfx::ResourceHandle::ptr_eq(&source, &self.handle) => anyhow::bail!("Feedback cannot read itself"),
+            Some(source) => {
+                let texture = self.handle.lock().unwrap();
+                let source = source.lock().unwrap();
+                texture.record_blit_from(ctx.device, ctx.cmd, &source)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl ProtosNodeUi for FeedbackNode {}
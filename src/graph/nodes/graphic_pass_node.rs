@@ -1,16 +1,18 @@
 use core::fmt;
 
-use egui::Vec2;
 use egui_node_graph::{InputParamKind, NodeId};
 
-use crate::{gfx, graph::{core::ProtosGraph, node::OutputsCache, ProtosDataType, ProtosNode, ProtosValueType}};
+use crate::{gfx, graph::{core::ProtosGraph, evaluation::{EvaluationContext, RecordContext}, node::OutputsCache, ProtosDataType, ProtosError, ProtosCategoryType, ProtosNode, ProtosNodeUi, ProtosValueType}};
 
 #[derive(Default, Clone)]
 #[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
 pub struct GraphicPassNode {
-    handle: gfx::ResourceHandle<gfx::GraphicPass>,
+    pub handle: gfx::ResourceHandle<gfx::GraphicPass>,
 }
 
+// Number of shader resource views exposed by the node.
+const MAX_SHADER_RESOURCE_VIEW: u32 = 4;
+
 pub enum GraphicPassNodeInput {
     ShaderResourceView(u32),
     VertexShader,
@@ -38,13 +40,31 @@ impl fmt::Display for GraphicPassNodeOutput {
     }
 }
 
+impl GraphicPassNode {
+    // What the pass drew during the last evaluation.
+    pub fn get_draw_counters(&self) -> Option<gfx::DrawCounters> {
+        self.handle.lock().unwrap().get_draw_counters()
+    }
+    fn get_shader_resource_view_count(&self, graph: &ProtosGraph, node_id: NodeId) -> u32 {
+        (0..MAX_SHADER_RESOURCE_VIEW)
+            .take_while(|i| graph[node_id].get_input(GraphicPassNodeInput::ShaderResourceView(*i).to_string().as_str()).is_ok())
+            .count() as u32
+    }
+}
+
 impl ProtosNode for GraphicPassNode {
     fn get_name(&self) -> &str {
         "Graphic pass"
     }
+    fn get_category(&self) -> ProtosCategoryType {
+        ProtosCategoryType::Passes
+    }
+    fn get_description(&self) -> &str {
+        "Draw a mesh with a shader into render targets."
+    }
     fn build(&self, graph: &mut ProtosGraph, node_id: NodeId) {
         // TODO: +/- button
-        for i in 0..1 {
+        for i in 0..MAX_SHADER_RESOURCE_VIEW {
             graph.add_input_param(
                 node_id,
                 GraphicPassNodeInput::ShaderResourceView(i).to_string(),
@@ -81,17 +101,12 @@ impl ProtosNode for GraphicPassNode {
         for i in 0..1 {
             graph.add_output_param(node_id, GraphicPassNodeOutput::RenderTarget(i).to_string(), ProtosDataType::Texture);
         }
-    }
-    fn ui(&self, _graph: &ProtosGraph, _node_id: NodeId, _ui: &mut egui::Ui) {
-        
     }
     fn evaluate(
         &self, 
-        device: &wgpu::Device,
-        queue: &wgpu::Queue,
+        ctx: &EvaluationContext<'_>,
         graph: &ProtosGraph,
         node_id: NodeId,
-        available_size: Vec2,
         outputs_cache: &mut OutputsCache
     ) -> anyhow::Result<()> {
         // Here we should call all input_xxx, which will update the description of the graphic pass.
@@ -100,41 +115,47 @@ impl ProtosNode for GraphicPassNode {
 
         let mut pass = self.handle.lock().unwrap();
 
-        let geometry = self.evaluate_input(device, queue, graph, node_id, available_size, GraphicPassNodeInput::Geometry.to_string(), outputs_cache)?.try_to_geometry()?;
+        let geometry = self.evaluate_input_as(ctx, graph, node_id, GraphicPassNodeInput::Geometry.to_string(), outputs_cache, ProtosValueType::try_to_geometry)?;
         if let Some(geo) = geometry {
             pass.set_geometry(geo);
         } else {
-            anyhow::bail!("Invalid geometry input")
+            anyhow::bail!(ProtosError::missing_input(GraphicPassNodeInput::Geometry))
         }
 
-        for i in 0..1 {
-            let srv = self.evaluate_input(device, queue, graph, node_id, available_size, GraphicPassNodeInput::ShaderResourceView(i).to_string(), outputs_cache)?.try_to_texture()?;
+        // Older graphs might have been built with less inputs.
+        let srv_count = self.get_shader_resource_view_count(graph, node_id);
+        for i in 0..srv_count {
+            let srv = self.evaluate_input_as(ctx, graph, node_id, GraphicPassNodeInput::ShaderResourceView(i).to_string(), outputs_cache, ProtosValueType::try_to_texture)?;
             pass.set_shader_resource_view(i, srv);
         }
+        pass.set_shader_resource_view_count(srv_count);
         let num_attachment = 1;
+        let (width, height) = pass.get_render_target_size().resolve(ctx.target_size)?;
         for i in 0..num_attachment {
             // Should gather these informations from a evaluate_output. -> reach output node, read its data & select informations.
-            pass.set_render_target(i, available_size.x as u32, available_size.y as u32);
+            pass.set_render_target(i, width, height);
         }
         {
-            let vertex = self.evaluate_input(device, queue, graph, node_id, available_size, GraphicPassNodeInput::VertexShader.to_string(), outputs_cache)?.try_to_shader()?;
+            let vertex = self.evaluate_input_as(ctx, graph, node_id, GraphicPassNodeInput::VertexShader.to_string(), outputs_cache, ProtosValueType::try_to_shader)?;
             if let Some(vert) = vertex{
                 pass.set_vertex_shader(vert);
             } else {
-                anyhow::bail!("Invalid vertex shader")
+                anyhow::bail!(ProtosError::missing_input(GraphicPassNodeInput::VertexShader))
             }
         }
         {
-            let fragment = self.evaluate_input(device, queue, graph, node_id, available_size, GraphicPassNodeInput::FragmentShader.to_string(), outputs_cache)?.try_to_shader()?;
+            let fragment = self.evaluate_input_as(ctx, graph, node_id, GraphicPassNodeInput::FragmentShader.to_string(), outputs_cache, ProtosValueType::try_to_shader)?;
             if let Some(frag) = fragment{
                 pass.set_fragment_shader(frag);
             } else {
-                anyhow::bail!("Invalid fragment shader")
+                anyhow::bail!(ProtosError::missing_input(GraphicPassNodeInput::FragmentShader))
             }
         }
         
         // Will call create if not created already.
-        pass.update_data(device, queue)?;
+        pass.update_dependencies();
+        pass.update_data(ctx.device, ctx.queue)?;
+        pass.update_constants(ctx.queue, ctx.frame.time, ctx.frame.delta_time, ctx.frame.index);
         
         for i in 0..num_attachment {
             // Output graphic pass will populate output. need to ensure data is created already.
@@ -145,16 +166,79 @@ impl ProtosNode for GraphicPassNode {
     }
     fn record(
         &self,
-        device: &wgpu::Device,
-        cmd: &mut wgpu::CommandEncoder,
+        ctx: &mut RecordContext<'_>,
         graph: &ProtosGraph,
         node_id: NodeId,
-        outputs_cache: &mut OutputsCache
+        outputs_cache: &OutputsCache
     ) -> anyhow::Result<()> {
-        for i in 0..1 {
-            self.record_input(device, cmd, graph, node_id, GraphicPassNodeInput::ShaderResourceView(i).to_string(), outputs_cache)?;
+        for i in 0..self.get_shader_resource_view_count(graph, node_id) {
+            self.record_input(ctx, graph, node_id, GraphicPassNodeInput::ShaderResourceView(i).to_string(), outputs_cache)?;
         }
         let pass = self.handle.lock().unwrap();
-        pass.record_data(device, cmd)
+        let queries = ctx.profiler.as_deref_mut().map(|profiler| profiler.render_pass_queries(node_id)).unwrap_or_default();
+        let result = pass.record_data_profiled(ctx.cmd, queries);
+        if result.is_err() {
+            if let Some(profiler) = ctx.profiler.as_deref_mut() {
+                profiler.release(&node_id);
+            }
+        }
+        result
+    }
+}
+
+impl ProtosNodeUi for GraphicPassNode {
+    fn ui(&self, _graph: &ProtosGraph, _node_id: NodeId, ui: &mut egui::Ui) -> bool {
+        let changed = gfx::visit_resource_mut(&self.handle, |pass| {
+            pass.visit_desc_mut(|desc| {
+                let mut changed = false;
+                let relative = gfx::RenderTargetSize::default();
+                let fixed = gfx::RenderTargetSize::Fixed { width: 512, height: 512 };
+                let expression = gfx::RenderTargetSize::Expression { width: String::from("bb.w"), height: String::from("bb.h") };
+                egui::ComboBox::from_label("Size")
+                    .selected_text(desc.render_target_size.get_size_name())
+                    .show_ui(ui, |ui| {
+                        for size in [relative, fixed, expression] {
+                            let selected = std::mem::discriminant(&size) == std::mem::discriminant(&desc.render_target_size);
+                            if ui.selectable_label(selected, size.get_size_name()).clicked() && !selected {
+                                desc.render_target_size = size;
+                                changed = true;
+                            }
+                        }
+                    });
+                match &mut desc.render_target_size {
+                    gfx::RenderTargetSize::Relative { percent } => {
+                        changed |= ui.add(egui::DragValue::new(percent).clamp_range(1..=400).suffix(" % of backbuffer")).changed();
+                    },
+                    gfx::RenderTargetSize::Fixed { width, height } => {
+                        ui.horizontal(|ui| {
+                            changed |= ui.add(egui::DragValue::new(width).clamp_range(1..=8192)).changed();
+                            ui.label("x");
+                            changed |= ui.add(egui::DragValue::new(height).clamp_range(1..=8192)).changed();
+                        });
+                    },
+                    gfx::RenderTargetSize::Expression { width, height } => {
+                        for (label, expression) in [("Width", width), ("Height", height)] {
+                            ui.horizontal(|ui| {
+                                ui.label(label);
+                                changed |= ui.add(egui::TextEdit::singleline(expression).hint_text("bb.w / 2"))
+                                    .on_hover_text("Pixels from the backbuffer size bb.w & bb.h, with + - * /, parentheses, min & max")
+                                    .changed();
+                            });
+                            if let Err(err) = gfx::check_size_expression(expression) {
+                                ui.colored_label(ui.visuals().error_fg_color, format!("{:#}", err));
+                            }
+                        }
+                    },
+                }
+                changed
+            })
+        });
+        if let Some(counters) = self.get_draw_counters() {
+            ui.weak(format!("▲ {} triangles", counters.triangles)).on_hover_text(format!(
+                "{} vertices\n{} indices\n{} instances",
+                counters.vertices, counters.indices, counters.instances
+            ));
+        }
+        changed
     }
 }
\ No newline at end of file
@@ -1,10 +1,9 @@
 use core::fmt;
 use std::path::PathBuf;
 
-use egui::Vec2;
 use egui_node_graph::NodeId;
 
-use crate::{gfx::{self, MeshShape, MeshSource}, graph::{core::ProtosGraph, node::OutputsCache, ProtosDataType, ProtosNode, ProtosValueType}};
+use crate::{gfx::{self, MeshShape, MeshSource}, graph::{core::ProtosGraph, evaluation::{EvaluationContext, RecordContext}, node::OutputsCache, ProtosDataType, ProtosCategoryType, ProtosNode, ProtosNodeUi, ProtosValueType}};
 
 #[derive(Default, Clone)]
 #[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
@@ -23,14 +22,53 @@ impl fmt::Display for MeshNodeOutput {
     }
 }
 
+impl MeshNode {
+    pub fn from_shape(shape: MeshShape) -> Self {
+        Self {
+            mesh: gfx::ResourceHandle::new(gfx::Mesh::from_shape(shape))
+        }
+    }
+}
+
 impl ProtosNode for MeshNode {
     fn get_name(&self) -> &str {
         "Mesh"
     }
+    fn get_category(&self) -> ProtosCategoryType {
+        ProtosCategoryType::Geometry
+    }
+    fn get_description(&self) -> &str {
+        "Geometry drawn by graphic passes."
+    }
     fn build(&self, graph: &mut ProtosGraph, node_id: NodeId) {
         graph.add_output_param(node_id, MeshNodeOutput::Geometry.to_string(), ProtosDataType::Mesh);
     }
-    fn ui(&self, _graph: &ProtosGraph, _node_id: NodeId, ui: &mut egui::Ui) {
+    fn evaluate(
+        &self, 
+        ctx: &EvaluationContext<'_>,
+        graph: &ProtosGraph,
+        node_id: NodeId,
+        outputs_cache: &mut OutputsCache
+    ) -> anyhow::Result<()> {
+        
+        let mut mesh = self.mesh.lock().unwrap();
+        mesh.update_data(ctx.device, ctx.queue)?;
+        self.populate_output(graph, node_id, MeshNodeOutput::Geometry.to_string(), ProtosValueType::Mesh(Some(self.mesh.clone())), outputs_cache);
+        Ok(())
+    }
+    fn record(
+        &self,
+        _ctx: &mut RecordContext<'_>,
+        _graph: &ProtosGraph,
+        _node_id: NodeId,
+        _outputs_cache: &OutputsCache
+    ) -> anyhow::Result<()> {
+        Ok(()) // Nothing to record here
+    }
+}
+
+impl ProtosNodeUi for MeshNode {
+    fn ui(&self, _graph: &ProtosGraph, _node_id: NodeId, ui: &mut egui::Ui) -> bool {
         gfx::visit_resource_mut(&self.mesh, |mesh| {
             mesh.visit_desc_mut(|desc| {
                 // Select shape
@@ -49,11 +87,13 @@ impl ProtosNode for MeshNode {
                     MeshSource::Shape(shape) => {
                         let default_sphere = MeshShape::default_sphere();
                         let default_cube = MeshShape::default_cube();
+                        let default_quad = MeshShape::default_quad();
                         egui::ComboBox::from_label("Shape")
                             .selected_text(format!("{:?}", shape.get_shape_name()))
                             .show_ui(ui, |ui| {
                                 changed |= ui.selectable_value(shape, default_sphere, default_sphere.get_shape_name()).changed();
                                 changed |= ui.selectable_value(shape, default_cube, default_cube.get_shape_name()).changed();
+                                changed |= ui.selectable_value(shape, default_quad, default_quad.get_shape_name()).changed();
                             });
                         match shape {
                             MeshShape::Sphere{ ring_count, segment_count, radius } => {
@@ -64,37 +104,15 @@ impl ProtosNode for MeshNode {
                             MeshShape::Cube{ size } => {
                                 changed |= ui.add(egui::Slider::new(size, 0.1..=10.0)).changed();
                             },
+                            MeshShape::Quad{ size } => {
+                                changed |= ui.add(egui::Slider::new(size, 0.1..=10.0)).changed();
+                            },
                         }
                     }
                     _ => {}
                 }
                 changed
-            });
-        });
-    }
-    fn evaluate(
-        &self, 
-        device: &wgpu::Device,
-        queue: &wgpu::Queue,
-        graph: &ProtosGraph,
-        node_id: NodeId,
-        _available_size: Vec2,
-        outputs_cache: &mut OutputsCache
-    ) -> anyhow::Result<()> {
-        
-        let mut mesh = self.mesh.lock().unwrap();
-        mesh.update_data(device, queue)?;
-        self.populate_output(graph, node_id, MeshNodeOutput::Geometry.to_string(), ProtosValueType::Mesh(Some(self.mesh.clone())), outputs_cache);
-        Ok(())
-    }
-    fn record(
-        &self,
-        _device: &wgpu::Device,
-        _cmd: &mut wgpu::CommandEncoder,
-        _graph: &ProtosGraph,
-        _node_id: NodeId,
-        _outputs_cache: &mut OutputsCache
-    ) -> anyhow::Result<()> {
-        Ok(()) // Nothing to record here
+            })
+        })
     }
 }
\ No newline at end of file
@@ -7,6 +7,8 @@ mod compute_pass_node;
 mod camera_node;
 mod shader_node;
 mod mesh_node;
+mod feedback_node;
+mod subgraph_node;
 
 pub use self::backbuffer_pass_node::BackbufferPassNode;
 pub use self::graphic_pass_node::GraphicPassNode;
@@ -16,4 +18,6 @@ pub use self::buffer_node::BufferNode;
 pub use self::texture_file_node::TextureFileNode;
 pub use self::texture_resource_node::TextureResourceNode;
 pub use self::mesh_node::MeshNode;
-pub use self::shader_node::ShaderNode;
\ No newline at end of file
+pub use self::shader_node::ShaderNode;
+pub use self::feedback_node::FeedbackNode;
+pub use self::subgraph_node::{SubgraphNode, GroupInputNode, GroupOutputNode};
\ No newline at end of file
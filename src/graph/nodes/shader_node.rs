@@ -1,9 +1,9 @@
 use core::fmt;
+use std::path::PathBuf;
 
-use egui::Vec2;
 use egui_node_graph::NodeId;
 
-use crate::{gfx, graph::{core::ProtosGraph, node::OutputsCache, ProtosDataType, ProtosNode, ProtosValueType}};
+use crate::{gfx::{self, ShaderLanguage, ShaderStage}, graph::{core::ProtosGraph, evaluation::{EvaluationContext, RecordContext}, node::OutputsCache, ProtosDataType, ProtosCategoryType, ProtosNode, ProtosNodeUi, ProtosValueType}};
 
 #[derive(Default, Clone)]
 #[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
@@ -23,57 +23,38 @@ impl fmt::Display for ShaderNodeOutput {
     }
 }
 
+impl ShaderNode {
+    pub fn new(language: ShaderLanguage, source: String) -> Self {
+        Self {
+            shader: gfx::ResourceHandle::new(gfx::Shader::from_source(language, source))
+        }
+    }
+}
 
 impl ProtosNode for ShaderNode {
     fn get_name(&self) -> &str {
         "Shader"
     }
+    fn get_category(&self) -> ProtosCategoryType {
+        ProtosCategoryType::Shaders
+    }
+    fn get_description(&self) -> &str {
+        "Vertex & fragment shader compiled from source."
+    }
     fn build(&self, graph: &mut ProtosGraph, node_id: NodeId) {
         graph.add_output_param(node_id, ShaderNodeOutput::VertexShader.to_string(), ProtosDataType::Shader);
         graph.add_output_param(node_id, ShaderNodeOutput::FragmentShader.to_string(), ProtosDataType::Shader);
     }
-    fn ui(&self, _graph: &ProtosGraph, _node_id: NodeId, ui: &mut egui::Ui) {
-        gfx::visit_resource_mut(&self.shader, |shader| {
-            shader.visit_desc_mut(|desc| {
-                let language = "rs";
-                let theme = egui_extras::syntax_highlighting::CodeTheme::from_memory(ui.ctx());
-                let mut layouter = |ui: &egui::Ui, string: &str, wrap_width: f32| {
-                    let mut layout_job =
-                        egui_extras::syntax_highlighting::highlight(ui.ctx(), &theme, string, language);
-                    layout_job.wrap.max_width = wrap_width;
-                    ui.fonts(|f| f.layout_job(layout_job))
-                };
-        
-                egui::ScrollArea::vertical().show(ui, |ui| {
-                    ui.add(
-                        egui::TextEdit::multiline(&mut desc.shader)
-                            .font(egui::TextStyle::Monospace) // for cursor height
-                            .code_editor()
-                            .desired_rows(10)
-                            .lock_focus(true)
-                            .desired_width(f32::INFINITY)
-                            .layouter(&mut layouter),
-                    );
-                });
-                //egui_extras::syntax_highlighting::code_view_ui(ui, &theme, desc.shader.as_str(), language);
-                //ui.text_edit_multiline(&mut desc.shader).changed()
-                true
-            });
-        });
-    }
     fn evaluate(
         &self, 
-        device: &wgpu::Device,
-        queue: &wgpu::Queue,
+        ctx: &EvaluationContext<'_>,
         graph: &ProtosGraph,
         node_id: NodeId,
-        _available_size: Vec2,
         outputs_cache: &mut OutputsCache
     ) -> anyhow::Result<()> {
         
-        let mut mesh = self.shader.lock().unwrap();
-        // TODO set path & shape ?
-        mesh.update_data(device, queue)?;
+        let mut shader = self.shader.lock().unwrap();
+        shader.update_data(ctx.device, ctx.queue)?;
         // TODO workaround this, having a select for shader type ? Or two shader node...
         self.populate_output(graph, node_id, ShaderNodeOutput::VertexShader.to_string(), ProtosValueType::Shader(Some(self.shader.clone())), outputs_cache);
         self.populate_output(graph, node_id, ShaderNodeOutput::FragmentShader.to_string(), ProtosValueType::Shader(Some(self.shader.clone())), outputs_cache);
@@ -81,12 +62,73 @@ impl ProtosNode for ShaderNode {
     }
     fn record(
         &self,
-        _device: &wgpu::Device,
-        _cmd: &mut wgpu::CommandEncoder,
+        _ctx: &mut RecordContext<'_>,
         _graph: &ProtosGraph,
         _node_id: NodeId,
-        _outputs_cache: &mut OutputsCache
+        _outputs_cache: &OutputsCache
     ) -> anyhow::Result<()> {
         Ok(()) // Nothing to record here
     }
+}
+
+impl ProtosNodeUi for ShaderNode {
+    fn ui(&self, _graph: &ProtosGraph, _node_id: NodeId, ui: &mut egui::Ui) -> bool {
+        gfx::visit_resource_mut(&self.shader, |shader| {
+            shader.visit_desc_mut(|desc| {
+                let mut changed = false;
+                let wgsl = ShaderLanguage::Wgsl;
+                let glsl = ShaderLanguage::Glsl(ShaderStage::Fragment);
+                let spirv = ShaderLanguage::SpirV(PathBuf::from(""));
+                egui::ComboBox::from_label("Language")
+                    .selected_text(desc.language.get_language_name())
+                    .show_ui(ui, |ui| {
+                        changed |= ui.selectable_value(&mut desc.language, wgsl.clone(), wgsl.get_language_name()).changed();
+                        changed |= ui.selectable_value(&mut desc.language, glsl.clone(), glsl.get_language_name()).changed();
+                        changed |= ui.selectable_value(&mut desc.language, spirv.clone(), spirv.get_language_name()).changed();
+                    });
+                match &mut desc.language {
+                    ShaderLanguage::Glsl(stage) => {
+                        egui::ComboBox::from_label("Stage")
+                            .selected_text(stage.get_stage_name())
+                            .show_ui(ui, |ui| {
+                                for value in [ShaderStage::Vertex, ShaderStage::Fragment, ShaderStage::Compute] {
+                                    changed |= ui.selectable_value(stage, value, value.get_stage_name()).changed();
+                                }
+                            });
+                    }
+                    ShaderLanguage::SpirV(path) => {
+                        let mut path_string = path.to_string_lossy().to_string();
+                        if ui.text_edit_singleline(&mut path_string).changed() {
+                            *path = PathBuf::from(path_string);
+                            changed = true;
+                        }
+                    }
+                    ShaderLanguage::Wgsl => {}
+                }
+                if desc.language.has_source() {
+                    let language = desc.language.get_syntax_name().to_owned();
+                    let theme = egui_extras::syntax_highlighting::CodeTheme::from_memory(ui.ctx());
+                    let mut layouter = |ui: &egui::Ui, string: &str, wrap_width: f32| {
+                        let mut layout_job =
+                            egui_extras::syntax_highlighting::highlight(ui.ctx(), &theme, string, language.as_str());
+                        layout_job.wrap.max_width = wrap_width;
+                        ui.fonts(|f| f.layout_job(layout_job))
+                    };
+
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        changed |= ui.add(
+                            egui::TextEdit::multiline(&mut desc.shader)
+                                .font(egui::TextStyle::Monospace) // for cursor height
+                                .code_editor()
+                                .desired_rows(10)
+                                .lock_focus(true)
+                                .desired_width(f32::INFINITY)
+                                .layouter(&mut layouter),
+                        ).changed();
+                    });
+                }
+                changed
+            })
+        })
+    }
 }
\ No newline at end of file
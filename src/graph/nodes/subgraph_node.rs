@@ -0,0 +1,164 @@
+use std::sync::{Arc, Mutex};
+
+use egui_node_graph::NodeId;
+
+use crate::graph::{core::ProtosGraph, evaluation::{EvaluationContext, RecordContext}, node::OutputsCache, subgraph::Subgraph, ProtosCategoryType, ProtosNode, ProtosNodeUi, ProtosNodeTemplate};
+
+// Composite node, its content is inlined in the graph before evaluation.
+#[derive(Default, Clone)]
+#[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
+pub struct SubgraphNode {
+    name: String,
+    // Shared by the clones of the node made for evaluation, saved once per project like resources.
+    #[cfg_attr(feature = "persistence", serde(with = "crate::gfx::shared_resource"))]
+    subgraph: Arc<Mutex<Subgraph>>,
+}
+
+impl SubgraphNode {
+    pub fn new(name: String, subgraph: Subgraph) -> Self {
+        Self {
+            name,
+            subgraph: Arc::new(Mutex::new(subgraph)),
+        }
+    }
+    pub fn find(graph: &ProtosGraph, node_id: NodeId) -> Option<&Self> {
+        match graph.nodes.get(node_id).map(|node| &node.user_data.template) {
+            Some(ProtosNodeTemplate::Subgraph(node)) => Some(node),
+            _ => None,
+        }
+    }
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+    pub fn subgraph(&self) -> &Arc<Mutex<Subgraph>> {
+        &self.subgraph
+    }
+    // Copy with its own subgraph & resources, so that instances do not share anything.
+    #[cfg(feature = "persistence")]
+    pub fn deep_copy(&self, name: &str) -> anyhow::Result<Self> {
+        let json = crate::gfx::with_resource_scope(|| serde_json::to_string(&*self.subgraph.lock().unwrap()))?;
+        let subgraph = crate::gfx::with_resource_scope(|| serde_json::from_str(json.as_str()))?;
+        Ok(Self::new(name.to_string(), subgraph))
+    }
+}
+
+impl ProtosNode for SubgraphNode {
+    fn get_name(&self) -> &str {
+        self.name.as_str()
+    }
+    fn get_category(&self) -> ProtosCategoryType {
+        ProtosCategoryType::Utility
+    }
+    fn get_description(&self) -> &str {
+        "Nodes grouped into a single one, open it to edit its content."
+    }
+    fn build(&self, graph: &mut ProtosGraph, node_id: NodeId) {
+        let subgraph = self.subgraph.lock().unwrap();
+        for port in &subgraph.inputs {
+            graph.add_input_param(node_id, port.name.clone(), port.typ.clone(), port.typ.default_value(), port.typ.input_kind(), true);
+        }
+        for port in &subgraph.outputs {
+            graph.add_output_param(node_id, port.name.clone(), port.typ.clone());
+        }
+    }
+    fn evaluate(
+        &self,
+        _ctx: &EvaluationContext<'_>,
+        _graph: &ProtosGraph,
+        _node_id: NodeId,
+        _outputs_cache: &mut OutputsCache
+    ) -> anyhow::Result<()> {
+        anyhow::bail!("Subgraph {} must be flattened before evaluation", self.name)
+    }
+    fn record(
+        &self,
+        _ctx: &mut RecordContext<'_>,
+        _graph: &ProtosGraph,
+        _node_id: NodeId,
+        _outputs_cache: &OutputsCache
+    ) -> anyhow::Result<()> {
+        anyhow::bail!("Subgraph {} must be flattened before recording", self.name)
+    }
+}
+
+impl ProtosNodeUi for SubgraphNode {}
+
+// Exposes the inputs of the subgraph node to the nodes of the subgraph.
+#[derive(Default, Clone)]
+#[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
+pub struct GroupInputNode {}
+
+// Exposes outputs of the nodes of the subgraph as outputs of the subgraph node.
+#[derive(Default, Clone)]
+#[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
+pub struct GroupOutputNode {}
+
+impl ProtosNode for GroupInputNode {
+    fn get_name(&self) -> &str {
+        "Group Input"
+    }
+    fn get_category(&self) -> ProtosCategoryType {
+        ProtosCategoryType::Utility
+    }
+    fn get_description(&self) -> &str {
+        "Inputs of the subgraph, as connected to the subgraph node."
+    }
+    fn build(&self, _graph: &mut ProtosGraph, _node_id: NodeId) {
+        // Ports are added when grouping nodes.
+    }
+    fn evaluate(
+        &self,
+        _ctx: &EvaluationContext<'_>,
+        _graph: &ProtosGraph,
+        _node_id: NodeId,
+        _outputs_cache: &mut OutputsCache
+    ) -> anyhow::Result<()> {
+        anyhow::bail!("Group input is only evaluated within a subgraph")
+    }
+    fn record(
+        &self,
+        _ctx: &mut RecordContext<'_>,
+        _graph: &ProtosGraph,
+        _node_id: NodeId,
+        _outputs_cache: &OutputsCache
+    ) -> anyhow::Result<()> {
+        Ok(()) // Nothing to record here
+    }
+}
+
+impl ProtosNodeUi for GroupInputNode {}
+
+impl ProtosNode for GroupOutputNode {
+    fn get_name(&self) -> &str {
+        "Group Output"
+    }
+    fn get_category(&self) -> ProtosCategoryType {
+        ProtosCategoryType::Utility
+    }
+    fn get_description(&self) -> &str {
+        "Outputs of the subgraph, as exposed by the subgraph node."
+    }
+    fn build(&self, _graph: &mut ProtosGraph, _node_id: NodeId) {
+        // Ports are added when grouping nodes.
+    }
+    fn evaluate(
+        &self,
+        _ctx: &EvaluationContext<'_>,
+        _graph: &ProtosGraph,
+        _node_id: NodeId,
+        _outputs_cache: &mut OutputsCache
+    ) -> anyhow::Result<()> {
+        anyhow::bail!("Group output is only evaluated within a subgraph")
+    }
+    fn record(
+        &self,
+        _ctx: &mut RecordContext<'_>,
+        _graph: &ProtosGraph,
+        _node_id: NodeId,
+        _outputs_cache: &OutputsCache
+    ) -> anyhow::Result<()> {
+        Ok(()) // Nothing to record here
+    }
+}
+
+impl ProtosNodeUi for GroupOutputNode {}
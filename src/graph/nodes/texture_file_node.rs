@@ -1,10 +1,9 @@
 use core::fmt;
 use std::{path::PathBuf, str::FromStr};
 
-use egui::Vec2;
 use egui_node_graph::{InputParamKind, NodeId};
 
-use crate::{gfx, graph::{core::ProtosGraph, node::OutputsCache, ProtosDataType, ProtosNode, ProtosValueType}};
+use crate::{gfx, graph::{core::ProtosGraph, evaluation::{EvaluationContext, RecordContext}, node::OutputsCache, ProtosDataType, ProtosCategoryType, ProtosNode, ProtosNodeUi, ProtosValueType}};
 
 #[derive(Default, Clone)]
 #[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
@@ -37,6 +36,12 @@ impl ProtosNode for TextureFileNode {
     fn get_name(&self) -> &str {
         "FileTexture"
     }
+    fn get_category(&self) -> ProtosCategoryType {
+        ProtosCategoryType::Inputs
+    }
+    fn get_description(&self) -> &str {
+        "Texture loaded from an image file."
+    }
     fn build(&self, graph: &mut ProtosGraph, node_id: NodeId) {
         graph.add_input_param(
             node_id.clone(),
@@ -51,35 +56,77 @@ impl ProtosNode for TextureFileNode {
             TextureFileNodeOutput::Texture.to_string(),
             ProtosDataType::Texture
         );
-    }
-    fn ui(&self, _graph: &ProtosGraph, _node_id: NodeId, _ui: &mut egui::Ui) {
-        
     }
     fn evaluate(
         &self, 
-        device: &wgpu::Device,
-        queue: &wgpu::Queue,
+        ctx: &EvaluationContext<'_>,
         graph: &ProtosGraph,
         node_id: NodeId,
-        available_size: Vec2,
         outputs_cache: &mut OutputsCache
     ) -> anyhow::Result<()> {
-        let path = self.evaluate_input(device, queue, graph, node_id, available_size, TextureFileNodeInput::Path.to_string(), outputs_cache)?.try_to_string()?;
+        let path = self.evaluate_input_as(ctx, graph, node_id, TextureFileNodeInput::Path.to_string(), outputs_cache, ProtosValueType::try_to_string)?;
         let mut texture = self.handle.lock().unwrap();
-        texture.set_path(PathBuf::from_str(path.as_str())?);
-        texture.update_data(device, queue)?;
+        if texture.get_file_name().is_none() {
+            texture.set_path(PathBuf::from_str(path.as_str())?);
+        }
+        texture.update_data(ctx.device, ctx.queue)?;
         self.populate_output(graph, node_id, TextureFileNodeOutput::Texture.to_string(), ProtosValueType::Texture(Some(self.handle.clone())), outputs_cache);
 
         Ok(())
     }
     fn record(
         &self,
-        _device: &wgpu::Device,
-        _cmd: &mut wgpu::CommandEncoder,
+        _ctx: &mut RecordContext<'_>,
         _graph: &ProtosGraph,
         _node_id: NodeId,
-        _outputs_cache: &mut OutputsCache
+        _outputs_cache: &OutputsCache
     ) -> anyhow::Result<()> {
         Ok(()) // Nothing to record here
     }
+}
+
+impl ProtosNodeUi for TextureFileNode {
+    fn ui(&self, _graph: &ProtosGraph, _node_id: NodeId, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+        let mut texture = self.handle.lock().unwrap();
+        // An embedded file is used instead of the path.
+        if let Some(name) = texture.get_file_name().map(String::from) {
+            ui.horizontal(|ui| {
+                ui.label(format!("Embedded : {}", name));
+                if ui.small_button("✖").on_hover_text("Use the path instead").clicked() {
+                    texture.clear_file();
+                    changed = true;
+                }
+            });
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if ui.button("Embed file...").clicked() {
+            if let Some(path) = rfd::FileDialog::new().add_filter("Image", &["png", "jpg", "jpeg", "bmp", "tga", "hdr"]).pick_file() {
+                match std::fs::read(&path) {
+                    Ok(bytes) => {
+                        let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+                        texture.set_file(name, bytes);
+                        changed = true;
+                    }
+                    Err(err) => log::error!("Failed to read {} : {}", path.display(), err),
+                }
+            }
+        }
+        // Browsers can't read the path, the file has to be uploaded.
+        #[cfg(target_arch = "wasm32")]
+        if ui.button("Upload...").clicked() {
+            let handle = self.handle.clone();
+            let result = crate::web::upload("image/*", move |result| match result {
+                Ok((name, bytes)) => {
+                    handle.lock().unwrap().set_file(name, bytes);
+                    crate::web::notify_file_loaded();
+                }
+                Err(err) => log::error!("Failed to upload texture : {:#}", err),
+            });
+            if let Err(err) = result {
+                log::error!("{:#}", err);
+            }
+        }
+        changed
+    }
 }
\ No newline at end of file
@@ -1,9 +1,8 @@
 use core::fmt;
 
-use egui::Vec2;
 use egui_node_graph::{InputParamKind, NodeId};
 
-use crate::{gfx, graph::{core::ProtosGraph, node::OutputsCache, ProtosDataType, ProtosNode, ProtosValueType}};
+use crate::{gfx, graph::{core::ProtosGraph, evaluation::{EvaluationContext, RecordContext}, node::OutputsCache, ProtosDataType, ProtosCategoryType, ProtosNode, ProtosNodeUi, ProtosValueType}};
 
 #[derive(Default, Clone)]
 #[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
@@ -36,6 +35,12 @@ impl ProtosNode for TextureResourceNode {
     fn get_name(&self) -> &str {
         "ResourceTexture"
     }
+    fn get_category(&self) -> ProtosCategoryType {
+        ProtosCategoryType::Resources
+    }
+    fn get_description(&self) -> &str {
+        "Empty texture created with the given size."
+    }
     fn build(&self, graph: &mut ProtosGraph, node_id: NodeId) {
         graph.add_input_param(
             node_id.clone(),
@@ -50,36 +55,32 @@ impl ProtosNode for TextureResourceNode {
             TextureResourceNodeOutput::Texture.to_string(),
             ProtosDataType::Texture
         );
-    }
-    fn ui(&self, _graph: &ProtosGraph, _node_id: NodeId, _ui: &mut egui::Ui) {
-        
     }
     fn evaluate(
         &self, 
-        device: &wgpu::Device,
-        queue: &wgpu::Queue,
+        ctx: &EvaluationContext<'_>,
         graph: &ProtosGraph,
         node_id: NodeId,
-        available_size: Vec2,
         outputs_cache: &mut OutputsCache
     ) -> anyhow::Result<()> {
-        let dimensions = self.evaluate_input(device, queue, graph, node_id, available_size, TextureResourceNodeInput::Dimensions.to_string(), outputs_cache)?.try_to_vec2()?;
+        let dimensions = self.evaluate_input_as(ctx, graph, node_id, TextureResourceNodeInput::Dimensions.to_string(), outputs_cache, ProtosValueType::try_to_vec2)?;
         let mut texture = self.handle.lock().unwrap();
         texture.set_width(dimensions[0] as u32);
         texture.set_height(dimensions[1] as u32);
-        texture.update_data(device, queue)?;
+        texture.update_data(ctx.device, ctx.queue)?;
         self.populate_output(graph, node_id, TextureResourceNodeOutput::Texture.to_string(), ProtosValueType::Texture(Some(self.handle.clone())), outputs_cache);
         
         Ok(())
     }
     fn record(
         &self,
-        _device: &wgpu::Device,
-        _cmd: &mut wgpu::CommandEncoder,
+        _ctx: &mut RecordContext<'_>,
         _graph: &ProtosGraph,
         _node_id: NodeId,
-        _outputs_cache: &mut OutputsCache
+        _outputs_cache: &OutputsCache
     ) -> anyhow::Result<()> {
         Ok(()) // Nothing to record here
     }
-}
\ No newline at end of file
+}
+
+impl ProtosNodeUi for TextureResourceNode {}
\ No newline at end of file
@@ -0,0 +1,94 @@
+use std::collections::{HashMap, HashSet};
+
+use egui_node_graph::{NodeId, OutputId};
+
+use crate::gfx;
+
+use super::{core::ProtosGraph, node::OutputsCache, FlatGraph, ProtosDataType, ProtosValueType};
+
+// Texture output of a node, identified by the path of the node from the top level graph & the output name.
+pub type PreviewKey = (Vec<NodeId>, String);
+
+// Thumbnails of the texture outputs of nodes, drawn in the node body, & resources opened in inspectors.
+#[derive(Default)]
+pub struct Previews {
+    enabled: HashSet<Vec<NodeId>>, // Nodes showing their thumbnails.
+    inspected: HashSet<PreviewKey>, // Outputs opened in an inspector, collected even without thumbnail.
+    textures: HashMap<PreviewKey, gfx::ResourceHandle<gfx::Texture>>, // Filled by the last evaluation.
+    buffers: HashMap<PreviewKey, gfx::ResourceHandle<gfx::Buffer>>, // Only the inspected ones.
+    texture_ids: HashMap<PreviewKey, egui::TextureId>, // Registered by the frontend.
+}
+
+impl Previews {
+    pub fn is_enabled(&self, path: &[NodeId]) -> bool {
+        self.enabled.contains(path)
+    }
+    pub fn set_enabled(&mut self, path: Vec<NodeId>, enabled: bool) {
+        if enabled {
+            self.enabled.insert(path);
+        } else {
+            self.enabled.remove(&path);
+        }
+    }
+    pub fn set_inspected(&mut self, key: PreviewKey, inspected: bool) {
+        if inspected {
+            self.inspected.insert(key);
+        } else {
+            self.inspected.remove(&key);
+        }
+    }
+    pub fn clear(&mut self) {
+        self.textures.clear();
+        self.buffers.clear();
+    }
+    // Gather the texture outputs of the enabled nodes from an evaluation of the flattened graph.
+    pub fn collect(&mut self, flat_graph: &FlatGraph, outputs_cache: &OutputsCache) {
+        for node_id in flat_graph.graph.iter_nodes() {
+            let path = match flat_graph.origin(node_id) {
+                Some(path) => path,
+                None => continue,
+            };
+            for (name, output_id) in &flat_graph.graph[node_id].outputs {
+                let key = (path.to_vec(), name.clone());
+                let inspected = self.inspected.contains(&key);
+                match outputs_cache.get(output_id) {
+                    Some(ProtosValueType::Texture(Some(texture))) if inspected || self.enabled.contains(path) => {
+                        self.textures.insert(key, texture.clone());
+                    }
+                    Some(ProtosValueType::Buffer(Some(buffer))) if inspected => {
+                        self.buffers.insert(key, buffer.clone());
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    pub fn textures(&self) -> impl Iterator<Item = (&PreviewKey, &gfx::ResourceHandle<gfx::Texture>)> {
+        self.textures.iter()
+    }
+    pub fn texture(&self, key: &PreviewKey) -> Option<&gfx::ResourceHandle<gfx::Texture>> {
+        self.textures.get(key)
+    }
+    pub fn buffer(&self, key: &PreviewKey) -> Option<&gfx::ResourceHandle<gfx::Buffer>> {
+        self.buffers.get(key)
+    }
+    pub fn texture_id(&self, key: &PreviewKey) -> Option<egui::TextureId> {
+        self.texture_ids.get(key).copied()
+    }
+    pub fn set_texture_id(&mut self, key: PreviewKey, id: egui::TextureId) {
+        self.texture_ids.insert(key, id);
+    }
+    // Forget the ids of textures which are not previewed anymore, returning them so that they can be freed.
+    pub fn take_unused_texture_ids(&mut self) -> Vec<egui::TextureId> {
+        let unused: Vec<PreviewKey> = self.texture_ids.keys().filter(|key| !self.textures.contains_key(*key)).cloned().collect();
+        unused.iter().filter_map(|key| self.texture_ids.remove(key)).collect()
+    }
+}
+
+// Node, name & type of an output of the graph.
+pub fn find_output(graph: &ProtosGraph, output_id: OutputId) -> Option<(NodeId, String, ProtosDataType)> {
+    let output = graph.outputs.get(output_id)?;
+    graph[output.node].outputs.iter()
+        .find(|(_, id)| *id == output_id)
+        .map(|(name, _)| (output.node, name.clone(), output.typ.clone()))
+}
@@ -1,4 +1,4 @@
-use egui_node_graph::{NodeId, UserResponseTrait};
+use egui_node_graph::{NodeId, OutputId, UserResponseTrait};
 
 /// The response type is used to encode side-effects produced when drawing a
 /// node in the graph. Most side-effects (creating new nodes, deleting existing
@@ -8,6 +8,13 @@ use egui_node_graph::{NodeId, UserResponseTrait};
 pub enum ProtosResponse {
     SetCurrentBackbuffer(NodeId),
     ClearCurrentBackbuffer,
+    ParameterChanged(NodeId), // A value edited from the node UI.
+    OpenSubgraph(NodeId),
+    InspectOutput(OutputId), // Open a texture or buffer output in an inspector window.
+    #[cfg(feature = "persistence")]
+    SaveSubgraph(NodeId), // Add the subgraph to the user library.
+    #[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+    ExportBackbuffer(NodeId), // Open the export window for a backbuffer pass.
 }
 
 impl UserResponseTrait for ProtosResponse {}
\ No newline at end of file
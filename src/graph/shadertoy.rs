@@ -0,0 +1,327 @@
+use std::collections::HashMap;
+
+use egui_node_graph::NodeId;
+
+use crate::gfx::{MeshShape, ShaderLanguage, ShaderStage};
+
+use super::{builder::GraphBuilder, nodes::{BackbufferPassNode, FeedbackNode, GraphicPassNode, MeshNode, ShaderNode}, ProtosEditorState, ProtosGraphState, ProtosNodeTemplate};
+
+// Number of iChannel available in shadertoy, matching graphic pass shader resource views.
+const CHANNEL_COUNT: usize = 4;
+
+// Vertex shader shared by every pass, only forwarding the fullscreen quad.
+const VERTEX_SHADER: &str = r#"struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+@vertex
+fn vs_main(model: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(model.position.xy, 0.0, 1.0);
+    out.tex_coords = model.tex_coords;
+    return out;
+}
+"#;
+
+// Declarations matching the graphic pass bindings, so that shadertoy uniforms are available.
+fn fragment_shader_header() -> String {
+    let mut header = String::from(r#"#version 450
+layout(location = 0) out vec4 protos_frag_color;
+
+layout(set = 2, binding = 0) uniform ProtosConstants {
+    vec4 protos_resolution;
+    float iTime;
+    float iTimeDelta;
+    int iFrame;
+    float protos_padding;
+};
+#define iResolution protos_resolution.xyz
+const vec4 iMouse = vec4(0.0);
+"#);
+    for channel in 0..CHANNEL_COUNT {
+        header.push_str(format!("layout(set = 0, binding = {0}) uniform texture2D iChannel{0}_texture;\n", channel).as_str());
+        header.push_str(format!("layout(set = 1, binding = {0}) uniform sampler iChannel{0}_sampler;\n", channel).as_str());
+        header.push_str(format!("#define iChannel{0} sampler2D(iChannel{0}_texture, iChannel{0}_sampler)\n", channel).as_str());
+    }
+    header
+}
+
+// Shadertoy origin is bottom left while ours is top left. Only the final image is flipped,
+// buffers are kept in our convention so that reading them with fragCoord / iResolution stays consistent.
+// Image alpha is ignored by shadertoy while buffers might store data in it.
+fn fragment_shader_footer(is_image: bool) -> String {
+    let (frag_coord, output) = if is_image {
+        ("vec2(gl_FragCoord.x, protos_resolution.y - gl_FragCoord.y)", "vec4(color.rgb, 1.0)")
+    } else {
+        ("gl_FragCoord.xy", "color")
+    };
+    format!(r#"
+void main() {{
+    vec4 color = vec4(0.0, 0.0, 0.0, 1.0);
+    mainImage(color, {});
+    protos_frag_color = {};
+}}
+"#, frag_coord, output)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ShadertoyInput {
+    Buffer(String), // Output id of the buffer read.
+    Texture(String), // Path of the texture on shadertoy.
+    Unsupported(String), // Type of the input.
+}
+
+#[derive(Debug, Clone)]
+struct ShadertoyPass {
+    name: String,
+    code: String,
+    output: Option<String>,
+    inputs: [Option<ShadertoyInput>; CHANNEL_COUNT],
+}
+
+// Ids are either strings or numbers depending on the export version.
+fn json_id(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(id) => Some(id.clone()),
+        serde_json::Value::Number(id) => Some(id.to_string()),
+        _ => None,
+    }
+}
+
+fn parse_pass(pass: &serde_json::Value) -> anyhow::Result<ShadertoyPass> {
+    let mut inputs: [Option<ShadertoyInput>; CHANNEL_COUNT] = Default::default();
+    for input in pass["inputs"].as_array().into_iter().flatten() {
+        let channel = match input["channel"].as_u64() {
+            Some(channel) if (channel as usize) < CHANNEL_COUNT => channel as usize,
+            _ => anyhow::bail!("Invalid channel for input {}", input),
+        };
+        let ctype = input["ctype"].as_str().or(input["type"].as_str()).unwrap_or_default();
+        inputs[channel] = Some(match ctype {
+            "buffer" => ShadertoyInput::Buffer(json_id(&input["id"]).ok_or(anyhow::anyhow!("Buffer input without id"))?),
+            "texture" => ShadertoyInput::Texture(input["src"].as_str().or(input["filepath"].as_str()).unwrap_or_default().to_string()),
+            other => ShadertoyInput::Unsupported(other.to_string()),
+        });
+    }
+    Ok(ShadertoyPass {
+        name: pass["name"].as_str().unwrap_or_default().to_string(),
+        code: pass["code"].as_str().ok_or(anyhow::anyhow!("Pass without code"))?.to_string(),
+        output: pass["outputs"].as_array().and_then(|outputs| outputs.first()).and_then(|output| json_id(&output["id"])),
+        inputs,
+    })
+}
+
+// Return the common code, the buffers in execution order and the image pass.
+fn parse_json(source: &str) -> anyhow::Result<(String, Vec<ShadertoyPass>, ShadertoyPass)> {
+    let root: serde_json::Value = serde_json::from_str(source)?;
+    // Exports are either the shader object, a list of shaders or the API response.
+    let shader = if root.is_array() {
+        &root[0]
+    } else if root.get("Shader").is_some() {
+        &root["Shader"]
+    } else {
+        &root
+    };
+    let passes = shader["renderpass"].as_array().ok_or(anyhow::anyhow!("No renderpass found in shadertoy json"))?;
+    let mut common = String::new();
+    let mut buffers = Vec::new();
+    let mut image = None;
+    for pass in passes {
+        match pass["type"].as_str().unwrap_or_default() {
+            "common" => common = pass["code"].as_str().unwrap_or_default().to_string(),
+            "buffer" => buffers.push(parse_pass(pass)?),
+            "image" => image = Some(parse_pass(pass)?),
+            other => log::warn!("Shadertoy pass of type {} is not supported, ignoring it.", other),
+        }
+    }
+    // Buffers A to D are executed in order.
+    buffers.sort_by(|lhs, rhs| lhs.name.cmp(&rhs.name));
+    match image {
+        Some(image) => Ok((common, buffers, image)),
+        None => anyhow::bail!("No image pass found in shadertoy json"),
+    }
+}
+
+/// Build a graph from a shadertoy, either a single `mainImage` GLSL snippet or
+/// a JSON export with multiple buffers.
+pub fn import_shadertoy(source: &str) -> anyhow::Result<(ProtosEditorState, ProtosGraphState)> {
+    let trimmed = source.trim_start();
+    let (common, buffers, image) = if trimmed.starts_with('{') || trimmed.starts_with('[') {
+        parse_json(source)?
+    } else {
+        (String::new(), Vec::new(), ShadertoyPass {
+            name: String::from("Image"),
+            code: source.to_string(),
+            output: None,
+            inputs: Default::default(),
+        })
+    };
+
+    let mut builder = GraphBuilder::new();
+
+    // Shared resources
+    let vertex_shader = builder.add_node(ProtosNodeTemplate::Shader(ShaderNode::new(ShaderLanguage::Wgsl, VERTEX_SHADER.to_string())), 0, 0);
+    let mesh = builder.add_node(ProtosNodeTemplate::Mesh(MeshNode::from_shape(MeshShape::fullscreen_quad())), 0, 1);
+
+    // Create all passes first so that inputs can reference any of them.
+    let header = fragment_shader_header();
+    let passes = buffers.iter().chain(std::iter::once(&image)).collect::<Vec<_>>();
+    let mut pass_nodes = Vec::new();
+    for (index, pass) in passes.iter().enumerate() {
+        let is_image = index == passes.len() - 1;
+        let code = format!("{}\n{}\n{}\n{}", header, common, pass.code, fragment_shader_footer(is_image));
+        let fragment_shader = builder.add_node(ProtosNodeTemplate::Shader(ShaderNode::new(ShaderLanguage::Glsl(ShaderStage::Fragment), code)), 2 * index + 1, 0);
+        let graphic_pass = builder.add_node(ProtosNodeTemplate::GraphicPass(GraphicPassNode::default()), 2 * index + 2, 1);
+        builder.connect(vertex_shader, "VertexShader", graphic_pass, "VertexShader")?;
+        builder.connect(fragment_shader, "FragmentShader", graphic_pass, "FragmentShader")?;
+        builder.connect(mesh, "Geometry", graphic_pass, "Geometry")?;
+        pass_nodes.push(graphic_pass);
+    }
+    let buffer_indices = buffers.iter().enumerate()
+        .filter_map(|(index, buffer)| buffer.output.clone().map(|output| (output, index)))
+        .collect::<HashMap<_, _>>();
+
+    // Wire channels
+    let mut feedback_nodes: HashMap<usize, NodeId> = HashMap::new();
+    for (index, pass) in passes.iter().enumerate() {
+        let is_image = index == passes.len() - 1;
+        for (channel, input) in pass.inputs.iter().enumerate() {
+            let srv = format!("SRV{}", channel);
+            match input {
+                Some(ShadertoyInput::Buffer(id)) => {
+                    let buffer_index = match buffer_indices.get(id) {
+                        Some(buffer_index) => *buffer_index,
+                        None => {
+                            log::warn!("{} iChannel{} read unknown buffer {}, leaving it unbound.", pass.name, channel, id);
+                            continue;
+                        }
+                    };
+                    if is_image || buffer_index < index {
+                        // Buffer already rendered this frame.
+                        builder.connect(pass_nodes[buffer_index], "RT0", pass_nodes[index], srv.as_str())?;
+                    } else {
+                        // Buffer not rendered yet, read its content from previous frame.
+                        let feedback = match feedback_nodes.get(&buffer_index) {
+                            Some(feedback) => *feedback,
+                            None => {
+                                let feedback = builder.add_node(ProtosNodeTemplate::Feedback(FeedbackNode::default()), 2 * buffer_index + 2, 2);
+                                builder.connect(pass_nodes[buffer_index], "RT0", feedback, "Input")?;
+                                feedback_nodes.insert(buffer_index, feedback);
+                                feedback
+                            }
+                        };
+                        builder.connect(feedback, "Previous", pass_nodes[index], srv.as_str())?;
+                    }
+                }
+                Some(ShadertoyInput::Texture(path)) => {
+                    // Paths such as /media/a/... are only served by shadertoy, the texture has to be downloaded & bound by hand.
+                    log::warn!("{} iChannel{} read texture {} which is not available locally, leaving it unbound.", pass.name, channel, path);
+                }
+                Some(ShadertoyInput::Unsupported(ctype)) => {
+                    log::warn!("{} iChannel{} use unsupported input {}, leaving it unbound.", pass.name, channel, ctype);
+                }
+                None => {}
+            }
+        }
+    }
+
+    // Output
+    let image_pass = *pass_nodes.last().expect("Image pass should be there");
+    let backbuffer = builder.add_node(ProtosNodeTemplate::BackbufferPass(BackbufferPassNode::default()), 2 * passes.len() + 1, 1);
+    builder.connect(image_pass, "RT0", backbuffer, "input")?;
+    builder.set_backbuffer(backbuffer);
+
+    Ok(builder.build())
+}
+
+#[cfg(test)]
+mod tests {
+    use egui_node_graph::NodeId;
+
+    use super::*;
+    use crate::graph::core::ProtosGraph;
+
+    // Export with Buffer B listed before Buffer A, and ids written as numbers like older exports.
+    const SHADER_JSON: &str = r#"{
+        "Shader": {
+            "renderpass": [
+                {
+                    "name": "Image", "type": "image", "code": "// Image",
+                    "inputs": [
+                        { "id": 258, "channel": 0, "type": "buffer" },
+                        { "id": 17, "channel": 1, "type": "texture", "src": "/media/a/noise.png" }
+                    ],
+                    "outputs": []
+                },
+                {
+                    "name": "Buffer B", "type": "buffer", "code": "// Buffer B",
+                    "inputs": [{ "id": "257", "channel": 0, "ctype": "buffer" }],
+                    "outputs": [{ "id": 258, "channel": 0 }]
+                },
+                {
+                    "name": "Buffer A", "type": "buffer", "code": "// Buffer A",
+                    "inputs": [
+                        { "id": 258, "channel": 0, "type": "buffer" },
+                        { "id": 257, "channel": 1, "type": "buffer" }
+                    ],
+                    "outputs": [{ "id": "257", "channel": 0 }]
+                },
+                { "name": "Common", "type": "common", "code": "// Common", "inputs": [], "outputs": [] }
+            ]
+        }
+    }"#;
+
+    // Node connected to the given input, if any.
+    fn source(graph: &ProtosGraph, node_id: NodeId, input: &str) -> Option<NodeId> {
+        let input_id = graph[node_id].get_input(input).ok()?;
+        graph.connection(input_id).map(|output_id| graph[output_id].node)
+    }
+
+    fn is_feedback(graph: &ProtosGraph, node_id: NodeId) -> bool {
+        matches!(graph[node_id].user_data.template, ProtosNodeTemplate::Feedback(_))
+    }
+
+    #[test]
+    fn buffers_sorted_by_name() {
+        let (common, buffers, image) = parse_json(SHADER_JSON).unwrap();
+        assert_eq!(common, "// Common");
+        assert_eq!(buffers.iter().map(|buffer| buffer.name.as_str()).collect::<Vec<_>>(), ["Buffer A", "Buffer B"]);
+        assert_eq!(image.name, "Image");
+    }
+
+    #[test]
+    fn string_and_numeric_ids() {
+        let (_, buffers, image) = parse_json(SHADER_JSON).unwrap();
+        assert_eq!(buffers[0].output.as_deref(), Some("257"));
+        assert_eq!(buffers[1].output.as_deref(), Some("258"));
+        assert_eq!(buffers[0].inputs[1], Some(ShadertoyInput::Buffer("257".to_string())));
+        assert_eq!(buffers[1].inputs[0], Some(ShadertoyInput::Buffer("257".to_string())));
+        assert_eq!(image.inputs[0], Some(ShadertoyInput::Buffer("258".to_string())));
+        assert_eq!(image.inputs[1], Some(ShadertoyInput::Texture("/media/a/noise.png".to_string())));
+    }
+
+    #[test]
+    fn direct_and_feedback_wiring() {
+        let (state, user_state) = import_shadertoy(SHADER_JSON).unwrap();
+        let graph = &state.graph;
+        let backbuffer = user_state.backbuffer_node.unwrap();
+        let image = source(graph, backbuffer, "input").unwrap();
+        // Buffers rendered earlier in the frame are read directly.
+        let buffer_b = source(graph, image, "SRV0").unwrap();
+        let buffer_a = source(graph, buffer_b, "SRV0").unwrap();
+        assert!(!is_feedback(graph, buffer_b) && !is_feedback(graph, buffer_a));
+        // Later buffers & the buffer itself are read from previous frame.
+        let feedback_b = source(graph, buffer_a, "SRV0").unwrap();
+        let feedback_a = source(graph, buffer_a, "SRV1").unwrap();
+        assert!(is_feedback(graph, feedback_b) && is_feedback(graph, feedback_a));
+        assert_eq!(source(graph, feedback_b, "Input"), Some(buffer_b));
+        assert_eq!(source(graph, feedback_a, "Input"), Some(buffer_a));
+        // Shadertoy textures are not available locally.
+        assert_eq!(source(graph, image, "SRV1"), None);
+    }
+}
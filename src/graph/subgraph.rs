@@ -0,0 +1,518 @@
+use std::collections::{HashMap, HashSet};
+
+use egui_node_graph::{InputId, NodeId, OutputId};
+
+use super::{core::ProtosGraph, error::ProtosError, nodes::{GroupInputNode, GroupOutputNode, SubgraphNode}, ProtosDataType, ProtosEditorState, ProtosNodeData, ProtosNodeTemplate, ProtosValueType};
+
+// Exposed input or output of a subgraph.
+#[derive(Clone)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+pub struct SubgraphPort {
+    pub name: String,
+    pub typ: ProtosDataType,
+}
+
+// Nodes grouped in a subgraph node. Inputs are fed through its group input node & outputs read from its group output node.
+#[derive(Default)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+pub struct Subgraph {
+    pub state: ProtosEditorState,
+    pub inputs: Vec<SubgraphPort>,
+    pub outputs: Vec<SubgraphPort>,
+}
+
+// Resources of the nodes are restored while deserializing the subgraph, it can be replaced as a whole.
+#[cfg(feature = "persistence")]
+impl crate::gfx::Restore for Subgraph {
+    fn restore(&mut self, snapshot: Self) {
+        *self = snapshot;
+    }
+}
+
+// A node copied to another graph, with the new id of each of its parameters.
+pub(super) struct CopiedNode {
+    pub node_id: NodeId,
+    pub inputs: HashMap<InputId, InputId>,
+    pub outputs: HashMap<OutputId, OutputId>,
+}
+
+pub(super) fn copy_node(source: &ProtosGraph, node_id: NodeId, destination: &mut ProtosGraph) -> CopiedNode {
+    let node = &source[node_id];
+    let user_data = ProtosNodeData { template: node.user_data.template.clone() };
+    let mut inputs = HashMap::new();
+    let mut outputs = HashMap::new();
+    let copy_id = destination.add_node(node.label.clone(), user_data, |graph, copy_id| {
+        for (name, input_id) in &node.inputs {
+            let input = &source[*input_id];
+            let copy = graph.add_input_param(copy_id, name.clone(), input.typ.clone(), input.value.clone(), input.kind, input.shown_inline);
+            inputs.insert(*input_id, copy);
+        }
+        for (name, output_id) in &node.outputs {
+            let copy = graph.add_output_param(copy_id, name.clone(), source[*output_id].typ.clone());
+            outputs.insert(*output_id, copy);
+        }
+    });
+    CopiedNode { node_id: copy_id, inputs, outputs }
+}
+
+fn unique_name(ports: &[SubgraphPort], name: &str) -> String {
+    let mut unique = name.to_string();
+    let mut index = 2;
+    while ports.iter().any(|port| port.name == unique) {
+        unique = format!("{} {}", name, index);
+        index += 1;
+    }
+    unique
+}
+
+// Move the given nodes to a new subgraph node, exposing the connections crossing the selection as its inputs & outputs.
+pub fn group_nodes(state: &mut ProtosEditorState, node_ids: &[NodeId]) -> anyhow::Result<NodeId> {
+    if node_ids.is_empty() {
+        anyhow::bail!("Select the nodes to group first");
+    }
+    for node_id in node_ids {
+        match state.graph[*node_id].user_data.template {
+            ProtosNodeTemplate::BackbufferPass(_) => anyhow::bail!("Backbuffer passes can't be grouped, only the top level graph is displayed"),
+            ProtosNodeTemplate::GroupInput(_) | ProtosNodeTemplate::GroupOutput(_) => anyhow::bail!("Group nodes can't be grouped, they expose the ports of their subgraph"),
+            _ => {}
+        }
+    }
+    let selected: HashSet<NodeId> = node_ids.iter().copied().collect();
+    let mut subgraph = Subgraph::default();
+    let mut copies = HashMap::new();
+    for node_id in node_ids {
+        let copy = copy_node(&state.graph, *node_id, &mut subgraph.state.graph);
+        let position = state.node_positions.get(*node_id).copied().unwrap_or_default();
+        subgraph.state.node_positions.insert(copy.node_id, position);
+        subgraph.state.node_order.push(copy.node_id);
+        copies.insert(*node_id, copy);
+    }
+
+    // Connections crossing the selection become ports, outputs feeding several nodes are exposed once.
+    let mut incoming: Vec<(OutputId, Vec<InputId>)> = Vec::new(); // Outer output & the copied inputs it feeds.
+    let mut outgoing: Vec<(OutputId, Vec<InputId>)> = Vec::new(); // Copied output & the outer inputs it feeds.
+    for (input_id, output_id) in state.graph.iter_connections() {
+        let input_node = state.graph[input_id].node;
+        let output_node = state.graph[output_id].node;
+        match (selected.contains(&input_node), selected.contains(&output_node)) {
+            (true, true) => subgraph.state.graph.add_connection(copies[&output_node].outputs[&output_id], copies[&input_node].inputs[&input_id]),
+            (true, false) => {
+                let copy = copies[&input_node].inputs[&input_id];
+                match incoming.iter_mut().find(|(output, _)| *output == output_id) {
+                    Some((_, inputs)) => inputs.push(copy),
+                    None => {
+                        let name = state.graph[input_node].inputs.iter().find(|(_, id)| *id == input_id).map(|(name, _)| name.clone()).unwrap_or_default();
+                        subgraph.inputs.push(SubgraphPort { name: unique_name(&subgraph.inputs, name.as_str()), typ: state.graph[output_id].typ.clone() });
+                        incoming.push((output_id, vec![copy]));
+                    }
+                }
+            }
+            (false, true) => {
+                let copy = copies[&output_node].outputs[&output_id];
+                match outgoing.iter_mut().find(|(output, _)| *output == copy) {
+                    Some((_, inputs)) => inputs.push(input_id),
+                    None => {
+                        let name = state.graph[output_node].outputs.iter().find(|(_, id)| *id == output_id).map(|(name, _)| name.clone()).unwrap_or_default();
+                        subgraph.outputs.push(SubgraphPort { name: unique_name(&subgraph.outputs, name.as_str()), typ: state.graph[output_id].typ.clone() });
+                        outgoing.push((copy, vec![input_id]));
+                    }
+                }
+            }
+            (false, false) => {}
+        }
+    }
+
+    // Group nodes are placed on each side of the grouped nodes.
+    let positions: Vec<egui::Pos2> = node_ids.iter().filter_map(|node_id| state.node_positions.get(*node_id).copied()).collect();
+    let min_x = positions.iter().map(|position| position.x).fold(f32::INFINITY, f32::min);
+    let max_x = positions.iter().map(|position| position.x).fold(f32::NEG_INFINITY, f32::max);
+    let center = positions.iter().fold(egui::Vec2::ZERO, |sum, position| sum + position.to_vec2()) / positions.len().max(1) as f32;
+
+    let ports = subgraph.inputs.clone();
+    let group_input = subgraph.state.graph.add_node(String::from("Group Input"), ProtosNodeData { template: ProtosNodeTemplate::GroupInput(GroupInputNode::default()) }, |graph, node_id| {
+        for port in &ports {
+            graph.add_output_param(node_id, port.name.clone(), port.typ.clone());
+        }
+    });
+    let ports = subgraph.outputs.clone();
+    let group_output = subgraph.state.graph.add_node(String::from("Group Output"), ProtosNodeData { template: ProtosNodeTemplate::GroupOutput(GroupOutputNode::default()) }, |graph, node_id| {
+        for port in &ports {
+            graph.add_input_param(node_id, port.name.clone(), port.typ.clone(), port.typ.default_value(), port.typ.input_kind(), true);
+        }
+    });
+    for (group_node, x) in [(group_input, min_x - 300.0), (group_output, max_x + 300.0)] {
+        subgraph.state.node_positions.insert(group_node, egui::pos2(x, center.y));
+        subgraph.state.node_order.push(group_node);
+    }
+    for ((_, inputs), port) in incoming.iter().zip(&subgraph.inputs) {
+        let output_id = subgraph.state.graph[group_input].get_output(port.name.as_str())?;
+        for input_id in inputs {
+            subgraph.state.graph.add_connection(output_id, *input_id);
+        }
+    }
+    for ((output_id, _), port) in outgoing.iter().zip(&subgraph.outputs) {
+        let input_id = subgraph.state.graph[group_output].get_input(port.name.as_str())?;
+        subgraph.state.graph.add_connection(*output_id, input_id);
+    }
+
+    // Replace the selection by the subgraph node.
+    let input_ports: Vec<String> = subgraph.inputs.iter().map(|port| port.name.clone()).collect();
+    let output_ports: Vec<String> = subgraph.outputs.iter().map(|port| port.name.clone()).collect();
+    for node_id in node_ids {
+        state.graph.remove_node(*node_id);
+        state.node_positions.remove(*node_id);
+    }
+    state.node_order.retain(|node_id| !selected.contains(node_id));
+    let template = ProtosNodeTemplate::Subgraph(SubgraphNode::new(String::from("Subgraph"), subgraph));
+    let node_id = state.graph.add_node(String::from("Subgraph"), ProtosNodeData { template: template.clone() }, |graph, node_id| {
+        template.visit_node(|node| {
+            node.build(graph, node_id);
+            Ok(())
+        }).expect("Should not fail.");
+    });
+    state.node_positions.insert(node_id, egui::pos2(center.x, center.y));
+    state.node_order.push(node_id);
+    for ((output_id, _), name) in incoming.iter().zip(&input_ports) {
+        let input_id = state.graph[node_id].get_input(name.as_str())?;
+        state.graph.add_connection(*output_id, input_id);
+    }
+    for ((_, inputs), name) in outgoing.iter().zip(&output_ports) {
+        let output_id = state.graph[node_id].get_output(name.as_str())?;
+        for input_id in inputs {
+            state.graph.add_connection(output_id, *input_id);
+        }
+    }
+    state.selected_nodes = vec![node_id];
+    Ok(node_id)
+}
+
+// Where the value of an input comes from once flattened.
+#[derive(Clone)]
+enum Source {
+    Output(OutputId), // Output of a node of the flattened graph.
+    Value(ProtosValueType),
+    None,
+}
+
+// Flattening state of a graph, the top level one or the content of a subgraph.
+struct Level<'a> {
+    graph: &'a ProtosGraph,
+    path: Vec<NodeId>, // Subgraph nodes from the top level graph.
+    inputs: HashMap<String, Source>, // Sources of the group input node ports.
+    copies: HashMap<NodeId, CopiedNode>,
+    expanded: HashMap<NodeId, HashMap<String, Source>>, // Sources of the outputs of expanded subgraph nodes.
+    expanding: HashSet<NodeId>,
+}
+
+impl<'a> Level<'a> {
+    fn flatten(flat: &mut FlatGraph, graph: &'a ProtosGraph, path: Vec<NodeId>, inputs: HashMap<String, Source>) -> anyhow::Result<Self> {
+        let mut level = Self {
+            graph,
+            path,
+            inputs,
+            copies: HashMap::new(),
+            expanded: HashMap::new(),
+            expanding: HashSet::new(),
+        };
+        for node_id in graph.iter_nodes() {
+            match graph[node_id].user_data.template {
+                ProtosNodeTemplate::Subgraph(_) | ProtosNodeTemplate::GroupInput(_) | ProtosNodeTemplate::GroupOutput(_) => {}
+                _ => {
+                    let copy = copy_node(graph, node_id, &mut flat.graph);
+                    flat.origins.insert(copy.node_id, level.path.iter().copied().chain([node_id]).collect());
+                    level.copies.insert(node_id, copy);
+                }
+            }
+        }
+        let copied: Vec<NodeId> = level.copies.keys().copied().collect();
+        for node_id in copied {
+            for (_, input_id) in &graph[node_id].inputs {
+                let copy = level.copies[&node_id].inputs[input_id];
+                match level.input_source(flat, *input_id)? {
+                    Source::Output(output_id) => flat.graph.add_connection(output_id, copy),
+                    Source::Value(value) => flat.graph[copy].value = value,
+                    Source::None => {}
+                }
+            }
+        }
+        Ok(level)
+    }
+    // Sources of the group output node ports.
+    fn outputs(&mut self, flat: &mut FlatGraph) -> anyhow::Result<HashMap<String, Source>> {
+        let graph = self.graph;
+        let mut outputs = HashMap::new();
+        for node_id in graph.iter_nodes() {
+            if let ProtosNodeTemplate::GroupOutput(_) = graph[node_id].user_data.template {
+                for (name, input_id) in &graph[node_id].inputs {
+                    outputs.insert(name.clone(), self.input_source(flat, *input_id)?);
+                }
+            }
+        }
+        Ok(outputs)
+    }
+    fn input_source(&mut self, flat: &mut FlatGraph, input_id: InputId) -> anyhow::Result<Source> {
+        match self.graph.connection(input_id) {
+            Some(output_id) => self.output_source(flat, output_id),
+            None => Ok(Source::Value(self.graph[input_id].value.clone())),
+        }
+    }
+    fn output_source(&mut self, flat: &mut FlatGraph, output_id: OutputId) -> anyhow::Result<Source> {
+        let graph = self.graph;
+        let node_id = graph[output_id].node;
+        let name = graph[node_id].outputs.iter().find(|(_, id)| *id == output_id).map(|(name, _)| name.as_str()).unwrap_or_default();
+        let source = match &graph[node_id].user_data.template {
+            ProtosNodeTemplate::GroupInput(_) => self.inputs.get(name).cloned(),
+            ProtosNodeTemplate::Subgraph(node) => self.expand(flat, node_id, node)?.get(name).cloned(),
+            _ => self.copies.get(&node_id).and_then(|copy| copy.outputs.get(&output_id)).map(|output_id| Source::Output(*output_id)),
+        };
+        Ok(source.unwrap_or(Source::None))
+    }
+    fn expand(&mut self, flat: &mut FlatGraph, node_id: NodeId, node: &SubgraphNode) -> anyhow::Result<HashMap<String, Source>> {
+        if let Some(outputs) = self.expanded.get(&node_id) {
+            return Ok(outputs.clone());
+        }
+        if !self.expanding.insert(node_id) {
+            anyhow::bail!("Subgraph {} depends on itself", node.name());
+        }
+        let graph = self.graph;
+        let mut inputs = HashMap::new();
+        for (name, input_id) in &graph[node_id].inputs {
+            inputs.insert(name.clone(), self.input_source(flat, *input_id)?);
+        }
+        let subgraph = node.subgraph().lock().unwrap();
+        let path = self.path.iter().copied().chain([node_id]).collect();
+        let outputs = Level::flatten(flat, &subgraph.state.graph, path, inputs)?.outputs(flat)?;
+        self.expanding.remove(&node_id);
+        self.expanded.insert(node_id, outputs.clone());
+        Ok(outputs)
+    }
+}
+
+// Graph with the content of every subgraph inlined, which is what gets evaluated.
+pub struct FlatGraph {
+    pub graph: ProtosGraph,
+    nodes: HashMap<NodeId, NodeId>, // Top level nodes to their copy.
+    origins: HashMap<NodeId, Vec<NodeId>>, // Copies to the subgraph nodes containing the original node, then the node itself.
+}
+
+impl FlatGraph {
+    pub fn node(&self, node_id: NodeId) -> Option<NodeId> {
+        self.nodes.get(&node_id).copied()
+    }
+    pub fn origin(&self, node_id: NodeId) -> Option<&[NodeId]> {
+        self.origins.get(&node_id).map(Vec::as_slice)
+    }
+    pub fn origins(&self) -> &HashMap<NodeId, Vec<NodeId>> {
+        &self.origins
+    }
+    // Error with its node located in the graph which was flattened.
+    pub fn locate_error(&self, err: &anyhow::Error) -> ProtosError {
+        let mut error = ProtosError::from_error(err);
+        match error.node.and_then(|node_id| self.origin(node_id)).and_then(<[NodeId]>::split_last) {
+            Some((node_id, subgraphs)) => {
+                error.node = Some(*node_id);
+                error.subgraphs = subgraphs.to_vec();
+            }
+            None => error.node = None,
+        }
+        error
+    }
+}
+
+// Labels of the nodes of a path, from the top level graph.
+pub fn node_labels(graph: &ProtosGraph, path: &[NodeId]) -> Vec<String> {
+    let mut labels = Vec::new();
+    let mut parent: Option<SubgraphNode> = None;
+    for node_id in path {
+        let (label, node) = match &parent {
+            Some(parent) => {
+                let subgraph = parent.subgraph().lock().unwrap();
+                (subgraph.state.graph.nodes.get(*node_id).map(|node| node.label.clone()), SubgraphNode::find(&subgraph.state.graph, *node_id).cloned())
+            }
+            None => (graph.nodes.get(*node_id).map(|node| node.label.clone()), SubgraphNode::find(graph, *node_id).cloned()),
+        };
+        labels.push(label.unwrap_or_else(|| String::from("?")));
+        parent = node;
+    }
+    labels
+}
+
+// Nodes keep their resources when copied, so that flattening each frame does not recreate anything.
+pub fn flatten_graph(graph: &ProtosGraph) -> anyhow::Result<FlatGraph> {
+    let mut flat = FlatGraph {
+        graph: ProtosGraph::new(),
+        nodes: HashMap::new(),
+        origins: HashMap::new(),
+    };
+    let level = Level::flatten(&mut flat, graph, Vec::new(), HashMap::new())?;
+    flat.nodes = level.copies.iter().map(|(node_id, copy)| (*node_id, copy.node_id)).collect();
+    Ok(flat)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::{gfx::{MeshShape, ShaderLanguage}, graph::{error::node_error, nodes::{BackbufferPassNode, GraphicPassNode, MeshNode, ShaderNode}, GraphBuilder}};
+
+    struct Scene {
+        state: ProtosEditorState,
+        shader: NodeId,
+        mesh: NodeId,
+        passes: Vec<NodeId>,
+        backbuffer: NodeId,
+    }
+
+    // Passes drawing the same mesh with the same shaders, the first one displayed by the backbuffer.
+    fn build_scene(pass_count: usize) -> Scene {
+        let mut builder = GraphBuilder::new();
+        let shader = builder.add_node(ProtosNodeTemplate::Shader(ShaderNode::new(ShaderLanguage::Wgsl, String::new())), 0, 0);
+        let mesh = builder.add_node(ProtosNodeTemplate::Mesh(MeshNode::from_shape(MeshShape::default_quad())), 0, 1);
+        let passes: Vec<NodeId> = (0..pass_count).map(|row| builder.add_node(ProtosNodeTemplate::GraphicPass(GraphicPassNode::default()), 1, row)).collect();
+        let backbuffer = builder.add_node(ProtosNodeTemplate::BackbufferPass(BackbufferPassNode::default()), 2, 0);
+        for pass in &passes {
+            builder.connect(shader, "VertexShader", *pass, "VertexShader").unwrap();
+            builder.connect(shader, "FragmentShader", *pass, "FragmentShader").unwrap();
+            builder.connect(mesh, "Geometry", *pass, "Geometry").unwrap();
+        }
+        builder.connect(passes[0], "RT0", backbuffer, "input").unwrap();
+        builder.set_backbuffer(backbuffer);
+        let (state, _) = builder.build();
+        Scene { state, shader, mesh, passes, backbuffer }
+    }
+
+    fn subgraph_of(graph: &ProtosGraph, node_id: NodeId) -> Arc<Mutex<Subgraph>> {
+        SubgraphNode::find(graph, node_id).expect("Not a subgraph node").subgraph().clone()
+    }
+
+    fn find_nodes(graph: &ProtosGraph, matches: impl Fn(&ProtosNodeTemplate) -> bool) -> Vec<NodeId> {
+        graph.iter_nodes().filter(|node_id| matches(&graph[*node_id].user_data.template)).collect()
+    }
+
+    fn port_names(ports: &[SubgraphPort]) -> Vec<&str> {
+        let mut names: Vec<&str> = ports.iter().map(|port| port.name.as_str()).collect();
+        names.sort();
+        names
+    }
+
+    fn connected(graph: &ProtosGraph, output_node: NodeId, output: &str, input_node: NodeId, input: &str) -> bool {
+        let input_id = graph[input_node].get_input(input).unwrap();
+        graph.connection(input_id) == Some(graph[output_node].get_output(output).unwrap())
+    }
+
+    #[test]
+    fn group_exposes_boundary_ports() {
+        let mut scene = build_scene(1);
+        let node_id = group_nodes(&mut scene.state, &[scene.passes[0]]).unwrap();
+        let graph = &scene.state.graph;
+        assert!(!graph.nodes.contains_key(scene.passes[0]));
+        assert!(connected(graph, scene.shader, "VertexShader", node_id, "VertexShader"));
+        assert!(connected(graph, scene.shader, "FragmentShader", node_id, "FragmentShader"));
+        assert!(connected(graph, scene.mesh, "Geometry", node_id, "Geometry"));
+        assert!(connected(graph, node_id, "RT0", scene.backbuffer, "input"));
+
+        let subgraph = subgraph_of(graph, node_id);
+        let subgraph = subgraph.lock().unwrap();
+        assert_eq!(port_names(&subgraph.inputs), ["FragmentShader", "Geometry", "VertexShader"]);
+        assert_eq!(port_names(&subgraph.outputs), ["RT0"]);
+        let inner = &subgraph.state.graph;
+        let pass = find_nodes(inner, |template| matches!(template, ProtosNodeTemplate::GraphicPass(_)))[0];
+        let group_input = find_nodes(inner, |template| matches!(template, ProtosNodeTemplate::GroupInput(_)))[0];
+        let group_output = find_nodes(inner, |template| matches!(template, ProtosNodeTemplate::GroupOutput(_)))[0];
+        for port in ["VertexShader", "FragmentShader", "Geometry"] {
+            assert!(connected(inner, group_input, port, pass, port), "{} is not fed by the group input", port);
+        }
+        assert!(connected(inner, pass, "RT0", group_output, "RT0"));
+    }
+
+    #[test]
+    fn group_exposes_fan_out_once() {
+        // Outer outputs feeding several grouped nodes.
+        let mut scene = build_scene(2);
+        let node_id = group_nodes(&mut scene.state, &scene.passes).unwrap();
+        let subgraph = subgraph_of(&scene.state.graph, node_id);
+        let subgraph = subgraph.lock().unwrap();
+        assert_eq!(port_names(&subgraph.inputs), ["FragmentShader", "Geometry", "VertexShader"]);
+        let inner = &subgraph.state.graph;
+        let group_input = find_nodes(inner, |template| matches!(template, ProtosNodeTemplate::GroupInput(_)))[0];
+        let geometry = inner[group_input].get_output("Geometry").unwrap();
+        assert_eq!(inner.iter_connections().filter(|(_, output_id)| *output_id == geometry).count(), 2);
+
+        // Grouped outputs feeding several outer nodes.
+        let mut scene = build_scene(2);
+        let node_id = group_nodes(&mut scene.state, &[scene.shader]).unwrap();
+        assert_eq!(port_names(&subgraph_of(&scene.state.graph, node_id).lock().unwrap().outputs), ["FragmentShader", "VertexShader"]);
+        for pass in &scene.passes {
+            assert!(connected(&scene.state.graph, node_id, "VertexShader", *pass, "VertexShader"));
+        }
+    }
+
+    #[test]
+    fn group_nodes_rejects_group_nodes() {
+        let mut scene = build_scene(1);
+        let node_id = group_nodes(&mut scene.state, &[scene.passes[0]]).unwrap();
+        let subgraph = subgraph_of(&scene.state.graph, node_id);
+        let mut subgraph = subgraph.lock().unwrap();
+        let group_input = find_nodes(&subgraph.state.graph, |template| matches!(template, ProtosNodeTemplate::GroupInput(_)))[0];
+        assert!(group_nodes(&mut subgraph.state, &[group_input]).is_err());
+    }
+
+    #[test]
+    fn flatten_nested_subgraphs() {
+        let mut scene = build_scene(1);
+        let outer = group_nodes(&mut scene.state, &[scene.passes[0]]).unwrap();
+        let (inner, pass) = {
+            let subgraph = subgraph_of(&scene.state.graph, outer);
+            let mut subgraph = subgraph.lock().unwrap();
+            let pass = find_nodes(&subgraph.state.graph, |template| matches!(template, ProtosNodeTemplate::GraphicPass(_)))[0];
+            let inner = group_nodes(&mut subgraph.state, &[pass]).unwrap();
+            let pass = find_nodes(&subgraph_of(&subgraph.state.graph, inner).lock().unwrap().state.graph, |template| matches!(template, ProtosNodeTemplate::GraphicPass(_)))[0];
+            (inner, pass)
+        };
+
+        let flat = flatten_graph(&scene.state.graph).unwrap();
+        let graph = &flat.graph;
+        assert!(find_nodes(graph, |template| matches!(template, ProtosNodeTemplate::Subgraph(_) | ProtosNodeTemplate::GroupInput(_) | ProtosNodeTemplate::GroupOutput(_))).is_empty());
+        let passes = find_nodes(graph, |template| matches!(template, ProtosNodeTemplate::GraphicPass(_)));
+        assert_eq!(passes.len(), 1);
+        assert_eq!(flat.origin(passes[0]), Some([outer, inner, pass].as_slice()));
+        let shader = flat.node(scene.shader).unwrap();
+        let mesh = flat.node(scene.mesh).unwrap();
+        let backbuffer = flat.node(scene.backbuffer).unwrap();
+        assert!(connected(graph, shader, "VertexShader", passes[0], "VertexShader"));
+        assert!(connected(graph, mesh, "Geometry", passes[0], "Geometry"));
+        assert!(connected(graph, passes[0], "RT0", backbuffer, "input"));
+    }
+
+    #[test]
+    fn flatten_rejects_self_dependency() {
+        let mut scene = build_scene(1);
+        let node_id = group_nodes(&mut scene.state, &[scene.passes[0]]).unwrap();
+        let output = scene.state.graph[node_id].get_output("RT0").unwrap();
+        let input = scene.state.graph[node_id].get_input("Geometry").unwrap();
+        scene.state.graph.add_connection(output, input);
+        let err = flatten_graph(&scene.state.graph).err().expect("Flattening a subgraph feeding itself should fail");
+        assert!(format!("{:#}", err).contains("depends on itself"), "{:#}", err);
+    }
+
+    #[test]
+    fn locate_error_in_subgraph() {
+        let mut scene = build_scene(1);
+        let node_id = group_nodes(&mut scene.state, &[scene.passes[0]]).unwrap();
+        let pass = find_nodes(&subgraph_of(&scene.state.graph, node_id).lock().unwrap().state.graph, |template| matches!(template, ProtosNodeTemplate::GraphicPass(_)))[0];
+        let flat = flatten_graph(&scene.state.graph).unwrap();
+        let flat_pass = find_nodes(&flat.graph, |template| matches!(template, ProtosNodeTemplate::GraphicPass(_)))[0];
+
+        let error = flat.locate_error(&node_error(anyhow::anyhow!("Failed"), flat_pass));
+        assert_eq!(error.node, Some(pass));
+        assert_eq!(error.subgraphs, vec![node_id]);
+        assert_eq!(error.path(), vec![node_id, pass]);
+
+        let error = flat.locate_error(&node_error(anyhow::anyhow!("Failed"), flat.node(scene.mesh).unwrap()));
+        assert_eq!(error.node, Some(scene.mesh));
+        assert!(error.subgraphs.is_empty());
+
+        assert_eq!(flat.locate_error(&anyhow::anyhow!("Failed")).node, None);
+    }
+}
@@ -0,0 +1,256 @@
+use egui_node_graph::{NodeId, NodeDataTrait, NodeResponse, OutputId, UserResponseTrait};
+
+use crate::gfx;
+
+use super::{core::ProtosGraph, diagnostics::Severity, node::ProtosNode, ProtosDataType, ProtosGraphState, ProtosNodeData, ProtosNodeTemplate, ProtosResponse, ProtosValueType};
+
+// Editor UI of a node, apart from ProtosNode so that evaluating graphs does not depend on egui.
+pub trait ProtosNodeUi: ProtosNode {
+    // Describe the UI, returns true if a parameter changed.
+    fn ui(&self, _graph: &ProtosGraph, _node_id: NodeId, _ui: &mut egui::Ui) -> bool {
+        false
+    }
+}
+
+impl ProtosNodeTemplate {
+    pub fn visit_node_ui<T>(&self, f: impl FnOnce(&dyn ProtosNodeUi) -> T) -> T {
+        match self {
+            ProtosNodeTemplate::BackbufferPass(handle) => { f(handle) }
+            ProtosNodeTemplate::GraphicPass(handle) => { f(handle) }
+            ProtosNodeTemplate::ComputePass(handle) => { f(handle) }
+            ProtosNodeTemplate::FileTexture(handle) => { f(handle) }
+            ProtosNodeTemplate::ResourceTexture(handle) => { f(handle) }
+            ProtosNodeTemplate::Buffer(handle) => { f(handle) }
+            ProtosNodeTemplate::Camera(handle) => { f(handle) }
+            ProtosNodeTemplate::Mesh(handle) => { f(handle) }
+            ProtosNodeTemplate::Shader(handle) => { f(handle) }
+            ProtosNodeTemplate::Feedback(handle) => { f(handle) }
+            ProtosNodeTemplate::Subgraph(handle) => { f(handle) }
+            ProtosNodeTemplate::GroupInput(handle) => { f(handle) }
+            ProtosNodeTemplate::GroupOutput(handle) => { f(handle) }
+        }
+    }
+}
+
+fn severity_color(severity: Severity) -> egui::Color32 {
+    match severity {
+        Severity::Warning => egui::Color32::from_rgb(180, 120, 20),
+        Severity::Error => egui::Color32::from_rgb(170, 40, 40),
+    }
+}
+
+// Badge listing the problems of the node in its tooltip.
+fn diagnostics_ui(ui: &mut egui::Ui, node_id: NodeId, user_state: &ProtosGraphState) {
+    let diagnostics = &user_state.diagnostics;
+    let path = user_state.node_path(node_id);
+    if let Some(severity) = diagnostics.severity(&path) {
+        let count = diagnostics.of_node(&path).count();
+        let text = match severity {
+            Severity::Warning => format!("⚠ {} warning(s)", count),
+            Severity::Error => format!("⛔ {} problem(s)", count),
+        };
+        ui.colored_label(severity_color(severity), text).on_hover_ui(|ui| {
+            for diagnostic in diagnostics.of_node(&path) {
+                ui.colored_label(severity_color(diagnostic.severity), diagnostic.message.as_str());
+            }
+        });
+    }
+}
+
+// GPU time & pipeline statistics of the passes of the node, or of their content for subgraph nodes.
+fn timing_ui(ui: &mut egui::Ui, node_id: NodeId, user_state: &ProtosGraphState) {
+    let path = user_state.node_path(node_id);
+    let times: Vec<f32> = user_state.gpu_times.iter()
+        .filter(|(timed_path, _)| timed_path.starts_with(&path))
+        .map(|(_, time)| *time)
+        .collect();
+    if !times.is_empty() {
+        ui.weak(format!("⏱ {:.3} ms", times.iter().sum::<f32>())).on_hover_text("GPU time");
+    }
+    let mut statistics = None;
+    for (_, pass_statistics) in user_state.gpu_statistics.iter().filter(|(profiled_path, _)| profiled_path.starts_with(&path)) {
+        *statistics.get_or_insert_with(gfx::PipelineStatistics::default) += *pass_statistics;
+    }
+    if let Some(statistics) = statistics {
+        ui.weak(format!("⚙ {} fragments", statistics.fragment_invocations)).on_hover_text(format!(
+            "{} vertex invocations\n{} clipper primitives\n{} fragment invocations",
+            statistics.vertex_invocations, statistics.clipper_primitives, statistics.fragment_invocations
+        ));
+    }
+}
+
+const PREVIEW_WIDTH: f32 = 128.0;
+
+// Buttons opening outputs of the node in an inspector.
+fn inspect_buttons_ui(ui: &mut egui::Ui, outputs: &[(String, OutputId)], responses: &mut Vec<NodeResponse<ProtosResponse, ProtosNodeData>>) {
+    if outputs.is_empty() {
+        return;
+    }
+    ui.horizontal(|ui| {
+        for (name, output_id) in outputs {
+            if ui.small_button(format!("🔍 {}", name)).on_hover_text("Inspect").clicked() {
+                responses.push(NodeResponse::User(ProtosResponse::InspectOutput(*output_id)));
+            }
+        }
+    });
+}
+
+// Thumbnails of the texture outputs of the node, filled by the last evaluation.
+fn previews_ui(ui: &mut egui::Ui, node_id: NodeId, graph: &ProtosGraph, user_state: &mut ProtosGraphState) -> Vec<NodeResponse<ProtosResponse, ProtosNodeData>> {
+    let mut responses = vec![];
+    // Subgraphs are inlined when evaluated, their outputs are previewed from their content.
+    if matches!(graph[node_id].user_data.template, ProtosNodeTemplate::Subgraph(_) | ProtosNodeTemplate::GroupInput(_) | ProtosNodeTemplate::GroupOutput(_)) {
+        return responses;
+    }
+    let outputs_of_type = |typ: ProtosDataType| -> Vec<(String, OutputId)> {
+        graph[node_id].outputs.iter()
+            .filter(|(_, output_id)| graph[*output_id].typ == typ)
+            .cloned()
+            .collect()
+    };
+    inspect_buttons_ui(ui, &outputs_of_type(ProtosDataType::Buffer), &mut responses);
+    let outputs = outputs_of_type(ProtosDataType::Texture);
+    if outputs.is_empty() {
+        return responses;
+    }
+    let path = user_state.node_path(node_id);
+    let mut enabled = user_state.previews.is_enabled(&path);
+    if ui.checkbox(&mut enabled, "Preview").changed() {
+        user_state.previews.set_enabled(path.clone(), enabled);
+    }
+    if !enabled {
+        // Textures can still be inspected without their thumbnail.
+        inspect_buttons_ui(ui, &outputs, &mut responses);
+        return responses;
+    }
+    for (name, output_id) in outputs {
+        let key = (path.clone(), name);
+        let size = user_state.previews.texture(&key).and_then(|texture| texture.lock().unwrap().get_size().ok());
+        match (user_state.previews.texture_id(&key), size) {
+            (Some(texture_id), Some((width, height))) if width > 0 && height > 0 => {
+                let size = egui::vec2(PREVIEW_WIDTH, PREVIEW_WIDTH * height as f32 / width as f32);
+                let image = egui::Image::new(egui::load::SizedTexture::new(texture_id, size)).sense(egui::Sense::click());
+                if ui.add(image).on_hover_text(format!("{} ({}x{}), click to inspect", key.1, width, height)).clicked() {
+                    responses.push(NodeResponse::User(ProtosResponse::InspectOutput(output_id)));
+                }
+            }
+            _ => { ui.weak(format!("{} : not evaluated", key.1)); }
+        }
+    }
+    responses
+}
+
+impl NodeDataTrait for ProtosNodeData {
+    type Response = ProtosResponse;
+    type UserState = ProtosGraphState;
+    type DataType = ProtosDataType;
+    type ValueType = ProtosValueType;
+
+    // Header is tinted with the most severe problem of the node.
+    fn titlebar_color(
+        &self,
+        _ui: &egui::Ui,
+        node_id: NodeId,
+        _graph: &ProtosGraph,
+        user_state: &mut Self::UserState,
+    ) -> Option<egui::Color32> {
+        user_state.diagnostics.severity(&user_state.node_path(node_id)).map(severity_color)
+    }
+
+    // Ports of a subgraph are fed & read through its group nodes, which can't be replaced once removed.
+    fn can_delete(
+        &self,
+        _node_id: NodeId,
+        _graph: &ProtosGraph,
+        _user_state: &mut Self::UserState,
+    ) -> bool {
+        !matches!(self.template, ProtosNodeTemplate::GroupInput(_) | ProtosNodeTemplate::GroupOutput(_))
+    }
+
+    // This method will be called when drawing each node. This allows adding
+    // extra ui elements inside the nodes. In this case, we create an "active"
+    // button which introduces the concept of having an active node in the
+    // graph. This is done entirely from user code with no modifications to the
+    // node graph library.
+    fn bottom_ui(
+        &self,
+        ui: &mut egui::Ui,
+        node_id: NodeId,
+        graph: &ProtosGraph,
+        user_state: &mut Self::UserState,
+    ) -> Vec<NodeResponse<ProtosResponse, ProtosNodeData>>
+    where
+        ProtosResponse: UserResponseTrait,
+    {
+        diagnostics_ui(ui, node_id, user_state);
+        timing_ui(ui, node_id, user_state);
+        let mut responses = match &self.template {
+            ProtosNodeTemplate::BackbufferPass(node) => {
+                // We only want bottom UI for backbuffer pass node.
+                let changed = node.ui(graph, node_id, ui);
+
+                // This logic is entirely up to the user. In this case, we check if the
+                // current node we're drawing is the active one, by comparing against
+                // the value stored in the global user state, and draw different button
+                // UIs based on that.
+
+                let mut responses = vec![];
+                if changed {
+                    responses.push(NodeResponse::User(ProtosResponse::ParameterChanged(node_id)));
+                }
+                let is_active = user_state
+                    .backbuffer_node
+                    .map(|id| id == node_id)
+                    .unwrap_or(false);
+
+                // Pressing the button will emit a custom user response to either set,
+                // or clear the active node. These responses do nothing by themselves,
+                // the library only makes the responses available to you after the graph
+                // has been drawn. See below at the update method for an example.
+                if !is_active {
+                    if ui.button("👁 Set active").clicked() {
+                        responses.push(NodeResponse::User(ProtosResponse::SetCurrentBackbuffer(node_id)));
+                    }
+                } else {
+                    let button =
+                        egui::Button::new(egui::RichText::new("👁 Active").color(egui::Color32::BLACK))
+                            .fill(egui::Color32::GOLD);
+                    if ui.add(button).clicked() {
+                        responses.push(NodeResponse::User(ProtosResponse::ClearCurrentBackbuffer));
+                    }
+                }
+                #[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+                if ui.button("💾 Export...").on_hover_text("Save frames to PNG, EXR, GIF or APNG").clicked() {
+                    responses.push(NodeResponse::User(ProtosResponse::ExportBackbuffer(node_id)));
+                }
+                responses
+            }
+            ProtosNodeTemplate::Subgraph(node) => {
+                let mut responses = vec![];
+                if node.ui(graph, node_id, ui) {
+                    responses.push(NodeResponse::User(ProtosResponse::ParameterChanged(node_id)));
+                }
+                ui.horizontal(|ui| {
+                    if ui.button("✏ Open").clicked() {
+                        responses.push(NodeResponse::User(ProtosResponse::OpenSubgraph(node_id)));
+                    }
+                    #[cfg(feature = "persistence")]
+                    if ui.button("📚 Save to library").clicked() {
+                        responses.push(NodeResponse::User(ProtosResponse::SaveSubgraph(node_id)));
+                    }
+                });
+                responses
+            }
+            _ => { 
+                let changed = self.template.visit_node_ui(|node| node.ui(graph, node_id, ui));
+                if changed {
+                    vec![NodeResponse::User(ProtosResponse::ParameterChanged(node_id))]
+                } else {
+                    vec![]
+                }
+            }
+        };
+        responses.extend(previews_ui(ui, node_id, graph, user_state));
+        responses
+    }
+}
\ No newline at end of file
@@ -0,0 +1,300 @@
+use std::{collections::{HashSet, VecDeque}, rc::Rc};
+
+use serde_json::Value;
+
+use crate::gfx::ResourceCapture;
+
+pub const DEFAULT_HISTORY_DEPTH: usize = 100;
+// Steps further than this from the current one forget their resource handles, so that GPU data of deleted nodes is released.
+// Restoring them creates new resources instead.
+const RESOURCE_STEPS: usize = 10;
+// Strings this long are stored once for every step holding them, mostly embedded textures which rarely change between steps.
+const SHARED_STRING_LENGTH: usize = 1024;
+// Replaces a shared string in a stored step, followed by its index in the step.
+const SHARED_STRING_MARKER: &str = "\u{0}shared:";
+
+// Serialized project, with the resource handles it was serialized from.
+// Restoring it updates these handles in place, so that GPU resources & feedback contents survive an undo.
+#[derive(Clone)]
+pub struct Snapshot {
+    pub json: String,
+    pub resources: ResourceCapture,
+}
+
+// Snapshot as stored, its long strings being shared with the other steps.
+struct Step {
+    json: String,
+    shared: Vec<Rc<str>>,
+    resources: ResourceCapture,
+    id: u64, // Identifies the step, to know whether it is the saved one.
+}
+
+fn visit_strings(value: &mut Value, f: &mut impl FnMut(&mut String)) {
+    match value {
+        Value::String(string) => f(string),
+        Value::Array(values) => values.iter_mut().for_each(|value| visit_strings(value, f)),
+        Value::Object(map) => map.values_mut().for_each(|value| visit_strings(value, f)),
+        _ => {}
+    }
+}
+
+// Snapshot based edit history. Each step is the serialized project, which covers every kind of edit
+// (nodes, connections, positions & parameters) without having to describe them as commands.
+pub struct History {
+    undo: VecDeque<Step>, // Oldest first.
+    redo: Vec<Step>, // Next step last.
+    current: Option<Step>, // Snapshot of the project as displayed.
+    strings: HashSet<Rc<str>>, // Shared strings of every step.
+    saved: Option<u64>, // Step matching the project file.
+    next_id: u64,
+    depth: usize,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_DEPTH)
+    }
+}
+
+impl History {
+    pub fn new(depth: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            current: None,
+            strings: HashSet::new(),
+            saved: None,
+            next_id: 0,
+            depth,
+        }
+    }
+    fn store(&mut self, json: String, resources: ResourceCapture) -> Step {
+        self.next_id += 1;
+        let mut shared = Vec::new();
+        let json = match serde_json::from_str::<Value>(json.as_str()) {
+            Ok(mut value) => {
+                let strings = &mut self.strings;
+                visit_strings(&mut value, &mut |string| {
+                    // Strings looking like a marker are shared too, so that they are not mistaken for one.
+                    if string.len() >= SHARED_STRING_LENGTH || string.starts_with(SHARED_STRING_MARKER) {
+                        let pooled = match strings.get(string.as_str()) {
+                            Some(pooled) => pooled.clone(),
+                            None => {
+                                let pooled: Rc<str> = Rc::from(string.as_str());
+                                strings.insert(pooled.clone());
+                                pooled
+                            }
+                        };
+                        *string = format!("{}{}", SHARED_STRING_MARKER, shared.len());
+                        shared.push(pooled);
+                    }
+                });
+                value.to_string()
+            }
+            Err(_) => json, // Stored whole.
+        };
+        Step { json, shared, resources, id: self.next_id }
+    }
+    fn load(step: &Step) -> Snapshot {
+        let json = match serde_json::from_str::<Value>(step.json.as_str()) {
+            Ok(mut value) if !step.shared.is_empty() => {
+                visit_strings(&mut value, &mut |string| {
+                    let index = string.strip_prefix(SHARED_STRING_MARKER).and_then(|index| index.parse::<usize>().ok());
+                    if let Some(shared) = index.and_then(|index| step.shared.get(index)) {
+                        *string = shared.to_string();
+                    }
+                });
+                value.to_string()
+            }
+            _ => step.json.clone(),
+        };
+        Snapshot { json, resources: step.resources.clone() }
+    }
+    // Forget resources of distant steps & strings no step holds anymore.
+    fn trim(&mut self) {
+        let forgotten = self.undo.len().saturating_sub(RESOURCE_STEPS);
+        for step in self.undo.iter_mut().take(forgotten) {
+            step.resources = ResourceCapture::default();
+        }
+        let forgotten = self.redo.len().saturating_sub(RESOURCE_STEPS);
+        for step in self.redo.iter_mut().take(forgotten) {
+            step.resources = ResourceCapture::default();
+        }
+        self.strings.retain(|string| Rc::strong_count(string) > 1);
+    }
+    // Forget every step, the snapshot becomes the initial state, which matches the saved project.
+    pub fn reset(&mut self, json: String, resources: ResourceCapture) {
+        self.undo.clear();
+        self.redo.clear();
+        self.current = None;
+        let step = self.store(json, resources);
+        self.saved = Some(step.id);
+        self.current = Some(step);
+        self.trim();
+    }
+    pub fn set_depth(&mut self, depth: usize) {
+        self.depth = depth;
+        while self.undo.len() > self.depth {
+            self.undo.pop_front();
+        }
+        self.trim();
+    }
+    // Add a step, ignored if nothing changed since the last one.
+    pub fn record(&mut self, json: String, resources: ResourceCapture) {
+        let step = self.store(json, resources);
+        if self.current.as_ref().is_some_and(|current| current.json == step.json && current.shared == step.shared) {
+            self.trim();
+            return;
+        }
+        if let Some(previous) = self.current.replace(step) {
+            self.undo.push_back(previous);
+            if self.undo.len() > self.depth {
+                self.undo.pop_front();
+            }
+        }
+        self.redo.clear();
+        self.trim();
+    }
+    // Returns the snapshot to restore, if any.
+    pub fn undo(&mut self) -> Option<Snapshot> {
+        let previous = self.undo.pop_back()?;
+        let snapshot = Self::load(&previous);
+        if let Some(current) = self.current.replace(previous) {
+            self.redo.push(current);
+        }
+        self.trim();
+        Some(snapshot)
+    }
+    pub fn redo(&mut self) -> Option<Snapshot> {
+        let next = self.redo.pop()?;
+        let snapshot = Self::load(&next);
+        if let Some(current) = self.current.replace(next) {
+            self.undo.push_back(current);
+        }
+        self.trim();
+        Some(snapshot)
+    }
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+    // The current step was saved.
+    pub fn mark_saved(&mut self) {
+        self.saved = self.current.as_ref().map(|current| current.id);
+    }
+    // No step matches the project file anymore, e.g. for a recovered project.
+    pub fn clear_saved(&mut self) {
+        self.saved = None;
+    }
+    pub fn is_saved(&self) -> bool {
+        self.saved.is_some() && self.saved == self.current.as_ref().map(|current| current.id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gfx::{with_resource_capture, ResourceHandle, Texture};
+
+    fn step(value: u32) -> String {
+        serde_json::json!({ "value": value }).to_string()
+    }
+
+    // Snapshot holding a resource handle, like the ones of a project.
+    fn captured(value: u32) -> (String, ResourceCapture) {
+        let handle = ResourceHandle::new(Texture::default());
+        let (_, resources) = with_resource_capture(|| serde_json::to_string(&handle));
+        (step(value), resources)
+    }
+
+    #[test]
+    fn record_ignores_unchanged_project() {
+        let mut history = History::default();
+        history.reset(step(0), ResourceCapture::default());
+        history.record(step(0), ResourceCapture::default());
+        assert!(!history.can_undo());
+        history.record(step(1), ResourceCapture::default());
+        history.record(step(1), ResourceCapture::default());
+        assert_eq!(history.undo().map(|snapshot| snapshot.json), Some(step(0)));
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn depth_drops_oldest_steps() {
+        let mut history = History::new(2);
+        history.reset(step(0), ResourceCapture::default());
+        for value in 1..5 {
+            history.record(step(value), ResourceCapture::default());
+        }
+        assert_eq!(history.undo.len(), 2);
+        history.set_depth(1);
+        assert_eq!(history.undo().map(|snapshot| snapshot.json), Some(step(3)));
+        assert!(history.undo().is_none());
+    }
+
+    #[test]
+    fn record_clears_redo() {
+        let mut history = History::default();
+        history.reset(step(0), ResourceCapture::default());
+        history.record(step(1), ResourceCapture::default());
+        history.undo();
+        assert!(history.can_redo());
+        history.record(step(2), ResourceCapture::default());
+        assert!(!history.can_redo());
+        assert_eq!(history.undo().map(|snapshot| snapshot.json), Some(step(0)));
+    }
+
+    #[test]
+    fn saved_step_after_undo() {
+        let mut history = History::default();
+        history.reset(step(0), ResourceCapture::default());
+        assert!(history.is_saved());
+        history.record(step(1), ResourceCapture::default());
+        assert!(!history.is_saved());
+        history.undo();
+        assert!(history.is_saved());
+        history.redo();
+        assert!(!history.is_saved());
+        history.mark_saved();
+        history.undo();
+        assert!(!history.is_saved());
+        history.redo();
+        assert!(history.is_saved());
+    }
+
+    #[test]
+    fn long_strings_are_shared() {
+        let bytes = "A".repeat(SHARED_STRING_LENGTH);
+        let project = |value: u32| serde_json::json!({ "texture": bytes, "value": value, "marker": SHARED_STRING_MARKER }).to_string();
+        let mut history = History::default();
+        history.reset(project(0), ResourceCapture::default());
+        history.record(project(1), ResourceCapture::default());
+        history.record(project(2), ResourceCapture::default());
+        assert_eq!(history.strings.len(), 2);
+        assert!(history.undo.iter().all(|step| !step.json.contains(bytes.as_str())));
+        assert_eq!(history.undo().map(|snapshot| snapshot.json), Some(project(1)));
+        assert_eq!(history.undo().map(|snapshot| snapshot.json), Some(project(0)));
+        history.reset(step(0), ResourceCapture::default());
+        assert!(history.strings.is_empty());
+    }
+
+    #[test]
+    fn distant_steps_forget_resources() {
+        let mut history = History::default();
+        let (json, resources) = captured(0);
+        history.reset(json, resources);
+        for value in 1..=RESOURCE_STEPS as u32 + 2 {
+            let (json, resources) = captured(value);
+            history.record(json, resources);
+        }
+        let kept = history.undo.iter().map(|step| !step.resources.is_empty()).collect::<Vec<_>>();
+        assert_eq!(kept.iter().filter(|kept| !**kept).count(), 2);
+        assert!(!kept[0] && !kept[1] && kept[2]);
+        while history.undo().is_some() {}
+        // Farthest steps from the initial one forget theirs too.
+        assert!(history.redo.iter().take(2).all(|step| step.resources.is_empty()));
+        assert!(!history.redo[2].resources.is_empty());
+    }
+}
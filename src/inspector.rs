@@ -0,0 +1,398 @@
+use egui::load::SizedTexture;
+
+use crate::{gfx, graph::PreviewKey};
+
+const HISTOGRAM_BINS: usize = 64;
+const HISTOGRAM_HEIGHT: f32 = 80.0;
+const CHANNELS: [&str; 4] = ["R", "G", "B", "A"];
+
+// Window showing the values of a texture of the graph, read back from the GPU.
+pub struct TextureInspector {
+    key: PreviewKey,
+    readback: Option<gfx::TextureReadback>, // Pending copy of the texture.
+    pixels: Option<gfx::TexturePixels>,
+    image: Option<egui::TextureHandle>, // Pixels as displayed, with channels & exposure applied.
+    histogram: [[u32; HISTOGRAM_BINS]; 3],
+    error: Option<String>,
+    zoom: f32,
+    channels: [bool; 4],
+    exposure: f32, // In stops.
+    gamma: f32,
+    live: bool, // Read the texture again as soon as the last readback is done.
+    refresh: bool, // Read the texture once.
+    dirty: bool, // Displayed image is out of date.
+}
+
+impl TextureInspector {
+    pub fn new(key: PreviewKey) -> Self {
+        Self {
+            key,
+            readback: None,
+            pixels: None,
+            image: None,
+            histogram: [[0; HISTOGRAM_BINS]; 3],
+            error: None,
+            zoom: 1.0,
+            channels: [true; 4],
+            exposure: 0.0,
+            gamma: 1.0,
+            live: true,
+            refresh: true,
+            dirty: false,
+        }
+    }
+    pub fn key(&self) -> &PreviewKey {
+        &self.key
+    }
+    // Texture is None when it was not evaluated. Open is cleared when the inspector should close.
+    pub fn ui(&mut self, ctx: &egui::Context, device: &wgpu::Device, queue: &wgpu::Queue, title: &str, texture: Option<&gfx::ResourceHandle<gfx::Texture>>, open: &mut bool) {
+        self.update_readback(ctx, device, queue, texture);
+        if self.dirty {
+            self.update_image(ctx);
+        }
+        egui::Window::new("Texture inspector")
+            .open(open)
+            .default_size([512.0, 512.0])
+            .show(ctx, |ui| {
+                ui.label(title);
+                if let Some(pixels) = &self.pixels {
+                    ui.label(format!("{}x{} {:?}", pixels.width, pixels.height, pixels.format));
+                }
+                ui.horizontal(|ui| {
+                    for (channel, name) in CHANNELS.iter().enumerate() {
+                        self.dirty |= ui.toggle_value(&mut self.channels[channel], *name).changed();
+                    }
+                    ui.separator();
+                    self.dirty |= ui.add(egui::Slider::new(&mut self.exposure, -10.0..=10.0).text("Exposure")).changed();
+                    self.dirty |= ui.add(egui::Slider::new(&mut self.gamma, 0.1..=4.0).text("Gamma")).changed();
+                });
+                ui.horizontal(|ui| {
+                    ui.add(egui::Slider::new(&mut self.zoom, 0.1..=32.0).logarithmic(true).text("Zoom"));
+                    if ui.button("1:1").clicked() {
+                        self.zoom = 1.0;
+                    }
+                    if ui.button("Fit").clicked() {
+                        if let Some(pixels) = &self.pixels {
+                            let available = ui.available_size() - egui::vec2(0.0, HISTOGRAM_HEIGHT * 2.0);
+                            self.zoom = (available.x / pixels.width as f32).min(available.y / pixels.height as f32).max(0.1);
+                        }
+                    }
+                    ui.separator();
+                    ui.checkbox(&mut self.live, "Live");
+                    if ui.button("Refresh").clicked() {
+                        self.refresh = true;
+                        self.error = None;
+                    }
+                });
+                if texture.is_none() {
+                    ui.label("Not evaluated, activate a backbuffer using this texture.");
+                }
+                if let Some(error) = &self.error {
+                    ui.colored_label(egui::Color32::RED, error);
+                }
+                let (pixels, image) = match (&self.pixels, &self.image) {
+                    (Some(pixels), Some(image)) => (pixels, image),
+                    _ => return,
+                };
+                let mut hovered = None;
+                egui::ScrollArea::both()
+                    .max_height((ui.available_height() - HISTOGRAM_HEIGHT * 2.0).max(HISTOGRAM_HEIGHT))
+                    .drag_to_scroll(true)
+                    .show(ui, |ui| {
+                        let size = egui::vec2(pixels.width as f32, pixels.height as f32) * self.zoom;
+                        let response = ui.add(egui::Image::new(SizedTexture::new(image.id(), size)).sense(egui::Sense::hover()));
+                        if let Some(position) = response.hover_pos() {
+                            let pixel = (position - response.rect.min) / self.zoom;
+                            hovered = Some((pixel.x as u32, pixel.y as u32));
+                            // Zoom around the image with ctrl + scroll.
+                            let zoom_delta = ui.input(|input| input.zoom_delta());
+                            if zoom_delta != 1.0 {
+                                self.zoom = (self.zoom * zoom_delta).clamp(0.1, 32.0);
+                            }
+                        }
+                    });
+                match hovered.and_then(|(x, y)| pixels.get(x, y).map(|value| (x, y, value))) {
+                    Some((x, y, value)) if pixels.is_hdr() => {
+                        ui.monospace(format!("({}, {}) : {:.4} {:.4} {:.4} {:.4}", x, y, value[0], value[1], value[2], value[3]));
+                    }
+                    Some((x, y, value)) => {
+                        let bytes = value.map(|value| (value * 255.0).round() as u8);
+                        ui.monospace(format!("({}, {}) : {:.4} {:.4} {:.4} {:.4} ({} {} {} {})", x, y, value[0], value[1], value[2], value[3], bytes[0], bytes[1], bytes[2], bytes[3]));
+                    }
+                    None => { ui.monospace("Hover the image to read a pixel."); }
+                }
+                self.histogram_ui(ui);
+            });
+    }
+    fn update_readback(&mut self, ctx: &egui::Context, device: &wgpu::Device, queue: &wgpu::Queue, texture: Option<&gfx::ResourceHandle<gfx::Texture>>) {
+        if let Some(readback) = &self.readback {
+            match readback.try_read(device) {
+                Some(Ok(pixels)) => {
+                    // HDR values are linear, first readback displays them with a usual gamma.
+                    if self.pixels.is_none() && pixels.is_hdr() {
+                        self.gamma = 2.2;
+                    }
+                    self.pixels = Some(pixels);
+                    self.readback = None;
+                    self.dirty = true;
+                }
+                Some(Err(err)) => {
+                    self.error = Some(format!("{:#}", err));
+                    self.readback = None;
+                }
+                None => ctx.request_repaint(),
+            }
+        }
+        let texture = match texture {
+            Some(texture) if self.readback.is_none() && (self.live || self.refresh) && self.error.is_none() => texture,
+            _ => return,
+        };
+        self.refresh = false;
+        let texture = texture.lock().unwrap();
+        match texture.get_handle().and_then(|texture| gfx::TextureReadback::new(device, queue, texture)) {
+            Ok(readback) => self.readback = Some(readback),
+            Err(err) => self.error = Some(format!("{:#}", err)),
+        }
+    }
+    // Apply channels & exposure to the pixels, & count them in the histogram.
+    fn update_image(&mut self, ctx: &egui::Context) {
+        self.dirty = false;
+        let pixels = match &self.pixels {
+            Some(pixels) => pixels,
+            None => return,
+        };
+        let scale = 2f32.powf(self.exposure);
+        let gamma = 1.0 / self.gamma;
+        let tone = |value: f32| ((value * scale).max(0.0).powf(gamma).min(1.0) * 255.0).round() as u8;
+        let alpha = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+        let isolated: Vec<usize> = (0..4).filter(|channel| self.channels[*channel]).collect();
+        self.histogram = [[0; HISTOGRAM_BINS]; 3];
+        let mut bytes = Vec::with_capacity(pixels.pixels.len() * 4);
+        for value in &pixels.pixels {
+            let color = [tone(value[0]), tone(value[1]), tone(value[2]), alpha(value[3])];
+            for (channel, bins) in self.histogram.iter_mut().enumerate() {
+                bins[color[channel] as usize * HISTOGRAM_BINS / 256] += 1;
+            }
+            // A single channel is shown in grey levels.
+            let displayed = match isolated.as_slice() {
+                [channel] => [color[*channel], color[*channel], color[*channel], 255],
+                _ => [
+                    if self.channels[0] { color[0] } else { 0 },
+                    if self.channels[1] { color[1] } else { 0 },
+                    if self.channels[2] { color[2] } else { 0 },
+                    if self.channels[3] { color[3] } else { 255 },
+                ],
+            };
+            bytes.extend_from_slice(&displayed);
+        }
+        let image = egui::ColorImage::from_rgba_unmultiplied([pixels.width as usize, pixels.height as usize], &bytes);
+        match &mut self.image {
+            Some(handle) => handle.set(image, egui::TextureOptions::NEAREST),
+            None => self.image = Some(ctx.load_texture("TextureInspector", image, egui::TextureOptions::NEAREST)),
+        }
+    }
+    fn histogram_ui(&self, ui: &mut egui::Ui) {
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(ui.available_width(), HISTOGRAM_HEIGHT), egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+        let max = self.histogram.iter().flatten().copied().max().unwrap_or(0).max(1) as f32;
+        let colors = [egui::Color32::RED, egui::Color32::GREEN, egui::Color32::LIGHT_BLUE];
+        for (bins, color) in self.histogram.iter().zip(colors) {
+            let points = bins.iter().enumerate().map(|(bin, count)| {
+                let x = rect.left() + rect.width() * bin as f32 / (HISTOGRAM_BINS - 1) as f32;
+                let y = rect.bottom() - rect.height() * *count as f32 / max;
+                egui::pos2(x, y)
+            }).collect();
+            painter.add(egui::Shape::line(points, egui::Stroke::new(1.0, color)));
+        }
+    }
+}
+
+const PAGE_SIZE: usize = 64;
+const PLOT_HEIGHT: f32 = 120.0;
+const MAX_PLOT_POINTS: usize = 4096;
+const LAYOUT_PRESETS: [&str; 4] = ["f32", "u32", "vec4<f32>", "struct Element {\n    position: vec3<f32>,\n    weight: f32,\n}"];
+
+// Window showing the content of a buffer of the graph, read back from the GPU & decoded with a WGSL layout.
+pub struct BufferInspector {
+    key: PreviewKey,
+    readback: Option<gfx::BufferReadback>, // Pending copy of the buffer.
+    bytes: Option<Vec<u8>>,
+    layout_source: String,
+    layout: Result<gfx::BufferLayout, String>,
+    page: usize,
+    plotted: Option<usize>, // Column drawn as a line graph.
+    error: Option<String>,
+    live: bool, // Read the buffer again as soon as the last readback is done.
+    refresh: bool, // Read the buffer once.
+}
+
+impl BufferInspector {
+    pub fn new(key: PreviewKey) -> Self {
+        let layout_source = String::from("f32");
+        Self {
+            key,
+            readback: None,
+            bytes: None,
+            layout: parse_layout(&layout_source),
+            layout_source,
+            page: 0,
+            plotted: None,
+            error: None,
+            live: true,
+            refresh: true,
+        }
+    }
+    pub fn key(&self) -> &PreviewKey {
+        &self.key
+    }
+    // Buffer is None when it was not evaluated. Open is cleared when the inspector should close.
+    pub fn ui(&mut self, ctx: &egui::Context, device: &wgpu::Device, queue: &wgpu::Queue, title: &str, buffer: Option<&gfx::ResourceHandle<gfx::Buffer>>, open: &mut bool) {
+        self.update_readback(ctx, device, queue, buffer);
+        egui::Window::new("Buffer inspector")
+            .open(open)
+            .default_size([512.0, 512.0])
+            .show(ctx, |ui| {
+                ui.label(title);
+                ui.horizontal(|ui| {
+                    ui.label("Layout");
+                    for preset in LAYOUT_PRESETS {
+                        let name = preset.lines().next().unwrap_or(preset).trim_end_matches(" {");
+                        if ui.small_button(name).clicked() {
+                            self.layout_source = preset.to_string();
+                            self.set_layout();
+                        }
+                    }
+                    ui.separator();
+                    ui.checkbox(&mut self.live, "Live");
+                    if ui.button("Refresh").clicked() {
+                        self.refresh = true;
+                        self.error = None;
+                    }
+                });
+                let response = ui.add(
+                    egui::TextEdit::multiline(&mut self.layout_source)
+                        .font(egui::TextStyle::Monospace)
+                        .code_editor()
+                        .desired_rows(1)
+                        .desired_width(f32::INFINITY)
+                        .hint_text("WGSL type or struct, the last struct being an element"),
+                );
+                if response.changed() {
+                    self.set_layout();
+                }
+                if buffer.is_none() {
+                    ui.label("Not evaluated, activate a backbuffer using this buffer.");
+                }
+                if let Some(error) = &self.error {
+                    ui.colored_label(egui::Color32::RED, error);
+                }
+                let layout = match &self.layout {
+                    Ok(layout) => layout,
+                    Err(error) => {
+                        ui.colored_label(egui::Color32::RED, egui::RichText::new(error).monospace());
+                        return;
+                    }
+                };
+                let bytes = match &self.bytes {
+                    Some(bytes) => bytes,
+                    None => return,
+                };
+                let count = layout.element_count(bytes);
+                let pages = count.div_ceil(PAGE_SIZE).max(1);
+                self.page = self.page.min(pages - 1);
+                ui.horizontal(|ui| {
+                    ui.label(format!("{} bytes, {} elements of {} bytes", bytes.len(), count, layout.stride));
+                    ui.separator();
+                    if ui.add_enabled(self.page > 0, egui::Button::new("◀")).clicked() {
+                        self.page -= 1;
+                    }
+                    ui.label(format!("Page {} / {}", self.page + 1, pages));
+                    if ui.add_enabled(self.page + 1 < pages, egui::Button::new("▶")).clicked() {
+                        self.page += 1;
+                    }
+                });
+                if let Some(column) = self.plotted {
+                    plot_ui(ui, layout, bytes, column);
+                }
+                egui::ScrollArea::both().show(ui, |ui| {
+                    egui::Grid::new("BufferInspectorTable").striped(true).show(ui, |ui| {
+                        ui.strong("#");
+                        for (index, column) in layout.columns.iter().enumerate() {
+                            let plotted = self.plotted == Some(index);
+                            if ui.selectable_label(plotted, column.name.as_str()).on_hover_text("Plot this column").clicked() {
+                                self.plotted = if plotted { None } else { Some(index) };
+                            }
+                        }
+                        ui.end_row();
+                        for element in self.page * PAGE_SIZE..((self.page + 1) * PAGE_SIZE).min(count) {
+                            ui.monospace(element.to_string());
+                            for column in 0..layout.columns.len() {
+                                match layout.value(bytes, element, column) {
+                                    Some(value) => ui.monospace(value.to_string()),
+                                    None => ui.monospace("-"),
+                                };
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+    }
+    fn set_layout(&mut self) {
+        self.layout = parse_layout(&self.layout_source);
+        self.plotted = None;
+        self.page = 0;
+    }
+    fn update_readback(&mut self, ctx: &egui::Context, device: &wgpu::Device, queue: &wgpu::Queue, buffer: Option<&gfx::ResourceHandle<gfx::Buffer>>) {
+        if let Some(readback) = &self.readback {
+            match readback.try_read(device) {
+                Some(Ok(bytes)) => {
+                    self.bytes = Some(bytes);
+                    self.readback = None;
+                }
+                Some(Err(err)) => {
+                    self.error = Some(format!("{:#}", err));
+                    self.readback = None;
+                }
+                None => ctx.request_repaint(),
+            }
+        }
+        let buffer = match buffer {
+            Some(buffer) if self.readback.is_none() && (self.live || self.refresh) && self.error.is_none() => buffer,
+            _ => return,
+        };
+        self.refresh = false;
+        match buffer.lock().unwrap().get_handle() {
+            Ok(buffer) => self.readback = Some(gfx::BufferReadback::new(device, queue, buffer)),
+            Err(err) => self.error = Some(format!("{:#}", err)),
+        }
+    }
+}
+
+fn parse_layout(source: &str) -> Result<gfx::BufferLayout, String> {
+    gfx::BufferLayout::parse(source).map_err(|err| format!("{:#}", err))
+}
+
+// Line graph of a column over all the elements of the buffer.
+fn plot_ui(ui: &mut egui::Ui, layout: &gfx::BufferLayout, bytes: &[u8], column: usize) {
+    let count = layout.element_count(bytes);
+    let step = (count / MAX_PLOT_POINTS).max(1);
+    let values: Vec<f64> = (0..count).step_by(step).filter_map(|element| layout.value(bytes, element, column)).map(|value| value.to_f64()).collect();
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    ui.label(format!("{} : min {}, max {}", layout.columns[column].name, min, max));
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(ui.available_width(), PLOT_HEIGHT), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+    if values.len() < 2 || !min.is_finite() || !max.is_finite() {
+        return;
+    }
+    let range = if max > min { max - min } else { 1.0 };
+    let points = values.iter().enumerate().map(|(index, value)| {
+        let x = rect.left() + rect.width() * index as f32 / (values.len() - 1) as f32;
+        let y = rect.bottom() - rect.height() * ((value - min) / range) as f32;
+        egui::pos2(x, y)
+    }).collect();
+    painter.add(egui::Shape::line(points, egui::Stroke::new(1.0, ui.visuals().text_color())));
+}
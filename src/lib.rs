@@ -3,8 +3,36 @@
 
 mod protos;
 mod app;
-mod gfx;
-mod graph;
+mod finder;
+mod inspector;
+mod profiler;
+pub mod gfx;
+pub mod graph;
+#[cfg(feature = "persistence")]
+pub mod project;
+#[cfg(feature = "persistence")]
+mod settings;
+#[cfg(feature = "persistence")]
+mod storage;
+#[cfg(target_arch = "wasm32")]
+mod web;
+#[cfg(feature = "persistence")]
+mod share;
+#[cfg(feature = "persistence")]
+mod history;
+#[cfg(feature = "persistence")]
+mod library;
+#[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+mod recovery;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod cli;
+#[cfg(all(feature = "trace", not(target_arch = "wasm32")))]
+mod trace;
+#[cfg(all(feature = "replay", not(target_arch = "wasm32")))]
+mod replay;
+#[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+mod export;
 
 pub use app::run;
 
@@ -24,7 +52,19 @@ pub async fn start() -> Result<(), JsValue> {
     console_log::init_with_level(log::Level::Debug).expect("could not initialize logger");
     panic::set_hook(Box::new(console_error_panic_hook::hook));
     
-    app::run().await;
+    #[allow(unused_mut)]
+    let mut protos_app = protos::ProtosApp::new();
+    // Shared projects are encoded in the URL fragment.
+    #[cfg(feature = "persistence")]
+    if let Some(fragment) = web::location_fragment() {
+        // Once loaded, the project lives in browser storage. Reloading the page must not replace later edits.
+        if protos_app.open_shared(fragment.as_str()) {
+            if let Err(err) = web::set_location_fragment("") {
+                log::warn!("Failed to clear shared link : {:#}", err);
+            }
+        }
+    }
+    app::run_app(protos_app).await;
 
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,144 @@
+use anyhow::Context;
+
+use crate::{gfx, graph::nodes::SubgraphNode, project::{self, PROJECT_VERSION}, storage::Storage};
+
+const LIBRARY_KEY: &str = "library.json";
+// Library which failed to load, kept aside before the next save overwrites it.
+const LIBRARY_BACKUP_KEY: &str = "library.json.bak";
+
+// Subgraphs are stored like in projects, & migrated along with them.
+#[derive(serde::Deserialize)]
+struct LibraryFile {
+    version: u64,
+    subgraphs: Vec<serde_json::Value>,
+}
+
+#[derive(serde::Serialize)]
+struct LibraryRef<'a> {
+    version: u64,
+    subgraphs: &'a [SubgraphNode],
+}
+
+// Subgraphs saved by the user to be reused across projects, listed in the node finder.
+#[derive(Default)]
+pub struct Library {
+    subgraphs: Vec<SubgraphNode>,
+    read_only: bool, // The stored library could not be loaded nor backed up, saving would lose it.
+}
+
+impl Library {
+    pub fn load(storage: &dyn Storage) -> Self {
+        let json = match storage.read(LIBRARY_KEY) {
+            Ok(Some(json)) => json,
+            Ok(None) => return Self::default(),
+            Err(err) => {
+                log::warn!("Failed to read library : {:#}", err);
+                return Self { read_only: true, ..Default::default() };
+            }
+        };
+        match Self::from_json(json.as_str()) {
+            Ok(subgraphs) => Self { subgraphs, read_only: false },
+            Err(err) => {
+                log::warn!("Failed to load library : {:#}", err);
+                match storage.write(LIBRARY_BACKUP_KEY, json.as_str()) {
+                    Ok(()) => {
+                        log::warn!("Library kept as {}", LIBRARY_BACKUP_KEY);
+                        Self::default()
+                    }
+                    Err(err) => {
+                        log::warn!("Failed to back the library up : {:#}", err);
+                        Self { read_only: true, ..Default::default() }
+                    }
+                }
+            }
+        }
+    }
+    fn from_json(json: &str) -> anyhow::Result<Vec<SubgraphNode>> {
+        let library: LibraryFile = serde_json::from_str(json).context("Library is not valid")?;
+        project::check_version(library.version)?;
+        library.subgraphs.into_iter().enumerate().map(|(index, mut subgraph)| {
+            project::migrate_subgraph(&mut subgraph, library.version)?;
+            // Entries are deep copies, each one defines its resources.
+            gfx::with_resource_scope(|| serde_json::from_value(subgraph))
+                .with_context(|| format!("Failed to read subgraph {} of the library", index))
+        }).collect()
+    }
+    pub fn save(&self, storage: &dyn Storage) -> anyhow::Result<()> {
+        if self.read_only {
+            anyhow::bail!("{} could not be loaded, it is not overwritten", LIBRARY_KEY);
+        }
+        let library = LibraryRef {
+            version: PROJECT_VERSION,
+            subgraphs: &self.subgraphs,
+        };
+        let json = gfx::with_resource_scope(|| serde_json::to_string(&library))?;
+        storage.write(LIBRARY_KEY, json.as_str())
+    }
+    pub fn subgraphs(&self) -> &[SubgraphNode] {
+        &self.subgraphs
+    }
+    // Add a copy of the subgraph, so that later edits of the project do not change the library.
+    pub fn add(&mut self, name: &str, subgraph: &SubgraphNode) -> anyhow::Result<()> {
+        let copy = subgraph.deep_copy(name)?;
+        self.remove(name);
+        self.subgraphs.push(copy);
+        Ok(())
+    }
+    pub fn remove(&mut self, name: &str) {
+        self.subgraphs.retain(|subgraph| subgraph.name() != name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, collections::HashMap};
+
+    use super::*;
+    use crate::graph::Subgraph;
+
+    #[derive(Default)]
+    struct MemoryStorage {
+        values: RefCell<HashMap<String, String>>,
+    }
+
+    impl Storage for MemoryStorage {
+        fn read(&self, key: &str) -> anyhow::Result<Option<String>> {
+            Ok(self.values.borrow().get(key).cloned())
+        }
+        fn write(&self, key: &str, value: &str) -> anyhow::Result<()> {
+            self.values.borrow_mut().insert(key.to_string(), value.to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn saved_library_loads() {
+        let storage = MemoryStorage::default();
+        let mut library = Library::default();
+        library.add("Blur", &SubgraphNode::new(String::from("Blur"), Subgraph::default())).unwrap();
+        library.save(&storage).unwrap();
+        let json: serde_json::Value = serde_json::from_str(storage.read(LIBRARY_KEY).unwrap().unwrap().as_str()).unwrap();
+        assert_eq!(json["version"], PROJECT_VERSION);
+        let library = Library::load(&storage);
+        assert_eq!(library.subgraphs().iter().map(|subgraph| subgraph.name()).collect::<Vec<_>>(), ["Blur"]);
+    }
+
+    #[test]
+    fn broken_library_is_kept() {
+        let storage = MemoryStorage::default();
+        storage.write(LIBRARY_KEY, "{ broken").unwrap();
+        let library = Library::load(&storage);
+        assert!(library.subgraphs().is_empty());
+        assert_eq!(storage.read(LIBRARY_BACKUP_KEY).unwrap().as_deref(), Some("{ broken"));
+        library.save(&storage).unwrap();
+    }
+
+    #[test]
+    fn newer_library_is_kept() {
+        let storage = MemoryStorage::default();
+        let json = serde_json::json!({ "version": PROJECT_VERSION + 1, "subgraphs": [] }).to_string();
+        storage.write(LIBRARY_KEY, json.as_str()).unwrap();
+        Library::load(&storage);
+        assert_eq!(storage.read(LIBRARY_BACKUP_KEY).unwrap(), Some(json));
+    }
+}
@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+
+use egui_node_graph::NodeId;
+
+use crate::{gfx::{DrawCounters, PassProfile}, graph::{node_labels, ProtosGraph}};
+
+const ROW_HEIGHT: f32 = 18.0;
+
+// Timings of the last profiled frame.
+#[derive(Default)]
+pub struct FrameProfile {
+    pub evaluate: f32, // Milliseconds spent on the CPU evaluating the graph.
+    pub record: f32, // Milliseconds spent on the CPU recording commands.
+    pub passes: Vec<PassProfile<Vec<NodeId>>>, // GPU time & statistics of passes, keyed by node path. Read back a few frames later.
+    pub draws: HashMap<Vec<NodeId>, DrawCounters>, // What graphic passes drew, keyed by node path.
+}
+
+// Milliseconds since the given time.
+pub fn elapsed_ms(start: chrono::DateTime<chrono::Utc>) -> f32 {
+    (chrono::Utc::now() - start).num_microseconds().unwrap_or(0) as f32 / 1000.0
+}
+
+// Details of a pass shown when hovering it.
+fn pass_details(name: &str, pass: &PassProfile<Vec<NodeId>>, draws: Option<&DrawCounters>) -> String {
+    let mut details = format!("{} : {:.3} ms, from {:.3} ms", name, pass.duration(), pass.start);
+    if let Some(draws) = draws {
+        details += &format!("\n{} vertices, {} indices, {} triangles, {} instances", draws.vertices, draws.indices, draws.triangles, draws.instances);
+    }
+    if let Some(statistics) = &pass.statistics {
+        details += &format!(
+            "\n{} vertex invocations, {} clipper primitives, {} fragment invocations",
+            statistics.vertex_invocations, statistics.clipper_primitives, statistics.fragment_invocations
+        );
+    }
+    details
+}
+
+// Frame timeline, with a row per pass. Returns the path of the node clicked.
+pub fn timeline_ui(ui: &mut egui::Ui, profile: &FrameProfile, graph: &ProtosGraph, has_timestamps: bool, has_statistics: bool) -> Option<Vec<NodeId>> {
+    ui.label(format!("CPU : evaluate {:.3} ms, record {:.3} ms", profile.evaluate, profile.record));
+    if !has_timestamps {
+        ui.weak("GPU timestamps are not supported by this adapter, only CPU times are measured.");
+    }
+    if !has_statistics {
+        ui.weak("Pipeline statistics are not supported by this adapter, only draw counters are reported.");
+    }
+    let total = profile.passes.iter().map(|pass| pass.end).fold(0.0, f32::max);
+    ui.label(format!("GPU : {} passes, {:.3} ms", profile.passes.len(), total));
+    if profile.passes.is_empty() {
+        return None;
+    }
+    let mut clicked = None;
+    let width = ui.available_width();
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(width, ROW_HEIGHT * profile.passes.len() as f32), egui::Sense::hover());
+    let scale = if total > 0.0 { rect.width() / total } else { 0.0 };
+    for (row, pass) in profile.passes.iter().enumerate() {
+        let top = rect.top() + row as f32 * ROW_HEIGHT;
+        let left = rect.left() + pass.start * scale;
+        let bar = egui::Rect::from_min_max(
+            egui::pos2(left, top + 1.0),
+            egui::pos2((rect.left() + pass.end * scale).max(left + 2.0), top + ROW_HEIGHT - 1.0),
+        );
+        let name = node_labels(graph, &pass.key).join(" > ");
+        let response = ui.interact(bar, ui.id().with(("ProfilerPass", row)), egui::Sense::click());
+        let color = if response.hovered() { ui.visuals().selection.bg_fill } else { ui.visuals().widgets.inactive.bg_fill };
+        ui.painter().rect_filled(bar, 2.0, color);
+        ui.painter().text(
+            egui::pos2(rect.left() + 4.0, top + ROW_HEIGHT / 2.0),
+            egui::Align2::LEFT_CENTER,
+            format!("{} {:.3} ms", name, pass.duration()),
+            egui::TextStyle::Small.resolve(ui.style()),
+            ui.visuals().text_color(),
+        );
+        if response.on_hover_text(pass_details(&name, pass, profile.draws.get(&pass.key))).clicked() {
+            clicked = Some(pass.key.clone());
+        }
+    }
+    clicked
+}
+
+// Passes of the frame as CSV, one row per pass. Missing counters are left empty.
+pub fn to_csv(profile: &FrameProfile, graph: &ProtosGraph) -> String {
+    let mut csv = String::from("pass,start_ms,duration_ms,vertices,indices,triangles,instances,vertex_invocations,clipper_primitives,fragment_invocations\n");
+    for pass in &profile.passes {
+        let name = node_labels(graph, &pass.key).join(" > ").replace('"', "\"\"");
+        let draws = profile.draws.get(&pass.key)
+            .map(|draws| format!("{},{},{},{}", draws.vertices, draws.indices, draws.triangles, draws.instances))
+            .unwrap_or(String::from(",,,"));
+        let statistics = pass.statistics.as_ref()
+            .map(|statistics| format!("{},{},{}", statistics.vertex_invocations, statistics.clipper_primitives, statistics.fragment_invocations))
+            .unwrap_or(String::from(",,"));
+        csv += &format!("\"{}\",{:.3},{:.3},{},{}\n", name, pass.start, pass.duration(), draws, statistics);
+    }
+    csv
+}
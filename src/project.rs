@@ -0,0 +1,349 @@
+use std::{fs, path::Path};
+
+use anyhow::Context;
+
+use crate::{gfx, graph::{ProtosEditorState, ProtosGraphState}};
+
+// Version written in saved projects, increment it & add a migration when the format change.
+pub const PROJECT_VERSION: u64 = 2;
+pub const PROJECT_EXTENSION: &str = "protos";
+
+// Upgrade the raw json of a project from a version to the next one, first as a whole, then each of its editor states.
+// Migrations of editor states also run on subgraphs saved outside of projects, in the library.
+struct Migration {
+    project: fn(&mut serde_json::Value) -> anyhow::Result<()>,
+    state: fn(&mut serde_json::Value) -> anyhow::Result<()>,
+}
+
+// Migration at index i upgrade a project from version i to version i + 1.
+const MIGRATIONS: &[Migration] = &[
+    Migration { project: migrate_legacy_files, state: unchanged },
+    Migration { project: unchanged, state: migrate_shared_resources },
+];
+
+fn unchanged(_value: &mut serde_json::Value) -> anyhow::Result<()> {
+    Ok(())
+}
+
+// Version 0 was made of loose state.json & user_state.json, gathered by Project::load_legacy without metadata.
+fn migrate_legacy_files(project: &mut serde_json::Value) -> anyhow::Result<()> {
+    let object = project.as_object_mut().ok_or(anyhow::anyhow!("Project is not an object"))?;
+    object.insert(String::from("metadata"), serde_json::to_value(ProjectMetadata::default())?);
+    Ok(())
+}
+
+// Version 1 stored resources by value in every node & input using them. Each one becomes a handle defining its own resource,
+// nodes reconnect them on first evaluation so that passes share the resources of the nodes they are connected to again.
+fn migrate_shared_resources(state: &mut serde_json::Value) -> anyhow::Result<()> {
+    let mut next_id = 0;
+    let mut define = |resource: &mut serde_json::Value| {
+        if !resource.is_null() {
+            *resource = serde_json::json!({ "id": next_id, "resource": resource.take() });
+            next_id += 1;
+        }
+    };
+    for node in slots_mut(state, "nodes") {
+        let template = match node.pointer_mut("/user_data/template").and_then(|template| template.as_object_mut()) {
+            Some(template) => template,
+            None => continue,
+        };
+        for (name, fields) in template.iter_mut() {
+            // Each node held a single resource.
+            let handle = match fields.as_object_mut().and_then(|fields| fields.values_mut().next()) {
+                Some(handle) => handle,
+                None => continue,
+            };
+            if let Some(desc) = handle.get_mut("desc").and_then(|desc| desc.as_object_mut()) {
+                match name.as_str() {
+                    "GraphicPass" => {
+                        for field in ["geometry", "vertex_shader", "fragment_shader"] {
+                            desc.get_mut(field).into_iter().for_each(&mut define);
+                        }
+                        if let Some(views) = desc.get_mut("shader_resource_view").and_then(|views| views.as_array_mut()) {
+                            views.iter_mut().for_each(&mut define);
+                        }
+                    }
+                    // Set on evaluation from the connected pass.
+                    "BackbufferPass" => {
+                        desc.remove("origin");
+                    }
+                    _ => {}
+                }
+            }
+            define(handle);
+        }
+    }
+    for input in slots_mut(state, "inputs") {
+        if let Some(value) = input.get_mut("value").and_then(|value| value.as_object_mut()) {
+            for (kind, resource) in value.iter_mut() {
+                if matches!(kind.as_str(), "Texture" | "Buffer" | "Mesh" | "Shader") {
+                    define(resource);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// Live values of a slot map of the graph of an editor state, serialized as slots whose value is null once removed.
+fn slots_mut<'a>(state: &'a mut serde_json::Value, name: &str) -> impl Iterator<Item = &'a mut serde_json::Value> {
+    state.get_mut("graph")
+        .and_then(|graph| graph.get_mut(name))
+        .and_then(|slots| slots.as_array_mut())
+        .into_iter()
+        .flatten()
+        .filter_map(|slot| slot.get_mut("value"))
+        .filter(|value| !value.is_null())
+}
+
+// Run a migration on an editor state & on those of the subgraphs it defines.
+fn migrate_state(state: &mut serde_json::Value, migration: &Migration) -> anyhow::Result<()> {
+    (migration.state)(state)?;
+    for node in slots_mut(state, "nodes") {
+        if let Some(state) = node.pointer_mut("/user_data/template/Subgraph/subgraph/resource/state") {
+            migrate_state(state, migration)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn check_version(version: u64) -> anyhow::Result<()> {
+    if version > PROJECT_VERSION {
+        anyhow::bail!("Project version {} is newer than supported version {}, update protos to open it", version, PROJECT_VERSION);
+    }
+    Ok(())
+}
+
+// Upgrade the raw json of a project to the current version.
+fn migrate(project: &mut serde_json::Value, version: u64) -> anyhow::Result<()> {
+    check_version(version)?;
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        (migration.project)(project)
+            .and_then(|()| project.get_mut("state").map_or(Ok(()), |state| migrate_state(state, migration)))
+            .with_context(|| format!("Failed to migrate project from version {} to {}", from, from + 1))?;
+    }
+    if let Some(object) = project.as_object_mut() {
+        object.insert(String::from("version"), serde_json::Value::from(PROJECT_VERSION));
+    }
+    Ok(())
+}
+
+// Upgrade the raw json of a subgraph node saved with a supported project version, to the current one.
+pub fn migrate_subgraph(subgraph: &mut serde_json::Value, version: u64) -> anyhow::Result<()> {
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        if let Some(state) = subgraph.pointer_mut("/subgraph/resource/state") {
+            migrate_state(state, migration).with_context(|| format!("Failed to migrate subgraph from version {} to {}", from, from + 1))?;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ProjectMetadata {
+    pub created: String, // RFC 3339 dates
+    pub modified: String,
+    pub app_version: String, // Version of protos which last saved the project.
+}
+
+impl Default for ProjectMetadata {
+    fn default() -> Self {
+        let now = chrono::Utc::now().to_rfc3339();
+        Self {
+            created: now.clone(),
+            modified: now,
+            app_version: String::from(env!("CARGO_PKG_VERSION")),
+        }
+    }
+}
+
+impl ProjectMetadata {
+    // Called before saving.
+    pub fn touch(&mut self) {
+        self.modified = chrono::Utc::now().to_rfc3339();
+        self.app_version = String::from(env!("CARGO_PKG_VERSION"));
+    }
+}
+
+#[derive(Default, serde::Deserialize)]
+pub struct Project {
+    pub metadata: ProjectMetadata,
+    pub state: ProtosEditorState,
+    pub user_state: ProtosGraphState,
+}
+
+// Borrowing version of the project to avoid cloning the graph when saving.
+#[derive(serde::Serialize)]
+struct ProjectRef<'a> {
+    version: u64,
+    metadata: &'a ProjectMetadata,
+    state: &'a ProtosEditorState,
+    user_state: &'a ProtosGraphState,
+}
+
+impl Project {
+    pub fn new(state: ProtosEditorState, user_state: ProtosGraphState) -> Self {
+        Self {
+            metadata: ProjectMetadata::default(),
+            state,
+            user_state,
+        }
+    }
+    // Parse a project of any supported version, running migrations if needed.
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        let mut value: serde_json::Value = serde_json::from_str(json).context("Project is not valid json")?;
+        let version = value.get("version")
+            .and_then(|version| version.as_u64())
+            .ok_or(anyhow::anyhow!("Project has no version, this is not a protos project"))?;
+        Self::from_value(value.take(), version)
+    }
+    fn from_value(mut value: serde_json::Value, version: u64) -> anyhow::Result<Self> {
+        migrate(&mut value, version)?;
+        gfx::with_resource_scope(|| serde_json::from_value(value))
+            .with_context(|| format!("Failed to read project (version {})", version))
+    }
+    pub fn to_json(metadata: &ProjectMetadata, state: &ProtosEditorState, user_state: &ProtosGraphState) -> anyhow::Result<String> {
+        let project = ProjectRef {
+            version: PROJECT_VERSION,
+            metadata,
+            state,
+            user_state,
+        };
+        Ok(gfx::with_resource_scope(|| serde_json::to_string_pretty(&project))?)
+    }
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let json = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::from_json(json.as_str()).with_context(|| format!("Failed to load {}", path.display()))
+    }
+    // Import a project from the state.json & user_state.json files written by older versions.
+    pub fn load_legacy(state_path: &Path, user_state_path: &Path) -> anyhow::Result<Self> {
+        let read = |path: &Path| -> anyhow::Result<serde_json::Value> {
+            let json = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+            serde_json::from_str(json.as_str()).with_context(|| format!("Failed to parse {}", path.display()))
+        };
+        let value = serde_json::json!({
+            "version": 0,
+            "state": read(state_path)?,
+            "user_state": read(user_state_path)?,
+        });
+        Self::from_value(value, 0).with_context(|| format!("Failed to import {}", state_path.display()))
+    }
+    // Write to a temporary file first so that a failure does not corrupt an existing project.
+    pub fn save(path: &Path, metadata: &mut ProjectMetadata, state: &ProtosEditorState, user_state: &ProtosGraphState) -> anyhow::Result<()> {
+        metadata.touch();
+        let json = Self::to_json(metadata, state, user_state)?;
+        let temporary_path = path.with_extension("tmp");
+        fs::write(&temporary_path, json).with_context(|| format!("Failed to write {}", temporary_path.display()))?;
+        fs::rename(&temporary_path, path).with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEGACY_V1: &str = include_str!("../tests/fixtures/legacy_v1.protos");
+
+    fn slot(value: serde_json::Value) -> serde_json::Value {
+        serde_json::json!({ "value": value, "version": 1 })
+    }
+
+    fn template(name: &str, handle: serde_json::Value) -> serde_json::Value {
+        let field = if name == "Mesh" { "mesh" } else { "handle" };
+        slot(serde_json::json!({ "user_data": { "template": { name: { field: handle } } } }))
+    }
+
+    fn texture(label: &str) -> serde_json::Value {
+        serde_json::json!({ "desc": { "source": "None", "width": 1, "height": 1, "label": label, "srgb": false } })
+    }
+
+    fn collect_ids(value: &serde_json::Value, ids: &mut Vec<u64>) {
+        match value {
+            serde_json::Value::Object(object) => {
+                if let Some(id) = object.get("id").and_then(|id| id.as_u64()).filter(|_| object.contains_key("resource")) {
+                    ids.push(id);
+                }
+                object.values().for_each(|value| collect_ids(value, ids));
+            }
+            serde_json::Value::Array(array) => array.iter().for_each(|value| collect_ids(value, ids)),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn legacy_files_get_metadata() {
+        let legacy: serde_json::Value = serde_json::from_str(LEGACY_V1).unwrap();
+        let value = serde_json::json!({
+            "version": 0,
+            "state": legacy["state"],
+            "user_state": legacy["user_state"],
+        });
+        let project = Project::from_value(value, 0).unwrap();
+        assert_eq!(project.metadata.app_version, env!("CARGO_PKG_VERSION"));
+        assert!(!project.state.graph.nodes.is_empty());
+    }
+
+    #[test]
+    fn v1_project_loads() {
+        let project = Project::from_json(LEGACY_V1).unwrap();
+        assert_eq!(project.metadata.app_version, "0.1.0");
+        assert!(project.user_state.backbuffer_node.is_some());
+    }
+
+    #[test]
+    fn v1_resources_become_handles() {
+        let mesh = serde_json::json!({ "desc": { "source": { "Shape": { "Quad": { "size": 2.0 } } } } });
+        let pass = serde_json::json!({ "desc": {
+            "geometry": mesh,
+            "render_target_desc": [],
+            "shader_resource_view": [null, texture("view")],
+            "vertex_shader": null,
+            "fragment_shader": null,
+        } });
+        let backbuffer = serde_json::json!({ "desc": { "origin": texture("origin"), "width": 0, "height": 0 } });
+        let mut project = serde_json::json!({
+            "version": 1,
+            "state": { "graph": {
+                "nodes": [
+                    { "value": null, "version": 0 },
+                    template("GraphicPass", pass),
+                    template("BackbufferPass", backbuffer),
+                    template("Mesh", mesh),
+                ],
+                "inputs": [
+                    slot(serde_json::json!({ "value": { "Texture": texture("input") } })),
+                    slot(serde_json::json!({ "value": { "Texture": null } })),
+                    slot(serde_json::json!({ "value": { "Float": 1.0 } })),
+                ],
+            } },
+        });
+        migrate(&mut project, 1).unwrap();
+        assert_eq!(project["version"], PROJECT_VERSION);
+        let nodes = &project["state"]["graph"]["nodes"];
+        let pass = &nodes[1]["value"]["user_data"]["template"]["GraphicPass"]["handle"]["resource"]["desc"];
+        assert_eq!(pass["geometry"]["resource"], mesh);
+        assert_eq!(pass["shader_resource_view"][0], serde_json::Value::Null);
+        assert_eq!(pass["shader_resource_view"][1]["resource"], texture("view"));
+        assert_eq!(pass["vertex_shader"], serde_json::Value::Null);
+        let backbuffer = &nodes[2]["value"]["user_data"]["template"]["BackbufferPass"]["handle"]["resource"]["desc"];
+        assert!(backbuffer.get("origin").is_none());
+        assert_eq!(nodes[3]["value"]["user_data"]["template"]["Mesh"]["mesh"]["resource"], mesh);
+        let inputs = &project["state"]["graph"]["inputs"];
+        assert_eq!(inputs[0]["value"]["value"]["Texture"]["resource"], texture("input"));
+        assert_eq!(inputs[1]["value"]["value"]["Texture"], serde_json::Value::Null);
+        assert_eq!(inputs[2]["value"]["value"]["Float"], 1.0);
+        // Every resource is its own.
+        let mut ids = Vec::new();
+        collect_ids(&project, &mut ids);
+        assert_eq!(ids.len(), 6);
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), 6);
+    }
+
+    #[test]
+    fn newer_version_is_rejected() {
+        let mut project = serde_json::json!({ "version": PROJECT_VERSION + 1 });
+        assert!(migrate(&mut project, PROJECT_VERSION + 1).is_err());
+    }
+}
@@ -1,18 +1,138 @@
-
+use egui::{self, load::SizedTexture};
+use egui_node_graph::*;
 use std::collections::HashMap;
 
-use egui::{self, load::SizedTexture, TextStyle, Vec2};
-use egui_node_graph::*;
+use crate::{finder::NodeFinder, inspector::{BufferInspector, TextureInspector}, profiler::{self, FrameProfile}, gfx, graph::{nodes::SubgraphNode, *}};
+#[cfg(feature = "persistence")]
+use crate::{history::{History, Snapshot}, library::Library, project::{Project, ProjectMetadata, PROJECT_EXTENSION}, settings::Settings, share, storage::{self, Storage}};
+#[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+use std::path::{Path, PathBuf};
+#[cfg(all(feature = "persistence", target_arch = "wasm32"))]
+use std::{cell::RefCell, rc::Rc};
+#[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+use crate::recovery::{RecoveredProject, Recovery};
+#[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+use crate::export::{ExportJob, ExportKind, ExportSettings};
+
+// Opened at startup if there is no recent project.
+#[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+const PROJECT_PATH: &str = "project.protos";
+// Files written by older versions, imported as an untitled project if there is no project to open.
+#[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+const LEGACY_STATE_PATH: &str = "state.json";
+#[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+const LEGACY_USER_STATE_PATH: &str = "user_state.json";
+// Frames an export renders per UI frame, reading each back blocks until the GPU is done.
+#[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+const EXPORT_FRAMES_PER_UPDATE: u32 = 2;
+// Browsers can't write files, the project is kept in the app storage instead.
+#[cfg(all(feature = "persistence", target_arch = "wasm32"))]
+const WEB_PROJECT_KEY: &str = "project.protos";
 
-use crate::{gfx, graph::*};
+const NEW_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::N);
+const GROUP_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::G);
+const ADD_NODE_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(egui::Modifiers::SHIFT, egui::Key::A);
+#[cfg(feature = "persistence")]
+const OPEN_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::O);
+#[cfg(feature = "persistence")]
+const SAVE_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::S);
+#[cfg(feature = "persistence")]
+const SAVE_AS_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND.plus(egui::Modifiers::SHIFT), egui::Key::S);
+// Copy & paste are received as events, these are only displayed.
+#[cfg(feature = "persistence")]
+const COPY_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::C);
+#[cfg(feature = "persistence")]
+const PASTE_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::V);
+#[cfg(feature = "persistence")]
+const DUPLICATE_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::D);
+#[cfg(feature = "persistence")]
+const UNDO_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z);
+#[cfg(feature = "persistence")]
+const REDO_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND.plus(egui::Modifiers::SHIFT), egui::Key::Z);
 
 
 #[derive(Default)]
 pub struct ProtosRuntimeState {
-    available_size: egui::Vec2,
+    displayed_size: (u32, u32), // Of the panel displaying the active backbuffer, which resolves its resolution from it.
     egui_image_filter: wgpu::FilterMode,
     egui_texture_id: egui::TextureId,
     dirty_egui_texture: bool,
+    start_time: Option<chrono::DateTime<chrono::Utc>>,
+    frame: FrameInfo,
+    shadertoy_import: Option<ShadertoyImport>,
+    error: Option<String>, // Displayed in a window until dismissed.
+    pending_action: Option<ProjectAction>, // Waiting for the user to decide what to do with unsaved changes.
+    quit: bool,
+    #[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+    recovered: Vec<RecoveredProject>, // Left by crashed sessions, waiting for the user to restore or discard them.
+    #[cfg(feature = "persistence")]
+    history_pending: bool, // Edits not recorded in history yet.
+    #[cfg(feature = "persistence")]
+    link_import: Option<LinkImport>,
+    #[cfg(all(feature = "persistence", target_arch = "wasm32"))]
+    uploaded_project: Rc<RefCell<Option<anyhow::Result<(String, Vec<u8>)>>>>, // Filled once the browser read the file.
+    subgraph_path: Vec<NodeId>, // Subgraph nodes opened for editing, from the top level graph.
+    graph_rect: egui::Rect, // Area of the node graph editor on screen.
+    node_finder: Option<NodeFinder>,
+    texture_inspector: Option<TextureInspector>,
+    buffer_inspector: Option<BufferInspector>,
+    show_profiler: bool,
+    profiler: Option<gfx::GpuProfiler<NodeId>>, // Created when the profiler is shown, times passes of the flattened graph.
+    profiled_origins: HashMap<NodeId, Vec<NodeId>>, // Origins of the flattened graph whose timings are read back.
+    frame_profile: FrameProfile,
+    #[cfg(feature = "persistence")]
+    library_save: Option<LibrarySave>,
+    #[cfg(all(feature = "trace", not(target_arch = "wasm32")))]
+    trace_capture: Option<TraceCapture>,
+    #[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+    export: Option<Export>,
+    #[cfg(feature = "persistence")]
+    copied_nodes: Option<String>, // Last nodes copied, for the paste menu which can't read the system clipboard.
+}
+
+#[cfg(feature = "persistence")]
+pub struct LibrarySave {
+    node: SubgraphNode,
+    name: String,
+    error: Option<String>,
+}
+
+#[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+pub struct Export {
+    node: NodeId, // Backbuffer pass of the top level graph.
+    settings: ExportSettings,
+    path: String,
+    job: Option<ExportJob>, // Export in progress.
+    result: Option<Result<String, String>>, // Of the last export.
+}
+
+#[cfg(all(feature = "trace", not(target_arch = "wasm32")))]
+pub struct TraceCapture {
+    directory: String,
+    frames: u32,
+    result: Option<Result<String, String>>, // Of the last capture.
+}
+
+#[cfg(feature = "persistence")]
+#[derive(Default)]
+pub struct LinkImport {
+    link: String,
+    error: Option<String>,
+}
+
+#[derive(Default)]
+pub struct ShadertoyImport {
+    source: String,
+    error: Option<String>,
+}
+
+// Actions replacing the current project, which might discard unsaved changes.
+pub enum ProjectAction {
+    New,
+    Import(Box<(ProtosEditorState, ProtosGraphState)>),
+    #[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+    Open(PathBuf),
+    Quit,
 }
 
 #[derive(Default)]
@@ -23,6 +143,22 @@ pub struct ProtosApp {
 
     user_state: ProtosGraphState,
     runtime_state: ProtosRuntimeState,
+    modified: bool, // Project has changes which are not saved.
+    #[cfg(feature = "persistence")]
+    metadata: ProjectMetadata,
+    // None for untitled projects, which are not saved anywhere yet.
+    #[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+    project_path: Option<PathBuf>,
+    #[cfg(feature = "persistence")]
+    settings: Settings,
+    #[cfg(feature = "persistence")]
+    storage: Option<Box<dyn Storage>>,
+    #[cfg(feature = "persistence")]
+    history: History,
+    #[cfg(feature = "persistence")]
+    library: Library,
+    #[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+    recovery: Option<Recovery>,
 }
 
 
@@ -31,77 +167,678 @@ impl ProtosApp {
     pub fn new() -> Self 
     {
         let runtime_state = ProtosRuntimeState {
-            available_size: Vec2::new(500.0, 500.0),
+            displayed_size: (500, 500),
             egui_image_filter: wgpu::FilterMode::Nearest,
             egui_texture_id: egui::TextureId::default(),
             dirty_egui_texture: false,
+            start_time: None,
+            frame: FrameInfo::default(),
+            shadertoy_import: None,
+            error: None,
+            pending_action: None,
+            quit: false,
+            #[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+            recovered: Vec::new(),
+            #[cfg(feature = "persistence")]
+            history_pending: false,
+            #[cfg(feature = "persistence")]
+            link_import: None,
+            #[cfg(all(feature = "persistence", target_arch = "wasm32"))]
+            uploaded_project: Rc::default(),
+            subgraph_path: Vec::new(),
+            graph_rect: egui::Rect::NOTHING,
+            node_finder: None,
+            texture_inspector: None,
+            buffer_inspector: None,
+            show_profiler: false,
+            profiler: None,
+            profiled_origins: HashMap::new(),
+            frame_profile: FrameProfile::default(),
+            #[cfg(feature = "persistence")]
+            library_save: None,
+            #[cfg(all(feature = "trace", not(target_arch = "wasm32")))]
+            trace_capture: None,
+            #[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+            export: None,
+            #[cfg(feature = "persistence")]
+            copied_nodes: None,
+        };
+        #[allow(unused_mut)]
+        let mut app = Self {
+            runtime_state,
+            ..Default::default()
         };
         #[cfg(feature = "persistence")]
         {
-            use std::fs;
-
-            let state_json = fs::read_to_string("state.json");
-            let user_state_json = fs::read_to_string("user_state.json");
-            if state_json.is_ok() && user_state_json.is_ok() {
-                let state = serde_json::from_str(state_json.unwrap().as_str()).unwrap_or_default();
-                let user_state = serde_json::from_str(user_state_json.unwrap().as_str()).unwrap_or_default();
-                Self {
-                    state,
-                    user_state,
-                    runtime_state
+            app.storage = storage::default_storage()
+                .map_err(|err| log::warn!("Settings will not be saved : {:#}", err))
+                .ok();
+            app.settings = app.storage.as_deref().map(Settings::load).unwrap_or_default();
+            app.library = app.storage.as_deref().map(Library::load).unwrap_or_default();
+            app.history = History::new(app.settings.history_depth.max(1));
+            app.load_startup_project();
+            app.reset_history();
+            #[cfg(not(target_arch = "wasm32"))]
+            match Recovery::start() {
+                Ok((recovery, recovered)) => {
+                    app.recovery = Some(recovery);
+                    app.runtime_state.recovered = recovered;
                 }
-            } else {
-                Self {
-                    state: ProtosEditorState::default(),
-                    user_state: ProtosGraphState::default(),
-                    runtime_state
+                Err(err) => log::warn!("Autosave disabled : {:#}", err),
+            }
+        }
+        app
+    }
+
+    // Called on clean exit.
+    pub fn exit(&mut self) {
+        #[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+        if let Some(recovery) = self.recovery.take() {
+            recovery.end();
+        }
+    }
+
+    #[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+    fn update_recovery(&mut self) {
+        if let Some(recovery) = &mut self.recovery {
+            recovery.update(self.project_path.as_deref(), self.modified, || {
+                Project::to_json(&self.metadata, &self.state, &self.user_state)
+            });
+        }
+    }
+
+    #[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+    fn load_startup_project(&mut self) {
+        let legacy_state_path = Path::new(LEGACY_STATE_PATH);
+        let legacy_user_state_path = Path::new(LEGACY_USER_STATE_PATH);
+        if let Some(path) = self.settings.recent_projects.first().cloned() {
+            self.open(&path);
+        } else if Path::new(PROJECT_PATH).exists() {
+            self.open(Path::new(PROJECT_PATH));
+        } else if legacy_state_path.exists() && legacy_user_state_path.exists() {
+            match Project::load_legacy(legacy_state_path, legacy_user_state_path) {
+                Ok(project) => {
+                    self.set_project(project.state, project.user_state);
+                    // Not saved in the new format yet.
+                    self.set_unsaved();
                 }
+                Err(err) => self.report_error(err),
+            }
+        }
+    }
+
+    #[cfg(all(feature = "persistence", target_arch = "wasm32"))]
+    fn load_startup_project(&mut self) {
+        let json = match self.storage.as_ref().map(|storage| storage.read(WEB_PROJECT_KEY)) {
+            Some(Ok(Some(json))) => json,
+            Some(Err(err)) => return self.report_error(err),
+            _ => return,
+        };
+        match Project::from_json(json.as_str()) {
+            Ok(project) => {
+                self.set_project(project.state, project.user_state);
+                self.metadata = project.metadata;
             }
+            Err(err) => self.report_error(err.context("Failed to restore the project from browser storage")),
         }
-        #[cfg(not(feature = "persistence"))]
-        Self {
-            state: ProtosEditorState::default(),
-            user_state: ProtosGraphState::default(),
-            runtime_state
+    }
+
+    // Replace the project with the one encoded in a shared link, it is only saved once edited.
+    #[cfg(all(feature = "persistence", target_arch = "wasm32"))]
+    // Returns true if the project was loaded.
+    pub fn open_shared(&mut self, encoded: &str) -> bool {
+        let project = share::decode(encoded).and_then(|json| Project::from_json(json.as_str()));
+        match project {
+            Ok(project) => {
+                self.set_project(project.state, project.user_state);
+                self.metadata = project.metadata;
+                true
+            }
+            Err(err) => {
+                self.report_error(err.context("Failed to open shared project"));
+                false
+            }
         }
     }
 
-    /// If the persistence function is enabled,
-    /// Called by the frame work to save state before shutdown.
-    pub fn save(&mut self) {
-        
+    #[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+    fn copy_share_link(&mut self, ctx: &egui::Context) {
+        match Project::to_json(&self.metadata, &self.state, &self.user_state) {
+            Ok(json) => ctx.output_mut(|output| output.copied_text = share::link(json.as_str())),
+            Err(err) => self.report_error(err),
+        }
+    }
+
+    // The page address becomes the link, there is no clipboard access on web.
+    #[cfg(all(feature = "persistence", target_arch = "wasm32"))]
+    fn copy_share_link(&mut self, _ctx: &egui::Context) {
+        let result = Project::to_json(&self.metadata, &self.state, &self.user_state)
+            .and_then(|json| crate::web::set_location_fragment(share::encode(json.as_str()).as_str()));
+        if let Err(err) = result {
+            self.report_error(err);
+        }
+    }
+
+    #[cfg(feature = "persistence")]
+    fn save_settings(&self) {
+        if let Some(storage) = &self.storage {
+            if let Err(err) = self.settings.save(storage.as_ref()) {
+                log::warn!("{:#}", err);
+            }
+        }
+    }
+
+    fn report_error(&mut self, err: anyhow::Error) {
+        log::error!("{:#}", err);
+        self.runtime_state.error = Some(format!("{:#}", err));
+    }
+
+    // Replace the current project with an untitled one.
+    fn set_project(&mut self, state: ProtosEditorState, user_state: ProtosGraphState) {
+        self.state = state;
+        self.user_state = user_state;
+        self.modified = false;
         #[cfg(feature = "persistence")]
         {
-            use std::fs;
-            let json_state = serde_json::to_string(&self.state).unwrap();
-            let json_user_state = serde_json::to_string(&self.user_state).unwrap();
-            fs::write("state.json", json_state).expect("Unable to write state file");
-            fs::write("user_state.json", json_user_state).expect("Unable to write user state file");
+            self.metadata = ProjectMetadata::default();
+        }
+        #[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+        {
+            self.project_path = None;
+        }
+        self.runtime_state.subgraph_path.clear();
+        // Restart time so that shaders start from their first frame.
+        self.runtime_state.start_time = None;
+        #[cfg(feature = "persistence")]
+        self.reset_history();
+    }
+
+    // Subgraph nodes opened for editing, the last one being displayed. Closes the ones which do not exist anymore.
+    fn subgraph_stack(&mut self) -> Vec<SubgraphNode> {
+        let mut stack: Vec<SubgraphNode> = Vec::new();
+        for (depth, node_id) in self.runtime_state.subgraph_path.iter().enumerate() {
+            let node = match stack.last() {
+                Some(parent) => SubgraphNode::find(&parent.subgraph().lock().unwrap().state.graph, *node_id).cloned(),
+                None => SubgraphNode::find(&self.state.graph, *node_id).cloned(),
+            };
+            match node {
+                Some(node) => stack.push(node),
+                None => {
+                    self.runtime_state.subgraph_path.truncate(depth);
+                    break;
+                }
+            }
+        }
+        stack
+    }
+
+    // Run f on the graph being edited, which is either the top level one or the last opened subgraph.
+    fn with_edited_state<T>(&mut self, f: impl FnOnce(&mut ProtosEditorState, &mut ProtosGraphState) -> T) -> T {
+        match self.subgraph_stack().last() {
+            Some(node) => f(&mut node.subgraph().lock().unwrap().state, &mut self.user_state),
+            None => f(&mut self.state, &mut self.user_state),
+        }
+    }
+
+    #[cfg(feature = "persistence")]
+    fn node_templates(&self) -> AllProtosNodeTemplates {
+        AllProtosNodeTemplates { library: self.library.subgraphs().to_vec() }
+    }
+    #[cfg(not(feature = "persistence"))]
+    fn node_templates(&self) -> AllProtosNodeTemplates {
+        AllProtosNodeTemplates::default()
+    }
+
+    fn group_selection(&mut self) {
+        let result = self.with_edited_state(|state, _| {
+            let selected_nodes = state.selected_nodes.clone();
+            group_nodes(state, &selected_nodes)
+        });
+        match result {
+            Ok(_) => self.mark_modified(),
+            Err(err) => self.report_error(err),
+        }
+    }
+
+    #[cfg(feature = "persistence")]
+    fn save_library(&mut self) {
+        if let Some(storage) = &self.storage {
+            if let Err(err) = self.library.save(storage.as_ref()) {
+                self.report_error(err.context("Failed to save the library"));
+            }
+        }
+    }
+
+    // Open the node finder at the pointer, or at the center of the graph if the pointer is elsewhere.
+    fn pointer_position(&self, ctx: &egui::Context) -> egui::Pos2 {
+        ctx.input(|input| input.pointer.hover_pos())
+            .filter(|position| self.runtime_state.graph_rect.contains(*position))
+            .unwrap_or(self.runtime_state.graph_rect.center())
+    }
+
+    // Position in the edited graph of a position on screen.
+    fn graph_position(graph_rect: egui::Rect, state: &ProtosEditorState, position: egui::Pos2) -> egui::Pos2 {
+        // Node positions are relative to the editor & its pan.
+        (position - graph_rect.min - state.pan_zoom.pan).to_pos2()
+    }
+
+    fn open_node_finder(&mut self, ctx: &egui::Context) {
+        self.runtime_state.node_finder = Some(NodeFinder::new(self.pointer_position(ctx)));
+    }
+
+    fn node_finder_ui(&mut self, ctx: &egui::Context) {
+        let templates = self.node_templates().all_kinds();
+        let mut open = true;
+        let picked = match &mut self.runtime_state.node_finder {
+            Some(finder) => finder.ui(ctx, &templates, &mut open).map(|template| (template, finder.position())),
+            None => return,
+        };
+        if !open {
+            self.runtime_state.node_finder = None;
+        }
+        if let Some((template, position)) = picked {
+            let graph_rect = self.runtime_state.graph_rect;
+            self.with_edited_state(|state, user_state| {
+                let position = Self::graph_position(graph_rect, state, position);
+                add_node(state, user_state, &template, position);
+            });
+            self.mark_modified();
+        }
+    }
+
+    #[cfg(feature = "persistence")]
+    fn has_selection(&mut self) -> bool {
+        self.with_edited_state(|state, _| !state.selected_nodes.is_empty())
+    }
+
+    #[cfg(feature = "persistence")]
+    fn copy_selection(&mut self, ctx: &egui::Context) {
+        match self.with_edited_state(|state, _| copy_nodes(state, &state.selected_nodes)) {
+            Ok(text) => {
+                ctx.output_mut(|output| output.copied_text = text.clone());
+                self.runtime_state.copied_nodes = Some(text);
+            }
+            Err(err) => self.report_error(err),
+        }
+    }
+
+    // Paste nodes at the given screen position.
+    #[cfg(feature = "persistence")]
+    fn paste(&mut self, text: &str, position: egui::Pos2) {
+        let graph_rect = self.runtime_state.graph_rect;
+        let result = self.with_edited_state(|state, _| {
+            let position = Self::graph_position(graph_rect, state, position);
+            paste_nodes(state, text, Some(position))
+        });
+        match result {
+            Ok(_) => self.mark_modified(),
+            Err(err) => self.report_error(err.context("Failed to paste nodes")),
+        }
+    }
+
+    #[cfg(feature = "persistence")]
+    fn duplicate_selection(&mut self) {
+        let result = self.with_edited_state(|state, _| {
+            let text = copy_nodes(state, &state.selected_nodes)?;
+            paste_nodes(state, text.as_str(), None)
+        });
+        match result {
+            Ok(_) => self.mark_modified(),
+            Err(err) => self.report_error(err),
+        }
+    }
+
+    fn mark_modified(&mut self) {
+        self.modified = true;
+        #[cfg(feature = "persistence")]
+        {
+            self.runtime_state.history_pending = true;
+        }
+    }
+
+    // The project was just saved, undoing back to this step makes it unmodified again.
+    #[cfg(feature = "persistence")]
+    fn mark_saved(&mut self) {
+        if self.runtime_state.history_pending {
+            self.record_history();
+        }
+        self.history.mark_saved();
+        self.modified = false;
+    }
+
+    // The project does not match any file, even once edits are undone.
+    fn set_unsaved(&mut self) {
+        #[cfg(feature = "persistence")]
+        self.history.clear_saved();
+        self.modified = true;
+    }
+
+    #[cfg(feature = "persistence")]
+    fn snapshot(&self) -> (serde_json::Result<String>, gfx::ResourceCapture) {
+        gfx::with_resource_capture(|| serde_json::to_string(&(&self.state, &self.user_state)))
+    }
+
+    #[cfg(feature = "persistence")]
+    fn reset_history(&mut self) {
+        self.runtime_state.history_pending = false;
+        match self.snapshot() {
+            (Ok(json), resources) => self.history.reset(json, resources),
+            (Err(err), _) => log::warn!("Failed to snapshot project : {:#}", err),
+        }
+    }
+
+    #[cfg(feature = "persistence")]
+    fn record_history(&mut self) {
+        self.runtime_state.history_pending = false;
+        match self.snapshot() {
+            (Ok(json), resources) => self.history.record(json, resources),
+            (Err(err), _) => log::warn!("Failed to snapshot project : {:#}", err),
+        }
+    }
+
+    // Record pending edits once the user is done with them, so that a slider drag or a text edit is a single step.
+    #[cfg(feature = "persistence")]
+    fn update_history(&mut self, ctx: &egui::Context) {
+        if !self.runtime_state.history_pending {
+            return;
+        }
+        let editing = ctx.input(|input| input.pointer.any_down()) || ctx.memory(|memory| memory.focus().is_some());
+        if !editing {
+            self.record_history();
+        }
+    }
+
+    #[cfg(feature = "persistence")]
+    fn restore_snapshot(&mut self, snapshot: Option<Snapshot>) {
+        let snapshot = match snapshot {
+            Some(snapshot) => snapshot,
+            None => return,
+        };
+        match gfx::with_restored_resources(&snapshot.resources, || serde_json::from_str::<(ProtosEditorState, ProtosGraphState)>(snapshot.json.as_str())) {
+            Ok((state, user_state)) => {
+                self.state = state;
+                self.user_state = user_state;
+                self.modified = !self.history.is_saved();
+            }
+            Err(err) => self.report_error(anyhow::Error::from(err).context("Failed to restore history")),
+        }
+    }
+
+    #[cfg(feature = "persistence")]
+    fn undo(&mut self) {
+        if self.runtime_state.history_pending {
+            self.record_history();
+        }
+        let snapshot = self.history.undo();
+        self.restore_snapshot(snapshot);
+    }
+
+    #[cfg(feature = "persistence")]
+    fn redo(&mut self) {
+        if self.runtime_state.history_pending {
+            self.record_history();
+        }
+        let snapshot = self.history.redo();
+        self.restore_snapshot(snapshot);
+    }
+
+    #[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+    fn add_recent_project(&mut self, path: &Path) {
+        self.settings.add_recent_project(path);
+        self.save_settings();
+    }
+
+    #[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+    fn open(&mut self, path: &Path) {
+        match Project::load(path) {
+            Ok(project) => {
+                self.set_project(project.state, project.user_state);
+                self.metadata = project.metadata;
+                self.project_path = Some(path.to_path_buf());
+                self.add_recent_project(path);
+            }
+            Err(err) => {
+                if !path.exists() {
+                    self.settings.remove_recent_project(path);
+                    self.save_settings();
+                }
+                self.report_error(err);
+            }
+        }
+    }
+
+    // Save the project to its file, asking for one if it is untitled. Returns true if the project was saved.
+    #[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+    fn save(&mut self) -> bool {
+        match self.project_path.clone() {
+            Some(path) => self.save_to(&path),
+            None => self.save_as(),
+        }
+    }
+
+    #[cfg(all(feature = "persistence", target_arch = "wasm32"))]
+    fn save(&mut self) -> bool {
+        self.metadata.touch();
+        let result = Project::to_json(&self.metadata, &self.state, &self.user_state)
+            .and_then(|json| match &self.storage {
+                Some(storage) => storage.write(WEB_PROJECT_KEY, json.as_str()),
+                None => Err(anyhow::anyhow!("Browser storage is not available")),
+            });
+        match result {
+            Ok(()) => {
+                self.mark_saved();
+                true
+            }
+            Err(err) => {
+                self.report_error(err.context("Failed to save the project in browser storage"));
+                false
+            }
+        }
+    }
+
+    #[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+    fn save_to(&mut self, path: &Path) -> bool {
+        match Project::save(path, &mut self.metadata, &self.state, &self.user_state) {
+            Ok(()) => {
+                self.project_path = Some(path.to_path_buf());
+                self.mark_saved();
+                self.add_recent_project(path);
+                true
+            }
+            Err(err) => {
+                self.report_error(err);
+                false
+            }
+        }
+    }
+
+    #[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+    fn save_as(&mut self) -> bool {
+        let dialog = rfd::FileDialog::new()
+            .add_filter("Protos project", &[PROJECT_EXTENSION])
+            .set_file_name(format!("{}.{}", self.project_name(), PROJECT_EXTENSION));
+        match dialog.save_file() {
+            Some(path) => self.save_to(&path.with_extension(PROJECT_EXTENSION)),
+            None => false,
+        }
+    }
+
+    // Browsers only let us download the project, it stays in browser storage.
+    #[cfg(all(feature = "persistence", target_arch = "wasm32"))]
+    fn save_as(&mut self) -> bool {
+        let file_name = format!("{}.{}", self.project_name(), PROJECT_EXTENSION);
+        let result = Project::to_json(&self.metadata, &self.state, &self.user_state)
+            .and_then(|json| crate::web::download(file_name.as_str(), json.as_bytes(), "application/json"));
+        match result {
+            Ok(()) => true,
+            Err(err) => {
+                self.report_error(err.context("Failed to download the project"));
+                false
+            }
+        }
+    }
+
+    #[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+    fn open_dialog(&mut self) {
+        let dialog = rfd::FileDialog::new()
+            .add_filter("Protos project", &[PROJECT_EXTENSION]);
+        if let Some(path) = dialog.pick_file() {
+            self.request_action(ProjectAction::Open(path));
         }
     }
+
+    #[cfg(all(feature = "persistence", target_arch = "wasm32"))]
+    fn open_dialog(&mut self) {
+        let uploaded_project = self.runtime_state.uploaded_project.clone();
+        let result = crate::web::upload(&format!(".{}", PROJECT_EXTENSION), move |result| {
+            *uploaded_project.borrow_mut() = Some(result);
+        });
+        if let Err(err) = result {
+            self.report_error(err);
+        }
+    }
+
+    // Browser callbacks are asynchronous, their results are picked up here.
+    #[cfg(all(feature = "persistence", target_arch = "wasm32"))]
+    fn update_web(&mut self) {
+        let uploaded_project = self.runtime_state.uploaded_project.borrow_mut().take();
+        if let Some(result) = uploaded_project {
+            let project = result.and_then(|(name, bytes)| {
+                let json = String::from_utf8(bytes)?;
+                Project::from_json(json.as_str()).map_err(|err| err.context(format!("Failed to open {}", name)))
+            });
+            match project {
+                Ok(project) => self.request_action(ProjectAction::Import(Box::new((project.state, project.user_state)))),
+                Err(err) => self.report_error(err),
+            }
+        }
+        // Browser storage is the save on web, keep it up to date once edits are recorded.
+        // Wait for the error to be dismissed before trying again.
+        if self.modified && !self.runtime_state.history_pending && self.runtime_state.error.is_none() {
+            self.save();
+        }
+    }
+
+    fn has_unsaved_changes(&self) -> bool {
+        // Nothing can be saved without persistence, no need to ask.
+        cfg!(feature = "persistence") && self.modified
+    }
+
+    fn request_action(&mut self, action: ProjectAction) {
+        if self.has_unsaved_changes() {
+            self.runtime_state.pending_action = Some(action);
+        } else {
+            self.perform_action(action);
+        }
+    }
+
+    fn perform_action(&mut self, action: ProjectAction) {
+        match action {
+            ProjectAction::New => self.set_project(ProtosEditorState::default(), ProtosGraphState::default()),
+            ProjectAction::Import(project) => {
+                let (state, user_state) = *project;
+                self.set_project(state, user_state);
+                self.set_unsaved();
+            }
+            #[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+            ProjectAction::Open(path) => self.open(&path),
+            ProjectAction::Quit => self.runtime_state.quit = true,
+        }
+    }
+
+    pub fn request_quit(&mut self) {
+        self.request_action(ProjectAction::Quit);
+    }
+
+    pub fn should_quit(&self) -> bool {
+        self.runtime_state.quit
+    }
+
+    #[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+    fn project_name(&self) -> String {
+        match &self.project_path {
+            Some(path) => path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default(),
+            None => String::from("Untitled"),
+        }
+    }
+
+    #[cfg(any(not(feature = "persistence"), target_arch = "wasm32"))]
+    fn project_name(&self) -> String {
+        String::from("Untitled")
+    }
+
+    // Window title, with a marker for unsaved changes.
+    pub fn title(&self) -> String {
+        let marker = if self.has_unsaved_changes() { "*" } else { "" };
+        format!("{}{} - Protos RS", self.project_name(), marker)
+    }
     
     /// Called each time the UI needs repainting, which may be many times per second.
     /// Put your widgets into a `SidePanel`, `TopPanel`, `CentralPanel`, `Window` or `Area`.
     pub fn ui(&mut self, ctx: &egui::Context, device : &wgpu::Device, queue : &wgpu::Queue, cmd : &mut wgpu::CommandEncoder, egui_renderer : &mut egui_wgpu::Renderer) {
+        self.shortcuts(ctx);
         egui::TopBottomPanel::top("top").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
                 ui.menu_button("Project", |ui| {
-                    if ui.button("Compile").clicked() {
-                        
+                    if ui.add(egui::Button::new("New").shortcut_text(ctx.format_shortcut(&NEW_SHORTCUT))).clicked() {
+                        self.request_action(ProjectAction::New);
+                        ui.close_menu();
                     }
-                    if ui.button("Save").clicked() {
-                        self.save();
+                    #[cfg(feature = "persistence")]
+                    self.project_menu_ui(ctx, ui);
+                    ui.separator();
+                    if ui.button("Import Shadertoy...").clicked() {
+                        self.runtime_state.shadertoy_import = Some(ShadertoyImport::default());
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    if ui.button("Quit").clicked() {
+                        self.request_quit();
+                        ui.close_menu();
+                    }
+                });
+                ui.menu_button("Edit", |ui| self.edit_menu_ui(ctx, ui));
+                ui.menu_button("View", |ui| {
+                    ui.checkbox(&mut self.runtime_state.show_profiler, "Profiler");
+                    #[cfg(all(feature = "trace", not(target_arch = "wasm32")))]
+                    if ui.button("Capture trace...").clicked() {
+                        ui.close_menu();
+                        self.runtime_state.trace_capture = Some(TraceCapture {
+                            directory: String::from("trace"),
+                            frames: 1,
+                            result: None,
+                        });
                     }
                 });
             });
         });
+        self.shadertoy_import_ui(ctx);
+        self.node_finder_ui(ctx);
+        #[cfg(feature = "persistence")]
+        self.link_import_ui(ctx);
+        #[cfg(feature = "persistence")]
+        self.library_save_ui(ctx);
+        #[cfg(all(feature = "trace", not(target_arch = "wasm32")))]
+        self.trace_capture_ui(ctx);
+        #[cfg(feature = "persistence")]
+        self.unsaved_changes_ui(ctx);
+        #[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+        self.recovery_ui(ctx);
+        self.error_ui(ctx);
+        self.texture_inspector_ui(ctx, device, queue);
+        self.buffer_inspector_ui(ctx, device, queue);
+        #[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+        self.export_ui(ctx, device, queue);
+        self.update_frame();
+        self.problems_ui(ctx);
+        self.profiler_ui(ctx);
         // Render zone
         egui::SidePanel::right("RenderPanel")
             .default_width(ctx.used_size().x / 2.0)
             .resizable(true)
             .show(ctx, |ui| {
                 egui::menu::bar(ui, |ui| {
-                    // Do we really need those ? Texture size exactly match the UI...
+                    // Used when the resolution of the backbuffer does not match the panel.
                     ui.menu_button("Sampling", |ui| {
                         if ui.button("Nearest").clicked() {
                             self.runtime_state.egui_image_filter = wgpu::FilterMode::Nearest;
@@ -113,19 +850,16 @@ impl ProtosApp {
                         }
                     });
                 });
-                self.runtime_state.available_size = ui.available_size();
+                let available_size = ui.available_size();
+                self.runtime_state.displayed_size = ((available_size.x as u32).max(1), (available_size.y as u32).max(1));
                 if self.user_state.backbuffer_node.is_some() {
                     let node = &self.state.graph[self.user_state.backbuffer_node.unwrap()];
 
                     match &node.user_data.template {
                         ProtosNodeTemplate::BackbufferPass(pass_node) => {
-                            gfx::visit_resource_mut(&pass_node.handle, |pass| {
-                                // Resize render_target if required.
-                                let render_target_size = egui::Vec2::new(pass.get_width() as f32, pass.get_height() as f32);
-                                if self.runtime_state.available_size != render_target_size  {
-                                    // resize, egui texture id
-                                    pass.set_size(self.runtime_state.available_size.x as u32, self.runtime_state.available_size.y as u32);
-                                }
+                            gfx::visit_resource(&pass_node.handle, |pass| {
+                                // Resized on evaluation, from the displayed size & its resolution mode.
+                                let (width, height) = (pass.get_width(), pass.get_height());
                                 let view_result = pass.get_view_handle();
                                 match view_result {
                                     Ok(view) => {
@@ -142,10 +876,14 @@ impl ProtosApp {
                                                 self.runtime_state.egui_texture_id
                                             );
                                         //}
-                                        ui.image(egui::ImageSource::Texture(SizedTexture {
+                                        // Fit the backbuffer in the panel, keeping its aspect ratio.
+                                        let scale = (available_size.x / width as f32).min(available_size.y / height as f32);
+                                        let size = egui::vec2(width as f32, height as f32) * scale;
+                                        let (rect, _) = ui.allocate_exact_size(available_size, egui::Sense::hover());
+                                        egui::Image::new(SizedTexture {
                                             id: self.runtime_state.egui_texture_id,
-                                            size: ui.available_size(),
-                                        }));
+                                            size,
+                                        }).paint_at(ui, egui::Rect::from_center_size(rect.center(), size));
                                     },
                                     Err(e) => {
                                         let message = format!("{}", e);
@@ -163,66 +901,184 @@ impl ProtosApp {
             });
         
         // Node graph
+        let subgraph_stack = self.subgraph_stack();
+        self.user_state.edited_path = self.runtime_state.subgraph_path.clone();
+        let node_templates = self.node_templates();
+        let mut closed_depth = None;
+        let mut finder_requested = false;
         let graph_response = egui::CentralPanel::default()
             .show(ctx, |ui| {
-                self.state
-                    .draw_graph_editor(ui, AllProtosNodeTemplates, &mut self.user_state, vec![])
+                if !subgraph_stack.is_empty() {
+                    ui.horizontal(|ui| {
+                        if ui.button("Project").clicked() {
+                            closed_depth = Some(0);
+                        }
+                        for (depth, node) in subgraph_stack.iter().enumerate() {
+                            ui.label(">");
+                            if ui.button(node.name()).clicked() {
+                                closed_depth = Some(depth + 1);
+                            }
+                        }
+                    });
+                }
+                self.runtime_state.graph_rect = ui.available_rect_before_wrap();
+                // The finder of the editor is replaced by ours, before it is ever drawn.
+                match subgraph_stack.last() {
+                    Some(node) => {
+                        let state = &mut node.subgraph().lock().unwrap().state;
+                        let response = state.draw_graph_editor(ui, node_templates, &mut self.user_state, vec![]);
+                        finder_requested = state.node_finder.take().is_some();
+                        response
+                    }
+                    None => {
+                        let response = self.state.draw_graph_editor(ui, node_templates, &mut self.user_state, vec![]);
+                        finder_requested = self.state.node_finder.take().is_some();
+                        response
+                    }
+                }
             })
             .inner;
+        if let Some(depth) = closed_depth {
+            self.runtime_state.subgraph_path.truncate(depth);
+        }
+        if finder_requested {
+            self.open_node_finder(ctx);
+        }
 
-        // This might not be necessary for protos...
         for node_response in graph_response.node_responses {
-            // Here, we ignore all other graph events. But you may find
-            // some use for them. For example, by playing a sound when a new
-            // connection is created
-            if let NodeResponse::User(user_event) = node_response {
-                match user_event {
-                    ProtosResponse::SetCurrentBackbuffer(node) => {
-                        self.user_state.backbuffer_node = Some(node);
+            match node_response {
+                NodeResponse::User(user_event) => {
+                    match user_event {
+                        // Only the top level graph is displayed.
+                        ProtosResponse::SetCurrentBackbuffer(node) if subgraph_stack.is_empty() => {
+                            self.user_state.backbuffer_node = Some(node);
+                            self.mark_modified();
+                        }
+                        ProtosResponse::SetCurrentBackbuffer(_) => self.report_error(anyhow::anyhow!("Only backbuffer passes of the top level graph can be displayed")),
+                        ProtosResponse::ClearCurrentBackbuffer => {
+                            self.user_state.backbuffer_node = None;
+                            self.mark_modified();
+                        }
+                        ProtosResponse::ParameterChanged(_) => self.mark_modified(),
+                        ProtosResponse::OpenSubgraph(node) => self.runtime_state.subgraph_path.push(node),
+                        ProtosResponse::InspectOutput(output_id) => {
+                            let output = match subgraph_stack.last() {
+                                Some(parent) => find_output(&parent.subgraph().lock().unwrap().state.graph, output_id),
+                                None => find_output(&self.state.graph, output_id),
+                            };
+                            if let Some((node_id, name, typ)) = output {
+                                self.inspect((self.user_state.node_path(node_id), name), typ);
+                            }
+                        }
+                        #[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+                        ProtosResponse::ExportBackbuffer(node) if subgraph_stack.is_empty() => {
+                            let kind = ExportKind::Frame;
+                            self.runtime_state.export = Some(Export {
+                                node,
+                                settings: ExportSettings {
+                                    kind,
+                                    size: self.runtime_state.displayed_size,
+                                    time: self.runtime_state.frame.time,
+                                    frames: 60,
+                                    frame_rate: 30,
+                                },
+                                path: kind.default_path().to_string(),
+                                job: None,
+                                result: None,
+                            });
+                        }
+                        #[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+                        ProtosResponse::ExportBackbuffer(_) => self.report_error(anyhow::anyhow!("Only backbuffer passes of the top level graph can be exported")),
+                        #[cfg(feature = "persistence")]
+                        ProtosResponse::SaveSubgraph(node_id) => {
+                            let node = match subgraph_stack.last() {
+                                Some(parent) => SubgraphNode::find(&parent.subgraph().lock().unwrap().state.graph, node_id).cloned(),
+                                None => SubgraphNode::find(&self.state.graph, node_id).cloned(),
+                            };
+                            if let Some(node) = node {
+                                self.runtime_state.library_save = Some(LibrarySave {
+                                    name: node.name().to_string(),
+                                    node,
+                                    error: None,
+                                });
+                            }
+                        }
                     }
-                    ProtosResponse::ClearCurrentBackbuffer => self.user_state.backbuffer_node = None,
                 }
+                // Interactions which do not change the saved graph.
+                NodeResponse::SelectNode(_) | NodeResponse::RaiseNode(_) | NodeResponse::ConnectEventStarted(..) | NodeResponse::DeleteNodeUi(_) => {}
+                _ => self.mark_modified(),
             }
         }
         // Here we must create all resources & cache it & create command buffers...
         // Should have a RUN button.
+        self.user_state.diagnostics.clear();
+        self.user_state.previews.clear();
+        self.update_profiler(device, queue);
         if let Some(node_id) = self.user_state.backbuffer_node {
             if self.state.graph.nodes.contains_key(node_id) {
                 // Evaluate & create nodes
-                let backbuffer_node = match &self.state.graph.nodes[node_id].user_data.template {
-                    ProtosNodeTemplate::BackbufferPass(node_handle) => node_handle,
-                    _ => unreachable!("to backbuffer or not to backbuffer ?")
+                let evaluation_ctx = EvaluationContext {
+                    device,
+                    queue,
+                    frame: self.runtime_state.frame,
+                    target_size: self.runtime_state.displayed_size,
                 };
-                match backbuffer_node.evaluate(device, queue, &self.state.graph, node_id, self.runtime_state.available_size, &mut HashMap::new()) {
-                    Ok(()) => {
-                        // Record node.
-                        match backbuffer_node.record(device, cmd, &self.state.graph, node_id, &mut HashMap::new()) {
-                            Ok(()) => {}
-                            Err(err) => {
-                                ctx.debug_painter().text(
-                                    egui::pos2(10.0, 55.0),
-                                    egui::Align2::LEFT_TOP,
-                                    err.to_string(),
-                                    TextStyle::Button.resolve(&ctx.style()),
-                                    egui::Color32::WHITE,
-                                );
+                let diagnostics = &mut self.user_state.diagnostics;
+                let previews = &mut self.user_state.previews;
+                let mut gpu_profiler = self.runtime_state.profiler.as_mut();
+                let frame_profile = &mut self.runtime_state.frame_profile;
+                let profiled_origins = &mut self.runtime_state.profiled_origins;
+                // Subgraphs are inlined, so that their passes are ordered with the others.
+                match flatten_graph(&self.state.graph) {
+                    Ok(flat_graph) => {
+                        let result = flat_graph.node(node_id).ok_or(anyhow::anyhow!("Backbuffer is not in the flattened graph")).and_then(|node_id| {
+                            let evaluate_start = chrono::Utc::now();
+                            let outputs_cache = evaluate_graph(&evaluation_ctx, &flat_graph.graph, node_id)?;
+                            frame_profile.evaluate = profiler::elapsed_ms(evaluate_start);
+                            previews.collect(&flat_graph, &outputs_cache);
+                            // Record node.
+                            let record_start = chrono::Utc::now();
+                            let mut record_ctx = RecordContext::new(device, cmd, gpu_profiler.as_deref_mut());
+                            record_frame(&mut record_ctx, &flat_graph.graph, node_id, &outputs_cache)?;
+                            frame_profile.record = profiler::elapsed_ms(record_start);
+                            if let Some(gpu_profiler) = record_ctx.profiler {
+                                // Keep the origins of the flattened graph to locate the passes once timings are read back.
+                                if gpu_profiler.end_frame(record_ctx.cmd) {
+                                    *profiled_origins = flat_graph.origins().clone();
+                                }
+                                frame_profile.draws = flat_graph.graph.iter_nodes().filter_map(|node_id| {
+                                    match &flat_graph.graph[node_id].user_data.template {
+                                        ProtosNodeTemplate::GraphicPass(node) => Some((flat_graph.origin(node_id)?.to_vec(), node.get_draw_counters()?)),
+                                        _ => None,
+                                    }
+                                }).collect();
                             }
+                            Ok(node_id)
+                        });
+                        match result {
+                            Ok(node_id) => diagnostics.push_unused(&flat_graph, node_id),
+                            Err(err) => diagnostics.push_error(&flat_graph, &err),
                         }
                     }
-                    Err(err) => {
-                        ctx.debug_painter().text(
-                            egui::pos2(10.0, 35.0),
-                            egui::Align2::LEFT_TOP,
-                            err.to_string(),
-                            TextStyle::Button.resolve(&ctx.style()),
-                            egui::Color32::WHITE,
-                        );
-                    }
+                    Err(err) => diagnostics.push(Vec::new(), Severity::Error, format!("{:#}", err)),
                 }
             } else {
                 self.user_state.backbuffer_node = None;
             }
         }
+        self.update_previews(device, egui_renderer);
+        // Uploaded files are loaded outside of the UI.
+        #[cfg(target_arch = "wasm32")]
+        if crate::web::take_file_loaded() {
+            self.mark_modified();
+        }
+        #[cfg(feature = "persistence")]
+        self.update_history(ctx);
+        #[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+        self.update_recovery();
+        #[cfg(all(feature = "persistence", target_arch = "wasm32"))]
+        self.update_web();
         // TODO: some control window for ui
         /*egui::Window::new("Window").show(ctx, |ui| {
             ui.label("Windows can be moved by dragging them.");
@@ -232,5 +1088,725 @@ impl ProtosApp {
         });*/
     }
 
-    
+    fn update_frame(&mut self) {
+        let now = chrono::Utc::now();
+        match self.runtime_state.start_time {
+            Some(start_time) => {
+                let time = (now - start_time).num_microseconds().unwrap_or(0) as f32 / 1_000_000.0;
+                let frame = &mut self.runtime_state.frame;
+                frame.delta_time = time - frame.time;
+                frame.time = time;
+                frame.index += 1;
+            }
+            None => {
+                self.runtime_state.start_time = Some(now);
+                self.runtime_state.frame = FrameInfo::default();
+            }
+        }
+    }
+
+    fn shortcuts(&mut self, ctx: &egui::Context) {
+        if ctx.input_mut(|input| input.consume_shortcut(&NEW_SHORTCUT)) {
+            self.request_action(ProjectAction::New);
+        }
+        if ctx.input_mut(|input| input.consume_shortcut(&GROUP_SHORTCUT)) {
+            self.group_selection();
+        }
+        // Typing in text edits should not open the finder.
+        if ctx.memory(|memory| memory.focus().is_none()) && ctx.input_mut(|input| input.consume_shortcut(&ADD_NODE_SHORTCUT)) {
+            self.open_node_finder(ctx);
+        }
+        #[cfg(feature = "persistence")]
+        {
+            // Check save as first, as save would match it too.
+            if ctx.input_mut(|input| input.consume_shortcut(&SAVE_AS_SHORTCUT)) {
+                self.save_as();
+            }
+            if ctx.input_mut(|input| input.consume_shortcut(&SAVE_SHORTCUT)) {
+                self.save();
+            }
+            if ctx.input_mut(|input| input.consume_shortcut(&OPEN_SHORTCUT)) {
+                self.open_dialog();
+            }
+            // Focused text edits have their own undo & clipboard.
+            if ctx.memory(|memory| memory.focus().is_none()) {
+                if ctx.input_mut(|input| input.consume_shortcut(&REDO_SHORTCUT)) {
+                    self.redo();
+                }
+                if ctx.input_mut(|input| input.consume_shortcut(&UNDO_SHORTCUT)) {
+                    self.undo();
+                }
+                if ctx.input_mut(|input| input.consume_shortcut(&DUPLICATE_SHORTCUT)) && self.has_selection() {
+                    self.duplicate_selection();
+                }
+                // Copy & paste are received as events, pasted text coming from the system clipboard.
+                let events = ctx.input(|input| input.events.clone());
+                for event in events {
+                    match event {
+                        egui::Event::Copy if self.has_selection() => self.copy_selection(ctx),
+                        egui::Event::Paste(text) if text.starts_with(NODES_CLIPBOARD_HEADER) => {
+                            let position = self.pointer_position(ctx);
+                            self.paste(text.as_str(), position);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    fn edit_menu_ui(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) {
+        #[cfg(feature = "persistence")]
+        {
+            if ui.add_enabled(self.history.can_undo() || self.runtime_state.history_pending, egui::Button::new("Undo").shortcut_text(ctx.format_shortcut(&UNDO_SHORTCUT))).clicked() {
+                self.undo();
+                ui.close_menu();
+            }
+            if ui.add_enabled(self.history.can_redo(), egui::Button::new("Redo").shortcut_text(ctx.format_shortcut(&REDO_SHORTCUT))).clicked() {
+                self.redo();
+                ui.close_menu();
+            }
+            ui.separator();
+            if ui.add_enabled(self.has_selection(), egui::Button::new("Copy").shortcut_text(ctx.format_shortcut(&COPY_SHORTCUT))).clicked() {
+                self.copy_selection(ctx);
+                ui.close_menu();
+            }
+            if ui.add_enabled(self.runtime_state.copied_nodes.is_some(), egui::Button::new("Paste").shortcut_text(ctx.format_shortcut(&PASTE_SHORTCUT))).clicked() {
+                if let Some(text) = self.runtime_state.copied_nodes.clone() {
+                    let position = self.runtime_state.graph_rect.center();
+                    self.paste(text.as_str(), position);
+                }
+                ui.close_menu();
+            }
+            if ui.add_enabled(self.has_selection(), egui::Button::new("Duplicate").shortcut_text(ctx.format_shortcut(&DUPLICATE_SHORTCUT))).clicked() {
+                self.duplicate_selection();
+                ui.close_menu();
+            }
+            ui.separator();
+        }
+        if ui.add(egui::Button::new("Add node...").shortcut_text(ctx.format_shortcut(&ADD_NODE_SHORTCUT))).clicked() {
+            self.open_node_finder(ctx);
+            ui.close_menu();
+        }
+        if ui.add(egui::Button::new("Group selection").shortcut_text(ctx.format_shortcut(&GROUP_SHORTCUT))).clicked() {
+            self.group_selection();
+            ui.close_menu();
+        }
+        #[cfg(feature = "persistence")]
+        {
+            ui.menu_button("Library", |ui| {
+                if self.library.subgraphs().is_empty() {
+                    ui.label("Save subgraphs from their node to reuse them in other projects.");
+                }
+                let mut removed = None;
+                for subgraph in self.library.subgraphs() {
+                    ui.horizontal(|ui| {
+                        ui.label(subgraph.name());
+                        if ui.small_button("🗑").on_hover_text("Remove from library").clicked() {
+                            removed = Some(subgraph.name().to_string());
+                        }
+                    });
+                }
+                if let Some(name) = removed {
+                    self.library.remove(name.as_str());
+                    self.save_library();
+                }
+            });
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("History depth");
+                if ui.add(egui::DragValue::new(&mut self.settings.history_depth).clamp_range(1..=1000)).changed() {
+                    self.history.set_depth(self.settings.history_depth);
+                    self.save_settings();
+                }
+            });
+        }
+    }
+
+    #[cfg(feature = "persistence")]
+    fn library_save_ui(&mut self, ctx: &egui::Context) {
+        let mut open = self.runtime_state.library_save.is_some();
+        let mut saved = None;
+        if let Some(save) = &mut self.runtime_state.library_save {
+            egui::Window::new("Save to library")
+                .open(&mut open)
+                .collapsible(false)
+                .show(ctx, |ui| {
+                    if let Some(error) = &save.error {
+                        ui.colored_label(egui::Color32::RED, error);
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("Name");
+                        ui.text_edit_singleline(&mut save.name);
+                        if ui.add_enabled(!save.name.trim().is_empty(), egui::Button::new("Save")).clicked() {
+                            saved = Some((save.name.trim().to_string(), save.node.clone()));
+                        }
+                    });
+                    if self.library.subgraphs().iter().any(|subgraph| subgraph.name() == save.name.trim()) {
+                        ui.label("A subgraph with this name will be replaced.");
+                    }
+                });
+        }
+        if let Some((name, node)) = saved {
+            match self.library.add(name.as_str(), &node) {
+                Ok(()) => {
+                    self.save_library();
+                    open = false;
+                }
+                Err(err) => {
+                    if let Some(save) = &mut self.runtime_state.library_save {
+                        save.error = Some(format!("{:#}", err));
+                    }
+                }
+            }
+        }
+        if !open {
+            self.runtime_state.library_save = None;
+        }
+    }
+
+    #[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+    fn export_ui(&mut self, ctx: &egui::Context, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let mut open = self.runtime_state.export.is_some();
+        let mut exported = false;
+        let mut cancelled = false;
+        if let Some(export) = &mut self.runtime_state.export {
+            let running = export.job.is_some();
+            egui::Window::new("Export")
+                .open(&mut open)
+                .collapsible(false)
+                .show(ctx, |ui| {
+                    ui.add_enabled_ui(!running, |ui| {
+                        let settings = &mut export.settings;
+                        egui::ComboBox::from_label("Format")
+                            .selected_text(settings.kind.name())
+                            .show_ui(ui, |ui| {
+                                for kind in ExportKind::ALL {
+                                    if ui.selectable_value(&mut settings.kind, kind, kind.name()).changed() {
+                                        export.path = kind.default_path().to_string();
+                                    }
+                                }
+                            });
+                        ui.horizontal(|ui| {
+                            ui.label("Resolution");
+                            ui.add(egui::DragValue::new(&mut settings.size.0).clamp_range(1..=8192));
+                            ui.label("x");
+                            ui.add(egui::DragValue::new(&mut settings.size.1).clamp_range(1..=8192));
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label(if settings.kind.is_animated() { "Start time" } else { "Time" });
+                            ui.add(egui::DragValue::new(&mut settings.time).clamp_range(0.0..=f32::MAX).speed(0.01).suffix(" s"));
+                        });
+                        if settings.kind.is_animated() {
+                            ui.horizontal(|ui| {
+                                ui.label("Frames");
+                                ui.add(egui::DragValue::new(&mut settings.frames).clamp_range(1..=10000));
+                                ui.label("at");
+                                ui.add(egui::DragValue::new(&mut settings.frame_rate).clamp_range(1..=240).suffix(" fps"));
+                            });
+                        }
+                        ui.horizontal(|ui| {
+                            ui.label("File");
+                            ui.text_edit_singleline(&mut export.path);
+                            if ui.button("...").clicked() {
+                                if let Some(path) = rfd::FileDialog::new().set_file_name(export.path.as_str()).save_file() {
+                                    export.path = path.display().to_string();
+                                }
+                            }
+                        });
+                        if settings.kind == ExportKind::Sequence {
+                            ui.weak("Frames are numbered after the file name, frame.png giving frame_0000.png, frame_0001.png...");
+                        }
+                    });
+                    match &export.job {
+                        Some(job) => {
+                            let (rendered, frames) = job.progress();
+                            ui.horizontal(|ui| {
+                                ui.add(egui::ProgressBar::new(rendered as f32 / frames as f32)
+                                    .text(format!("{}/{} frame(s)", rendered, frames))
+                                    .desired_width(200.0));
+                                cancelled = ui.button("Cancel").clicked();
+                            });
+                        }
+                        None => {
+                            if ui.add_enabled(!export.path.trim().is_empty(), egui::Button::new("Export")).clicked() {
+                                exported = true;
+                            }
+                        }
+                    }
+                    match &export.result {
+                        Some(Ok(message)) => { ui.label(message); }
+                        Some(Err(error)) => { ui.colored_label(egui::Color32::RED, error); }
+                        None => {}
+                    }
+                });
+        }
+        if let Some(export) = &mut self.runtime_state.export {
+            if exported {
+                let path = PathBuf::from(export.path.trim());
+                match ExportJob::new(&self.state, &self.user_state, export.node, &export.settings, &path) {
+                    Ok(job) => {
+                        export.job = Some(job);
+                        export.result = None;
+                    }
+                    Err(err) => export.result = Some(Err(format!("{:#}", err))),
+                }
+            }
+            if let Some(mut job) = export.job.take() {
+                if cancelled || !open {
+                    job.cancel();
+                    export.result = Some(Err("Export cancelled".to_string()));
+                } else {
+                    match job.step(device, queue, EXPORT_FRAMES_PER_UPDATE) {
+                        Ok(()) if job.is_finished() => {
+                            export.result = Some(Ok(format!("Exported {} frame(s) to {}", job.progress().1, job.path().display())));
+                        }
+                        Ok(()) => {
+                            export.job = Some(job);
+                            ctx.request_repaint();
+                        }
+                        Err(err) => {
+                            export.result = Some(Err(format!("{:#}", err)));
+                            job.cancel();
+                        }
+                    }
+                }
+            }
+        }
+        if !open {
+            self.runtime_state.export = None;
+        }
+    }
+
+    #[cfg(all(feature = "trace", not(target_arch = "wasm32")))]
+    fn trace_capture_ui(&mut self, ctx: &egui::Context) {
+        let mut open = self.runtime_state.trace_capture.is_some();
+        let mut captured = None;
+        if let Some(capture) = &mut self.runtime_state.trace_capture {
+            egui::Window::new("Capture trace")
+                .open(&mut open)
+                .collapsible(false)
+                .show(ctx, |ui| {
+                    ui.label("Record the wgpu calls rendering the active backbuffer, along with the project.");
+                    ui.horizontal(|ui| {
+                        ui.label("Directory");
+                        ui.text_edit_singleline(&mut capture.directory);
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Frames");
+                        ui.add(egui::DragValue::new(&mut capture.frames).clamp_range(1..=600));
+                    });
+                    if ui.add_enabled(!capture.directory.trim().is_empty(), egui::Button::new("Capture")).clicked() {
+                        captured = Some((PathBuf::from(capture.directory.trim()), capture.frames));
+                    }
+                    match &capture.result {
+                        Some(Ok(message)) => { ui.label(message); }
+                        Some(Err(error)) => { ui.colored_label(egui::Color32::RED, error); }
+                        None => {}
+                    }
+                });
+        }
+        if let Some((directory, frames)) = captured {
+            let result = crate::trace::capture(&directory, &self.state, &self.user_state, self.runtime_state.displayed_size, self.runtime_state.frame.time, frames)
+                .map(|info| format!("Captured {} frame(s) on {} into {}, render it again with protos-cli --from-capture {}", info.frames, info.adapter, directory.display(), directory.display()))
+                .map_err(|err| format!("{:#}", err));
+            if let Some(capture) = &mut self.runtime_state.trace_capture {
+                capture.result = Some(result);
+            }
+        }
+        if !open {
+            self.runtime_state.trace_capture = None;
+        }
+    }
+
+    #[cfg(feature = "persistence")]
+    fn project_menu_ui(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) {
+        if ui.add(egui::Button::new("Open...").shortcut_text(ctx.format_shortcut(&OPEN_SHORTCUT))).clicked() {
+            ui.close_menu();
+            self.open_dialog();
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        ui.add_enabled_ui(!self.settings.recent_projects.is_empty(), |ui| {
+            ui.menu_button("Open Recent", |ui| {
+                let mut selected = None;
+                for path in &self.settings.recent_projects {
+                    if ui.button(path.display().to_string()).clicked() {
+                        selected = Some(path.clone());
+                    }
+                }
+                if let Some(path) = selected {
+                    self.request_action(ProjectAction::Open(path));
+                    ui.close_menu();
+                }
+            });
+        });
+        ui.separator();
+        if ui.add(egui::Button::new("Save").shortcut_text(ctx.format_shortcut(&SAVE_SHORTCUT))).clicked() {
+            ui.close_menu();
+            self.save();
+        }
+        let save_as_label = if cfg!(target_arch = "wasm32") { "Download..." } else { "Save As..." };
+        if ui.add(egui::Button::new(save_as_label).shortcut_text(ctx.format_shortcut(&SAVE_AS_SHORTCUT))).clicked() {
+            ui.close_menu();
+            self.save_as();
+        }
+        ui.separator();
+        let share_hint = if cfg!(target_arch = "wasm32") { "Put the link in the page address" } else { "Copy a link opening the project on the web version" };
+        if ui.button("Copy share link").on_hover_text(share_hint).clicked() {
+            ui.close_menu();
+            self.copy_share_link(ctx);
+        }
+        if ui.button("Open from link...").clicked() {
+            ui.close_menu();
+            self.runtime_state.link_import = Some(LinkImport::default());
+        }
+    }
+
+    #[cfg(feature = "persistence")]
+    fn unsaved_changes_ui(&mut self, ctx: &egui::Context) {
+        if self.runtime_state.pending_action.is_none() {
+            return;
+        }
+        let mut choice = None;
+        egui::Window::new("Unsaved changes")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label(format!("Save changes to {} before closing it ?", self.project_name()));
+                ui.horizontal(|ui| {
+                    if ui.button("Save").clicked() {
+                        choice = Some(true);
+                    }
+                    if ui.button("Don't save").clicked() {
+                        choice = Some(false);
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.runtime_state.pending_action = None;
+                    }
+                });
+            });
+        // Keep the action pending if saving failed or was cancelled.
+        let proceed = match choice {
+            Some(save) => !save || self.save(),
+            None => false,
+        };
+        if proceed {
+            if let Some(action) = self.runtime_state.pending_action.take() {
+                self.perform_action(action);
+            }
+        }
+    }
+
+    #[cfg(all(feature = "persistence", not(target_arch = "wasm32")))]
+    fn recovery_ui(&mut self, ctx: &egui::Context) {
+        let mut restore = None;
+        if let Some(recovered) = self.runtime_state.recovered.first() {
+            let name = match &recovered.project_path {
+                Some(path) => path.display().to_string(),
+                None => String::from("an untitled project"),
+            };
+            egui::Window::new("Recover unsaved work")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+                .show(ctx, |ui| {
+                    ui.label(format!("Protos did not exit properly. Restore the unsaved changes of {} ?", name));
+                    ui.horizontal(|ui| {
+                        if ui.button("Restore").clicked() {
+                            restore = Some(true);
+                        }
+                        if ui.button("Discard").clicked() {
+                            restore = Some(false);
+                        }
+                    });
+                });
+        }
+        if let Some(restore) = restore {
+            let recovered = self.runtime_state.recovered.remove(0);
+            // Once restored, the project is snapshotted by this session.
+            recovered.discard();
+            if restore {
+                self.set_project(recovered.project.state, recovered.project.user_state);
+                self.metadata = recovered.project.metadata;
+                self.project_path = recovered.project_path;
+                self.set_unsaved();
+            }
+        }
+    }
+
+    // Open the graph containing the node, select it & move the view on it.
+    fn focus_node(&mut self, path: &[NodeId]) {
+        if let Some((node_id, subgraph_path)) = path.split_last() {
+            self.runtime_state.subgraph_path = subgraph_path.to_vec();
+            let graph_size = self.runtime_state.graph_rect.size();
+            self.with_edited_state(|state, _| {
+                if let Some(position) = state.node_positions.get(*node_id).copied() {
+                    state.pan_zoom.pan = graph_size / 2.0 - position.to_vec2();
+                    state.selected_nodes = vec![*node_id];
+                }
+            });
+        }
+    }
+
+    // Register the textures previewed on nodes to egui, & free the ones which are not anymore.
+    fn update_previews(&mut self, device: &wgpu::Device, egui_renderer: &mut egui_wgpu::Renderer) {
+        let previews = &mut self.user_state.previews;
+        for texture_id in previews.take_unused_texture_ids() {
+            egui_renderer.free_texture(&texture_id);
+        }
+        let textures: Vec<(PreviewKey, gfx::ResourceHandle<gfx::Texture>)> = previews.textures()
+            .filter(|(key, _)| previews.is_enabled(&key.0))
+            .map(|(key, texture)| (key.clone(), texture.clone()))
+            .collect();
+        for (key, texture) in textures {
+            let texture = texture.lock().unwrap();
+            let view = match texture.get_view_handle() {
+                Ok(view) => view,
+                Err(_) => continue,
+            };
+            match previews.texture_id(&key) {
+                Some(texture_id) => egui_renderer.update_egui_texture_from_wgpu_texture(device, view, wgpu::FilterMode::Linear, texture_id),
+                None => {
+                    let texture_id = egui_renderer.register_native_texture(device, view, wgpu::FilterMode::Linear);
+                    previews.set_texture_id(key, texture_id);
+                }
+            }
+        }
+    }
+
+    // Open the output in the inspector of its type, replacing the output inspected before.
+    fn inspect(&mut self, key: PreviewKey, typ: ProtosDataType) {
+        let previews = &mut self.user_state.previews;
+        match typ {
+            ProtosDataType::Texture => {
+                if let Some(inspector) = self.runtime_state.texture_inspector.replace(TextureInspector::new(key.clone())) {
+                    previews.set_inspected(inspector.key().clone(), false);
+                }
+            }
+            ProtosDataType::Buffer => {
+                if let Some(inspector) = self.runtime_state.buffer_inspector.replace(BufferInspector::new(key.clone())) {
+                    previews.set_inspected(inspector.key().clone(), false);
+                }
+            }
+            _ => return,
+        }
+        previews.set_inspected(key, true);
+    }
+
+    fn texture_inspector_ui(&mut self, ctx: &egui::Context, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let inspector = match &mut self.runtime_state.texture_inspector {
+            Some(inspector) => inspector,
+            None => return,
+        };
+        let (path, output) = inspector.key();
+        let title = format!("{} > {}", node_labels(&self.state.graph, path).join(" > "), output);
+        let texture = self.user_state.previews.texture(inspector.key()).cloned();
+        let mut open = true;
+        inspector.ui(ctx, device, queue, &title, texture.as_ref(), &mut open);
+        if !open {
+            self.user_state.previews.set_inspected(inspector.key().clone(), false);
+            self.runtime_state.texture_inspector = None;
+        }
+    }
+
+    fn buffer_inspector_ui(&mut self, ctx: &egui::Context, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let inspector = match &mut self.runtime_state.buffer_inspector {
+            Some(inspector) => inspector,
+            None => return,
+        };
+        let (path, output) = inspector.key();
+        let title = format!("{} > {}", node_labels(&self.state.graph, path).join(" > "), output);
+        let buffer = self.user_state.previews.buffer(inspector.key()).cloned();
+        let mut open = true;
+        inspector.ui(ctx, device, queue, &title, buffer.as_ref(), &mut open);
+        if !open {
+            self.user_state.previews.set_inspected(inspector.key().clone(), false);
+            self.runtime_state.buffer_inspector = None;
+        }
+    }
+
+    // Start timing a new frame, & gather the GPU timings of a previous one once read back.
+    fn update_profiler(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if !self.runtime_state.show_profiler {
+            self.runtime_state.profiler = None;
+            self.user_state.gpu_times.clear();
+            self.user_state.gpu_statistics.clear();
+            return;
+        }
+        let gpu_profiler = self.runtime_state.profiler.get_or_insert_with(|| gfx::GpuProfiler::new(device, queue));
+        let has_timestamps = gpu_profiler.has_timestamps();
+        if let Some(profiles) = gpu_profiler.begin_frame(device) {
+            let origins = &self.runtime_state.profiled_origins;
+            let passes: Vec<gfx::PassProfile<Vec<NodeId>>> = profiles.into_iter()
+                .filter_map(|profile| origins.get(&profile.key).map(|path| gfx::PassProfile {
+                    key: path.clone(),
+                    start: profile.start,
+                    end: profile.end,
+                    statistics: profile.statistics,
+                }))
+                .collect();
+            self.user_state.gpu_times.clear();
+            self.user_state.gpu_statistics.clear();
+            for pass in &passes {
+                if has_timestamps {
+                    *self.user_state.gpu_times.entry(pass.key.clone()).or_default() += pass.duration();
+                }
+                if let Some(statistics) = pass.statistics {
+                    *self.user_state.gpu_statistics.entry(pass.key.clone()).or_default() += statistics;
+                }
+            }
+            self.runtime_state.frame_profile.passes = passes;
+        }
+    }
+
+    fn profiler_ui(&mut self, ctx: &egui::Context) {
+        if !self.runtime_state.show_profiler {
+            return;
+        }
+        let has_timestamps = self.runtime_state.profiler.as_ref().map(gfx::GpuProfiler::has_timestamps).unwrap_or(true);
+        let has_statistics = self.runtime_state.profiler.as_ref().map(gfx::GpuProfiler::has_statistics).unwrap_or(true);
+        let mut focused = None;
+        egui::TopBottomPanel::bottom("ProfilerPanel").show(ctx, |ui| {
+            egui::CollapsingHeader::new("Profiler")
+                .default_open(true)
+                .show(ui, |ui| {
+                    if ui.button("Copy as CSV").on_hover_text("Copy the passes of the frame to the clipboard").clicked() {
+                        let csv = profiler::to_csv(&self.runtime_state.frame_profile, &self.state.graph);
+                        ui.output_mut(|output| output.copied_text = csv);
+                    }
+                    egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                        focused = profiler::timeline_ui(ui, &self.runtime_state.frame_profile, &self.state.graph, has_timestamps, has_statistics);
+                    });
+                });
+        });
+        if let Some(path) = focused {
+            self.focus_node(&path);
+        }
+    }
+
+    fn problems_ui(&mut self, ctx: &egui::Context) {
+        let diagnostics: Vec<Diagnostic> = self.user_state.diagnostics.iter().cloned().collect();
+        let errors = diagnostics.iter().filter(|diagnostic| diagnostic.severity == Severity::Error).count();
+        let warnings = diagnostics.len() - errors;
+        let mut focused = None;
+        egui::TopBottomPanel::bottom("ProblemsPanel").show(ctx, |ui| {
+            egui::CollapsingHeader::new(format!("Problems ({} errors, {} warnings)", errors, warnings))
+                .id_source("Problems")
+                .show(ui, |ui| {
+                    if diagnostics.is_empty() {
+                        ui.label("No problem found.");
+                    }
+                    egui::ScrollArea::vertical().max_height(150.0).show(ui, |ui| {
+                        for diagnostic in &diagnostics {
+                            ui.horizontal(|ui| {
+                                match diagnostic.severity {
+                                    Severity::Warning => ui.colored_label(egui::Color32::GOLD, "⚠"),
+                                    Severity::Error => ui.colored_label(egui::Color32::RED, "⛔"),
+                                };
+                                if !diagnostic.path.is_empty() {
+                                    let location = node_labels(&self.state.graph, &diagnostic.path).join(" > ");
+                                    if ui.link(location).on_hover_text("Show the node").clicked() {
+                                        focused = Some(diagnostic.path.clone());
+                                    }
+                                }
+                                ui.label(diagnostic.message.as_str());
+                            });
+                        }
+                    });
+                });
+        });
+        if let Some(path) = focused {
+            self.focus_node(&path);
+        }
+    }
+
+    fn error_ui(&mut self, ctx: &egui::Context) {
+        let mut open = self.runtime_state.error.is_some();
+        let mut dismissed = false;
+        if let Some(error) = &self.runtime_state.error {
+            egui::Window::new("Error")
+                .open(&mut open)
+                .collapsible(false)
+                .show(ctx, |ui| {
+                    ui.colored_label(egui::Color32::RED, error);
+                    dismissed = ui.button("Ok").clicked();
+                });
+        }
+        if !open || dismissed {
+            self.runtime_state.error = None;
+        }
+    }
+
+    #[cfg(feature = "persistence")]
+    fn link_import_ui(&mut self, ctx: &egui::Context) {
+        let mut open = self.runtime_state.link_import.is_some();
+        let mut imported = None;
+        if let Some(import) = &mut self.runtime_state.link_import {
+            egui::Window::new("Open from link")
+                .open(&mut open)
+                .collapsible(false)
+                .show(ctx, |ui| {
+                    ui.label("Paste a shared link.");
+                    if let Some(error) = &import.error {
+                        ui.colored_label(egui::Color32::RED, error);
+                    }
+                    ui.horizontal(|ui| {
+                        ui.text_edit_singleline(&mut import.link);
+                        if ui.button("Open").clicked() {
+                            let project = share::parse_link(import.link.as_str()).and_then(|json| Project::from_json(json.as_str()));
+                            match project {
+                                Ok(project) => imported = Some((project.state, project.user_state)),
+                                Err(err) => import.error = Some(format!("{:#}", err)),
+                            }
+                        }
+                    });
+                });
+        }
+        if let Some(project) = imported {
+            self.request_action(ProjectAction::Import(Box::new(project)));
+            open = false;
+        }
+        if !open {
+            self.runtime_state.link_import = None;
+        }
+    }
+
+    fn shadertoy_import_ui(&mut self, ctx: &egui::Context) {
+        let mut open = self.runtime_state.shadertoy_import.is_some();
+        let mut imported = None;
+        if let Some(import) = &mut self.runtime_state.shadertoy_import {
+            egui::Window::new("Import Shadertoy")
+                .open(&mut open)
+                .default_size([500.0, 400.0])
+                .show(ctx, |ui| {
+                    ui.label("Paste a mainImage function or a shadertoy JSON export.");
+                    if let Some(error) = &import.error {
+                        ui.colored_label(egui::Color32::RED, error);
+                    }
+                    if ui.button("Import").clicked() {
+                        match import_shadertoy(import.source.as_str()) {
+                            Ok(result) => imported = Some(result),
+                            Err(err) => import.error = Some(err.to_string()),
+                        }
+                    }
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        ui.add(
+                            egui::TextEdit::multiline(&mut import.source)
+                                .font(egui::TextStyle::Monospace)
+                                .code_editor()
+                                .desired_rows(20)
+                                .desired_width(f32::INFINITY),
+                        );
+                    });
+                });
+        }
+        if let Some(project) = imported {
+            self.request_action(ProjectAction::Import(Box::new(project)));
+            open = false;
+        }
+        if !open {
+            self.runtime_state.shadertoy_import = None;
+        }
+    }
 }
\ No newline at end of file
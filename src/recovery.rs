@@ -0,0 +1,215 @@
+use std::{path::{Path, PathBuf}, sync::{Arc, Mutex}, time::{Duration, Instant, SystemTime}};
+
+use anyhow::Context;
+
+use crate::project::Project;
+
+// Snapshots are cheap & kept in memory for the panic hook, they are written to disk less often.
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(1);
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(30);
+// Sessions not updated for longer than the stale delay belong to processes which did not exit cleanly.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+const STALE_DELAY: Duration = Duration::from_secs(60);
+
+// Recovery file & latest snapshot of the project, flushed by the panic hook.
+static SNAPSHOT: Mutex<Option<(PathBuf, String)>> = Mutex::new(None);
+
+// Written by each running process, several instances can run at the same time.
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+struct Session {
+    project_path: Option<PathBuf>,
+    heartbeat: u64, // Seconds since epoch.
+}
+
+// Shared with the heartbeat thread.
+struct SessionFile {
+    path: PathBuf,
+    session: Session,
+    ended: bool,
+}
+
+pub struct RecoveredProject {
+    pub project: Project,
+    pub project_path: Option<PathBuf>,
+    files: Vec<PathBuf>,
+}
+
+pub struct Recovery {
+    recovery_path: PathBuf,
+    session: Arc<Mutex<SessionFile>>,
+    snapshot: Option<String>,
+    last_snapshot: Instant,
+    last_autosave: Instant,
+    dirty: bool, // Snapshot not written to disk yet.
+}
+
+fn directory() -> anyhow::Result<PathBuf> {
+    dirs::data_local_dir()
+        .map(|dir| dir.join("protos-rs").join("sessions"))
+        .ok_or(anyhow::anyhow!("No local data directory available"))
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0)
+}
+
+impl SessionFile {
+    fn write(&mut self) -> anyhow::Result<()> {
+        self.session.heartbeat = now_secs();
+        std::fs::write(&self.path, serde_json::to_string(&self.session)?).with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+}
+
+impl RecoveredProject {
+    // Remove the files left by the crashed session, once restored or discarded.
+    pub fn discard(&self) {
+        for file in &self.files {
+            if let Err(err) = std::fs::remove_file(file) {
+                log::warn!("Failed to remove {} : {}", file.display(), err);
+            }
+        }
+    }
+}
+
+// Write the latest snapshot before the default hook runs, so that unsaved work can be restored on next start.
+pub fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        // Do not wait on the lock, the panic might come from a thread holding it.
+        if let Ok(snapshot) = SNAPSHOT.try_lock() {
+            if let Some((path, snapshot)) = snapshot.as_ref() {
+                let _ = std::fs::write(path, snapshot);
+            }
+        }
+        default_hook(info);
+    }));
+}
+
+impl Recovery {
+    // Start a session, returning the projects left by other sessions which did not exit cleanly.
+    pub fn start() -> anyhow::Result<(Self, Vec<RecoveredProject>)> {
+        let directory = directory()?;
+        std::fs::create_dir_all(&directory).with_context(|| format!("Failed to create {}", directory.display()))?;
+        let recovered = Self::find_crashed(&directory)?;
+        // Process ids can be reused, the start time makes the session unique.
+        let id = format!("{}-{}", std::process::id(), now_secs());
+        let mut session = SessionFile {
+            path: directory.join(format!("{}.json", id)),
+            session: Session::default(),
+            ended: false,
+        };
+        session.write()?;
+        let session = Arc::new(Mutex::new(session));
+        // Keep the session alive even when the window is not redrawn.
+        let heartbeat = session.clone();
+        std::thread::spawn(move || loop {
+            std::thread::sleep(HEARTBEAT_INTERVAL);
+            let mut session = heartbeat.lock().unwrap();
+            if session.ended {
+                break;
+            }
+            if let Err(err) = session.write() {
+                log::warn!("{:#}", err);
+            }
+        });
+        let now = Instant::now();
+        let recovery = Self {
+            recovery_path: directory.join(format!("{}.protos", id)),
+            session,
+            snapshot: None,
+            last_snapshot: now,
+            last_autosave: now,
+            dirty: false,
+        };
+        Ok((recovery, recovered))
+    }
+    fn find_crashed(directory: &Path) -> anyhow::Result<Vec<RecoveredProject>> {
+        let mut recovered = Vec::new();
+        let now = now_secs();
+        for entry in std::fs::read_dir(directory)? {
+            let session_path = entry?.path();
+            if session_path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            // Might be written by a running session, check it again on next start.
+            let session: Session = match std::fs::read_to_string(&session_path).ok().and_then(|json| serde_json::from_str(json.as_str()).ok()) {
+                Some(session) => session,
+                None => continue,
+            };
+            if now.saturating_sub(session.heartbeat) < STALE_DELAY.as_secs() {
+                continue;
+            }
+            let recovery_path = session_path.with_extension("protos");
+            let files = vec![session_path, recovery_path.clone()];
+            if !recovery_path.exists() {
+                // Nothing was modified in the crashed session.
+                let _ = std::fs::remove_file(&files[0]);
+                continue;
+            }
+            match Project::load(&recovery_path) {
+                Ok(project) => recovered.push(RecoveredProject {
+                    project,
+                    project_path: session.project_path,
+                    files,
+                }),
+                Err(err) => log::warn!("Failed to recover {} : {:#}", recovery_path.display(), err),
+            }
+        }
+        Ok(recovered)
+    }
+    // Called every frame, serialize is only called when a snapshot is due.
+    pub fn update(&mut self, project_path: Option<&Path>, modified: bool, serialize: impl FnOnce() -> anyhow::Result<String>) {
+        {
+            let mut session = self.session.lock().unwrap();
+            if session.session.project_path.as_deref() != project_path {
+                session.session.project_path = project_path.map(Path::to_path_buf);
+                if let Err(err) = session.write() {
+                    log::warn!("{:#}", err);
+                }
+            }
+        }
+        if !modified {
+            // Saved or replaced, nothing to recover anymore.
+            if self.snapshot.is_some() {
+                self.clear();
+            }
+            return;
+        }
+        let now = Instant::now();
+        if now - self.last_snapshot >= SNAPSHOT_INTERVAL {
+            self.last_snapshot = now;
+            match serialize() {
+                Ok(snapshot) => {
+                    if self.snapshot.as_ref() != Some(&snapshot) {
+                        *SNAPSHOT.lock().unwrap() = Some((self.recovery_path.clone(), snapshot.clone()));
+                        self.snapshot = Some(snapshot);
+                        self.dirty = true;
+                    }
+                }
+                Err(err) => log::warn!("Failed to snapshot project : {:#}", err),
+            }
+        }
+        if self.dirty && now - self.last_autosave >= AUTOSAVE_INTERVAL {
+            self.last_autosave = now;
+            self.dirty = false;
+            if let Some(snapshot) = &self.snapshot {
+                if let Err(err) = std::fs::write(&self.recovery_path, snapshot) {
+                    log::warn!("Failed to autosave {} : {}", self.recovery_path.display(), err);
+                }
+            }
+        }
+    }
+    fn clear(&mut self) {
+        self.snapshot = None;
+        self.dirty = false;
+        *SNAPSHOT.lock().unwrap() = None;
+        let _ = std::fs::remove_file(&self.recovery_path);
+    }
+    // Clean exit, nothing to recover on next start.
+    pub fn end(mut self) {
+        self.clear();
+        let mut session = self.session.lock().unwrap();
+        session.ended = true;
+        let _ = std::fs::remove_file(&session.path);
+    }
+}
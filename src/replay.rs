@@ -0,0 +1,235 @@
+use std::{borrow::Cow, fs, path::Path};
+
+use anyhow::Context;
+use wgpu::core as wgc;
+use wgpu_types as wgt; // Named by gfx_select.
+use wgc::{
+    device::trace::{self, Action, Command},
+    hal_api::HalApi,
+    id::{AdapterId, CommandBufferId, DeviceId, TypedId},
+    identity::{GlobalIdentityHandlerFactory, IdentityHandler, IdentityHandlerFactory, IdentityManager},
+};
+
+// What a replay ran on.
+pub struct ReplayInfo {
+    pub actions: usize,
+    pub adapter: String,
+}
+
+// Resources are created with the ids of the trace, which its actions refer to.
+#[derive(Debug)]
+struct TraceIds;
+
+impl<I: TypedId + Clone + std::fmt::Debug> IdentityHandler<I> for TraceIds {
+    type Input = I;
+    fn process(&self, id: I, backend: wgt::Backend) -> I {
+        let (index, epoch, _) = id.unzip();
+        I::zip(index, epoch, backend)
+    }
+    fn free(&self, _id: I) {}
+}
+
+impl<I: TypedId + Clone + std::fmt::Debug> IdentityHandlerFactory<I> for TraceIds {
+    type Filter = Self;
+    fn spawn(&self) -> Self {
+        Self
+    }
+}
+
+impl GlobalIdentityHandlerFactory for TraceIds {
+    fn ids_are_generated_in_wgpu() -> bool {
+        false
+    }
+}
+
+// wgpu-core errors are reported by their message, some of them can't be sent across threads.
+fn check<E: std::fmt::Display>(error: Option<E>) -> anyhow::Result<()> {
+    match error {
+        Some(err) => anyhow::bail!("{}", err),
+        None => Ok(()),
+    }
+}
+
+fn checked<T, E: std::fmt::Display>(result: Result<T, E>) -> anyhow::Result<T> {
+    result.map_err(|err| anyhow::anyhow!("{}", err))
+}
+
+// Plays the actions of a trace on a device, like the player of the wgpu repository but without window.
+struct Player<'a> {
+    global: wgc::global::Global<TraceIds>,
+    directory: &'a Path, // Holds the data files the trace refers to.
+    encoders: IdentityManager, // Command encoders are not traced, only their commands.
+}
+
+impl Player<'_> {
+    fn play<A: HalApi>(&mut self, device: DeviceId, action: Action<'_>) -> anyhow::Result<()> {
+        let global = &self.global;
+        match action {
+            Action::Init { .. } => anyhow::bail!("The device is created twice"),
+            Action::ConfigureSurface(..) | Action::GetSurfaceTexture { .. } | Action::Present(_) | Action::DiscardSurfaceTexture(_) => {
+                anyhow::bail!("Surfaces can't be replayed headlessly")
+            }
+            Action::CreateBuffer(id, desc) => check(global.device_create_buffer::<A>(device, &desc, id).1)?,
+            Action::FreeBuffer(id) => checked(global.buffer_destroy::<A>(id))?,
+            Action::DestroyBuffer(id) => global.buffer_drop::<A>(id, true),
+            Action::CreateTexture(id, desc) => check(global.device_create_texture::<A>(device, &desc, id).1)?,
+            Action::FreeTexture(id) => checked(global.texture_destroy::<A>(id))?,
+            Action::DestroyTexture(id) => global.texture_drop::<A>(id, true),
+            Action::CreateTextureView { id, parent_id, desc } => check(global.texture_create_view::<A>(parent_id, &desc, id).1)?,
+            Action::DestroyTextureView(id) => checked(global.texture_view_drop::<A>(id, true))?,
+            Action::CreateSampler(id, desc) => check(global.device_create_sampler::<A>(device, &desc, id).1)?,
+            Action::DestroySampler(id) => global.sampler_drop::<A>(id),
+            Action::CreateBindGroupLayout(id, desc) => check(global.device_create_bind_group_layout::<A>(device, &desc, id).1)?,
+            Action::DestroyBindGroupLayout(id) => global.bind_group_layout_drop::<A>(id),
+            Action::CreatePipelineLayout(id, desc) => check(global.device_create_pipeline_layout::<A>(device, &desc, id).1)?,
+            Action::DestroyPipelineLayout(id) => global.pipeline_layout_drop::<A>(id),
+            Action::CreateBindGroup(id, desc) => check(global.device_create_bind_group::<A>(device, &desc, id).1)?,
+            Action::DestroyBindGroup(id) => global.bind_group_drop::<A>(id),
+            Action::CreateShaderModule { id, desc, data } => {
+                let path = self.directory.join(&data);
+                let code = fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+                // Modules are traced as WGSL source, or as a naga module when created from another language.
+                let source = if data.ends_with(".wgsl") {
+                    wgc::pipeline::ShaderModuleSource::Wgsl(Cow::Owned(code))
+                } else {
+                    let module = ron::de::from_str(code.as_str()).with_context(|| format!("Failed to parse {}", path.display()))?;
+                    wgc::pipeline::ShaderModuleSource::Naga(Cow::Owned(module))
+                };
+                check(global.device_create_shader_module::<A>(device, &desc, source, id).1)?
+            }
+            Action::DestroyShaderModule(id) => global.shader_module_drop::<A>(id),
+            Action::CreateComputePipeline { id, desc, implicit_context } => {
+                let implicit_ids = implicit_context.as_ref().map(|context| wgc::device::ImplicitPipelineIds {
+                    root_id: context.root_id,
+                    group_ids: &context.group_ids[..],
+                });
+                check(global.device_create_compute_pipeline::<A>(device, &desc, id, implicit_ids).1)?
+            }
+            Action::DestroyComputePipeline(id) => global.compute_pipeline_drop::<A>(id),
+            Action::CreateRenderPipeline { id, desc, implicit_context } => {
+                let implicit_ids = implicit_context.as_ref().map(|context| wgc::device::ImplicitPipelineIds {
+                    root_id: context.root_id,
+                    group_ids: &context.group_ids[..],
+                });
+                check(global.device_create_render_pipeline::<A>(device, &desc, id, implicit_ids).1)?
+            }
+            Action::DestroyRenderPipeline(id) => global.render_pipeline_drop::<A>(id),
+            Action::CreateRenderBundle { id, desc, base } => {
+                let encoder = checked(wgc::command::RenderBundleEncoder::new(&desc, device, Some(base)))?;
+                let desc = wgc::command::RenderBundleDescriptor { label: desc.label };
+                check(global.render_bundle_encoder_finish::<A>(encoder, &desc, id).1)?
+            }
+            Action::DestroyRenderBundle(id) => global.render_bundle_drop::<A>(id),
+            Action::CreateQuerySet { id, desc } => check(global.device_create_query_set::<A>(device, &desc, id).1)?,
+            Action::DestroyQuerySet(id) => global.query_set_drop::<A>(id),
+            Action::WriteBuffer { id, data, range, queued } => {
+                let bytes = self.read_data(data.as_str())?;
+                let size = (range.end - range.start) as usize;
+                if queued {
+                    checked(global.queue_write_buffer::<A>(device, id, range.start, &bytes))?;
+                } else {
+                    // Written while mapped.
+                    checked(global.device_wait_for_buffer::<A>(device, id))?;
+                    checked(global.device_set_buffer_sub_data::<A>(device, id, range.start, &bytes[..size]))?;
+                }
+            }
+            Action::WriteTexture { to, data, layout, size } => {
+                let bytes = self.read_data(data.as_str())?;
+                checked(global.queue_write_texture::<A>(device, &to, &bytes, &layout, &size))?;
+            }
+            Action::Submit(_, commands) => {
+                let command_buffer = self.encode::<A>(device, commands)?;
+                checked(self.global.queue_submit::<A>(device, &[command_buffer]))?;
+            }
+        }
+        Ok(())
+    }
+    fn encode<A: HalApi>(&mut self, device: DeviceId, commands: Vec<Command>) -> anyhow::Result<CommandBufferId> {
+        let global = &self.global;
+        let encoder = self.encoders.alloc(device.backend());
+        let (encoder, error) = global.device_create_command_encoder::<A>(device, &wgt::CommandEncoderDescriptor { label: None }, encoder);
+        check(error)?;
+        for command in commands {
+            match command {
+                Command::CopyBufferToBuffer { src, src_offset, dst, dst_offset, size } => {
+                    checked(global.command_encoder_copy_buffer_to_buffer::<A>(encoder, src, src_offset, dst, dst_offset, size))?
+                }
+                Command::CopyBufferToTexture { src, dst, size } => checked(global.command_encoder_copy_buffer_to_texture::<A>(encoder, &src, &dst, &size))?,
+                Command::CopyTextureToBuffer { src, dst, size } => checked(global.command_encoder_copy_texture_to_buffer::<A>(encoder, &src, &dst, &size))?,
+                Command::CopyTextureToTexture { src, dst, size } => checked(global.command_encoder_copy_texture_to_texture::<A>(encoder, &src, &dst, &size))?,
+                Command::ClearBuffer { dst, offset, size } => checked(global.command_encoder_clear_buffer::<A>(encoder, dst, offset, size))?,
+                Command::ClearTexture { dst, subresource_range } => checked(global.command_encoder_clear_texture::<A>(encoder, dst, &subresource_range))?,
+                Command::WriteTimestamp { query_set_id, query_index } => checked(global.command_encoder_write_timestamp::<A>(encoder, query_set_id, query_index))?,
+                Command::ResolveQuerySet { query_set_id, start_query, query_count, destination, destination_offset } => {
+                    checked(global.command_encoder_resolve_query_set::<A>(encoder, query_set_id, start_query, query_count, destination, destination_offset))?
+                }
+                Command::PushDebugGroup(label) => checked(global.command_encoder_push_debug_group::<A>(encoder, label.as_str()))?,
+                Command::PopDebugGroup => checked(global.command_encoder_pop_debug_group::<A>(encoder))?,
+                Command::InsertDebugMarker(label) => checked(global.command_encoder_insert_debug_marker::<A>(encoder, label.as_str()))?,
+                Command::RunComputePass { base, timestamp_writes } => {
+                    checked(global.command_encoder_run_compute_pass_impl::<A>(encoder, base.as_ref(), timestamp_writes.as_ref()))?
+                }
+                Command::RunRenderPass { base, target_colors, target_depth_stencil, timestamp_writes, occlusion_query_set_id } => {
+                    checked(global.command_encoder_run_render_pass_impl::<A>(
+                        encoder,
+                        base.as_ref(),
+                        &target_colors,
+                        target_depth_stencil.as_ref(),
+                        timestamp_writes.as_ref(),
+                        occlusion_query_set_id,
+                    ))?
+                }
+            }
+        }
+        let (command_buffer, error) = global.command_encoder_finish::<A>(encoder, &wgt::CommandBufferDescriptor { label: None });
+        check(error)?;
+        Ok(command_buffer)
+    }
+    fn read_data(&self, name: &str) -> anyhow::Result<Vec<u8>> {
+        let path = self.directory.join(name);
+        fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))
+    }
+    fn wait<A: HalApi>(&self, device: DeviceId) -> anyhow::Result<()> {
+        checked(self.global.device_poll::<A>(device, wgt::Maintain::Wait)).map(|_| ())
+    }
+}
+
+// Play the wgpu trace recorded in directory back on a new device of the backend it was recorded with.
+// Stops at the first action failing, which is usually what the trace was recorded for.
+pub fn replay(directory: &Path) -> anyhow::Result<ReplayInfo> {
+    let path = directory.join(trace::FILE_NAME);
+    let ron = fs::read_to_string(&path).with_context(|| format!("Failed to read {}, {} is not a trace", path.display(), directory.display()))?;
+    // The list is closed once the traced device is dropped, a trace missing its end was interrupted.
+    let actions: Vec<Action<'_>> = ron::de::from_str(ron.as_str()).with_context(|| format!("Failed to parse {}", path.display()))?;
+    let mut actions = actions.into_iter();
+    let (desc, backend) = match actions.next() {
+        Some(Action::Init { desc, backend }) => (desc, backend),
+        _ => anyhow::bail!("{} does not start by creating its device", path.display()),
+    };
+    let global = wgc::global::Global::new("protos-replay", TraceIds, wgt::InstanceDescriptor {
+        backends: backend.into(),
+        ..Default::default()
+    });
+    let options = wgc::instance::RequestAdapterOptions {
+        power_preference: wgt::PowerPreference::None,
+        force_fallback_adapter: false,
+        compatible_surface: None,
+    };
+    let adapter = checked(global.request_adapter(&options, wgc::instance::AdapterInputs::IdSet(&[AdapterId::zip(0, 0, backend)], |id| id.backend())))
+        .with_context(|| format!("No {:?} adapter to replay the trace on", backend))?;
+    let info = checked(wgc::gfx_select!(adapter => global.adapter_get_info(adapter)))?;
+    let (device, error) = wgc::gfx_select!(adapter => global.adapter_request_device(adapter, &desc, None, DeviceId::zip(0, 0, backend)));
+    check(error).context("Failed to create the device")?;
+    let mut player = Player {
+        global,
+        directory,
+        encoders: IdentityManager::default(),
+    };
+    let mut count = 1;
+    for action in actions {
+        count += 1;
+        wgc::gfx_select!(device => player.play(device, action)).with_context(|| format!("Action {} of the trace failed", count))?;
+    }
+    wgc::gfx_select!(device => player.wait(device))?;
+    Ok(ReplayInfo { actions: count, adapter: info.name })
+}
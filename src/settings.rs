@@ -0,0 +1,57 @@
+use std::path::{Path, PathBuf};
+
+use crate::{history::DEFAULT_HISTORY_DEPTH, storage::Storage};
+
+const MAX_RECENT_PROJECTS: usize = 10;
+const SETTINGS_KEY: &str = "settings.json";
+
+// Application settings, shared between projects & kept in the app storage.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub recent_projects: Vec<PathBuf>, // Most recent first.
+    pub history_depth: usize, // Maximum number of undo steps.
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            recent_projects: Vec::new(),
+            history_depth: DEFAULT_HISTORY_DEPTH,
+        }
+    }
+}
+
+impl Settings {
+    // Settings are not worth bothering the user with, fallback to default ones if they can't be read.
+    pub fn load(storage: &dyn Storage) -> Self {
+        let result = storage.read(SETTINGS_KEY)
+            .and_then(|json| match json {
+                Some(json) => Ok(serde_json::from_str(json.as_str())?),
+                None => Ok(Self::default()),
+            });
+        match result {
+            Ok(settings) => settings,
+            Err(err) => {
+                log::warn!("Failed to load settings : {:#}", err);
+                Self::default()
+            }
+        }
+    }
+    pub fn save(&self, storage: &dyn Storage) -> anyhow::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        storage.write(SETTINGS_KEY, json.as_str())
+    }
+    // Projects are stored in the browser on web, there are no files to reopen.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn add_recent_project(&mut self, path: &Path) {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        self.recent_projects.retain(|recent| *recent != path);
+        self.recent_projects.insert(0, path);
+        self.recent_projects.truncate(MAX_RECENT_PROJECTS);
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn remove_recent_project(&mut self, path: &Path) {
+        self.recent_projects.retain(|recent| recent != path);
+    }
+}
@@ -0,0 +1,66 @@
+use anyhow::Context;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
+// Projects are shared as links to the web deployment, with the compressed project in the URL fragment.
+// The fragment is never sent to the server, so the project only lives in the link.
+#[cfg(not(target_arch = "wasm32"))]
+pub const SHARE_URL: &str = "https://antaalt.github.io/protos-rs/";
+
+// Decompressed projects larger than this are rejected, a link should not be able to exhaust memory.
+const MAX_PROJECT_SIZE: usize = 64 * 1024 * 1024;
+
+pub fn encode(json: &str) -> String {
+    let compressed = miniz_oxide::deflate::compress_to_vec(json.as_bytes(), 9);
+    URL_SAFE_NO_PAD.encode(compressed)
+}
+
+pub fn decode(encoded: &str) -> anyhow::Result<String> {
+    let compressed = URL_SAFE_NO_PAD.decode(encoded.trim()).context("Link is not valid base64")?;
+    let bytes = miniz_oxide::inflate::decompress_to_vec_with_limit(compressed.as_slice(), MAX_PROJECT_SIZE)
+        .map_err(|err| anyhow::anyhow!("Failed to decompress link : {:?}", err.status))?;
+    String::from_utf8(bytes).context("Link does not hold a project")
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn link(json: &str) -> String {
+    format!("{}#{}", SHARE_URL, encode(json))
+}
+
+// Accepts a whole link or only its fragment.
+pub fn parse_link(link: &str) -> anyhow::Result<String> {
+    let encoded = match link.split_once('#') {
+        Some((_, fragment)) => fragment,
+        None => link,
+    };
+    decode(encoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROJECT: &str = r#"{"version":4,"state":{"nodes":[]}}"#;
+
+    #[test]
+    fn round_trip() {
+        let encoded = encode(PROJECT);
+        assert!(encoded.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'), "{} is not url safe", encoded);
+        assert_eq!(decode(encoded.as_str()).unwrap(), PROJECT);
+    }
+
+    #[test]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn parse_whole_link_or_fragment() {
+        let link = link(PROJECT);
+        assert!(link.starts_with(SHARE_URL));
+        assert_eq!(parse_link(link.as_str()).unwrap(), PROJECT);
+        let (_, fragment) = link.split_once('#').unwrap();
+        assert_eq!(parse_link(fragment).unwrap(), PROJECT);
+    }
+
+    #[test]
+    fn reject_invalid_link() {
+        assert!(parse_link("https://antaalt.github.io/protos-rs/#not base64!").is_err());
+        assert!(decode(URL_SAFE_NO_PAD.encode(b"not compressed").as_str()).is_err());
+    }
+}
@@ -0,0 +1,79 @@
+// Small key value store for settings & projects which do not live in a user chosen file.
+pub trait Storage {
+    fn read(&self, key: &str) -> anyhow::Result<Option<String>>;
+    fn write(&self, key: &str, value: &str) -> anyhow::Result<()>;
+}
+
+// Every key is a file in the given directory.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct FileStorage {
+    directory: std::path::PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FileStorage {
+    pub fn new(directory: std::path::PathBuf) -> Self {
+        Self { directory }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Storage for FileStorage {
+    fn read(&self, key: &str) -> anyhow::Result<Option<String>> {
+        use anyhow::Context;
+        let path = self.directory.join(key);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let value = std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        Ok(Some(value))
+    }
+    fn write(&self, key: &str, value: &str) -> anyhow::Result<()> {
+        use anyhow::Context;
+        std::fs::create_dir_all(&self.directory).with_context(|| format!("Failed to create {}", self.directory.display()))?;
+        let path = self.directory.join(key);
+        std::fs::write(&path, value).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+// Browser local storage, keys are prefixed to avoid collisions with other apps on the same origin.
+#[cfg(target_arch = "wasm32")]
+pub struct LocalStorage {
+    storage: web_sys::Storage,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl LocalStorage {
+    const PREFIX: &'static str = "protos-rs/";
+    pub fn new() -> anyhow::Result<Self> {
+        let window = web_sys::window().ok_or(anyhow::anyhow!("No window available"))?;
+        let storage = window.local_storage()
+            .map_err(|err| anyhow::anyhow!("Local storage is not accessible : {:?}", err))?
+            .ok_or(anyhow::anyhow!("Local storage is not available"))?;
+        Ok(Self { storage })
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Storage for LocalStorage {
+    fn read(&self, key: &str) -> anyhow::Result<Option<String>> {
+        self.storage.get_item(format!("{}{}", Self::PREFIX, key).as_str())
+            .map_err(|err| anyhow::anyhow!("Failed to read {} : {:?}", key, err))
+    }
+    fn write(&self, key: &str, value: &str) -> anyhow::Result<()> {
+        // Fails if the quota of the origin is exceeded.
+        self.storage.set_item(format!("{}{}", Self::PREFIX, key).as_str(), value)
+            .map_err(|err| anyhow::anyhow!("Failed to write {} : {:?}", key, err))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn default_storage() -> anyhow::Result<Box<dyn Storage>> {
+    let directory = dirs::config_dir().ok_or(anyhow::anyhow!("No config directory available"))?;
+    Ok(Box::new(FileStorage::new(directory.join("protos-rs"))))
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn default_storage() -> anyhow::Result<Box<dyn Storage>> {
+    Ok(Box::new(LocalStorage::new()?))
+}
@@ -0,0 +1,48 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use anyhow::Context;
+
+use crate::{cli, graph::*, project::{Project, ProjectMetadata}};
+
+// Written next to the wgpu trace, so that protos can render the capture again.
+const PROJECT_FILE: &str = "project.protos";
+const CAPTURE_FILE: &str = "capture.json";
+
+// How the frames of a capture were rendered.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CaptureInfo {
+    pub width: u32,
+    pub height: u32,
+    pub time: f32, // Of the last frame.
+    pub frames: u32,
+    pub adapter: String, // Replaying on another adapter might not reproduce a driver issue.
+}
+
+// Render frames of a project on a dedicated device recording a wgpu trace into directory, with the project & render settings.
+// The device displaying the app can't be traced once created, and its commands would mostly be the UI.
+pub fn capture(directory: &Path, state: &ProtosEditorState, user_state: &ProtosGraphState, size: (u32, u32), time: f32, frames: u32) -> anyhow::Result<CaptureInfo> {
+    // Round trip through the project format, so that resources are created on the traced device.
+    let json = Project::to_json(&ProjectMetadata::default(), state, user_state)?;
+    let project = Project::from_json(json.as_str())?;
+    let (adapter, device, queue) = cli::create_traced_device(false, Some(directory))?;
+    let info = CaptureInfo {
+        width: size.0,
+        height: size.1,
+        time,
+        frames,
+        adapter: adapter.get_info().name,
+    };
+    fs::write(directory.join(PROJECT_FILE), json).with_context(|| format!("Failed to write {}", PROJECT_FILE))?;
+    fs::write(directory.join(CAPTURE_FILE), serde_json::to_string_pretty(&info)?).with_context(|| format!("Failed to write {}", CAPTURE_FILE))?;
+    cli::render_frames(&device, &queue, &project.state, &project.user_state, size, time, frames)?;
+    device.poll(wgpu::Maintain::Wait);
+    Ok(info)
+}
+
+// Project & render settings of a capture.
+pub fn load_capture(directory: &Path) -> anyhow::Result<(PathBuf, CaptureInfo)> {
+    let path = directory.join(CAPTURE_FILE);
+    let json = fs::read_to_string(&path).with_context(|| format!("Failed to read {}, {} is not a capture", path.display(), directory.display()))?;
+    let info = serde_json::from_str(json.as_str()).with_context(|| format!("Failed to parse {}", path.display()))?;
+    Ok((directory.join(PROJECT_FILE), info))
+}
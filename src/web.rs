@@ -0,0 +1,85 @@
+use std::cell::Cell;
+
+use wasm_bindgen::{prelude::*, JsCast};
+
+thread_local! {
+    // Set when an upload modified the project outside of the UI.
+    static FILE_LOADED: Cell<bool> = Cell::new(false);
+}
+
+fn js_error(err: JsValue) -> anyhow::Error {
+    anyhow::anyhow!("{:?}", err)
+}
+
+fn document() -> anyhow::Result<web_sys::Document> {
+    web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or(anyhow::anyhow!("No document available"))
+}
+
+// Let the browser save the bytes as a file.
+#[cfg(feature = "persistence")]
+pub fn download(file_name: &str, bytes: &[u8], mime: &str) -> anyhow::Result<()> {
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(bytes));
+    let options = web_sys::BlobPropertyBag::new();
+    options.set_type(mime);
+    let blob = web_sys::Blob::new_with_u8_array_sequence_and_options(&parts, &options).map_err(js_error)?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob).map_err(js_error)?;
+    let anchor = document()?
+        .create_element("a").map_err(js_error)?
+        .dyn_into::<web_sys::HtmlAnchorElement>().map_err(|_| anyhow::anyhow!("Not an anchor"))?;
+    anchor.set_href(url.as_str());
+    anchor.set_download(file_name);
+    anchor.click();
+    web_sys::Url::revoke_object_url(url.as_str()).map_err(js_error)?;
+    Ok(())
+}
+
+// Ask the user for a file, on_loaded is called with its name & content once read, which might never happen if cancelled.
+pub fn upload(accept: &str, on_loaded: impl FnOnce(anyhow::Result<(String, Vec<u8>)>) + 'static) -> anyhow::Result<()> {
+    let input = document()?
+        .create_element("input").map_err(js_error)?
+        .dyn_into::<web_sys::HtmlInputElement>().map_err(|_| anyhow::anyhow!("Not an input"))?;
+    input.set_type("file");
+    input.set_accept(accept);
+    let input_changed = input.clone();
+    let on_change = Closure::once_into_js(move |_event: web_sys::Event| {
+        let file = match input_changed.files().and_then(|files| files.get(0)) {
+            Some(file) => file,
+            None => return,
+        };
+        wasm_bindgen_futures::spawn_local(async move {
+            let result = wasm_bindgen_futures::JsFuture::from(file.array_buffer()).await
+                .map(|buffer| (file.name(), js_sys::Uint8Array::new(&buffer).to_vec()))
+                .map_err(js_error);
+            on_loaded(result);
+        });
+    });
+    input.set_onchange(Some(on_change.unchecked_ref()));
+    input.click();
+    Ok(())
+}
+
+pub fn notify_file_loaded() {
+    FILE_LOADED.with(|loaded| loaded.set(true));
+}
+
+// Returns true once after a file was loaded.
+pub fn take_file_loaded() -> bool {
+    FILE_LOADED.with(|loaded| loaded.replace(false))
+}
+
+// Fragment of the page address, without the leading '#'.
+#[cfg(feature = "persistence")]
+pub fn location_fragment() -> Option<String> {
+    let hash = web_sys::window()?.location().hash().ok()?;
+    let fragment = hash.trim_start_matches('#');
+    (!fragment.is_empty()).then(|| fragment.to_string())
+}
+
+// Replaces the fragment of the page address, which does not reload the page.
+#[cfg(feature = "persistence")]
+pub fn set_location_fragment(fragment: &str) -> anyhow::Result<()> {
+    let window = web_sys::window().ok_or(anyhow::anyhow!("No window available"))?;
+    window.location().set_hash(fragment).map_err(js_error)
+}
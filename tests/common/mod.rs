@@ -0,0 +1,35 @@
+// Helpers shared by the test suites rendering on a GPU.
+// Items unused by some suites are still compiled in each of them.
+#![allow(dead_code)]
+
+use protos_rs::cli;
+
+// Draw a mesh as is, passing texture coordinates to the fragment shader.
+pub const VERTEX_SHADER: &str = r#"struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+};
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+@vertex
+fn vs_main(model: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(model.position.xy, 0.0, 1.0);
+    out.tex_coords = model.tex_coords;
+    return out;
+}
+"#;
+
+// Prefer a software adapter for reproducible results, but use whatever is there otherwise.
+// Tests fail without adapter rather than passing without running, a software one such as lavapipe or WARP is enough.
+pub fn create_device() -> (wgpu::Device, wgpu::Queue) {
+    match cli::create_device(true).or_else(|_| cli::create_device(false)) {
+        Ok((adapter, device, queue)) => {
+            println!("Rendering on {:?}", adapter.get_info());
+            (device, queue)
+        }
+        Err(err) => panic!("No adapter available, GPU tests need one : {:#}", err),
+    }
+}
@@ -0,0 +1,85 @@
+// Evaluation errors must point to the node & port they come from.
+mod common;
+
+use egui_node_graph::NodeId;
+use protos_rs::{
+    cli,
+    gfx::{MeshShape, ShaderLanguage},
+    graph::{nodes::{BackbufferPassNode, GraphicPassNode, MeshNode, ShaderNode}, FrameInfo, GraphBuilder, ProtosError, ProtosErrorKind, ProtosNodeTemplate},
+};
+
+const FRAGMENT_SHADER: &str = r#"@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0);
+}
+"#;
+
+const INVALID_FRAGMENT_SHADER: &str = r#"@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return undefined_value;
+}
+"#;
+
+fn render(builder: GraphBuilder) -> ProtosError {
+    let (device, queue) = common::create_device();
+    let (state, user_state) = builder.build();
+    let err = cli::render_frame(&device, &queue, &state, &user_state, 16, 16, &FrameInfo::default()).expect_err("Render should fail");
+    err.downcast_ref::<ProtosError>().expect("Error should be a protos error").clone()
+}
+
+// Nodes of a single pass project that errors point to.
+struct SinglePass {
+    fragment: NodeId,
+    pass: NodeId,
+}
+
+// Single pass drawing to the backbuffer, its mesh node connected to the given inputs of the pass.
+fn single_pass_project(fragment_shader: &str, mesh_inputs: &[&str]) -> (GraphBuilder, SinglePass) {
+    let mut builder = GraphBuilder::new();
+    let vertex = builder.add_node(ProtosNodeTemplate::Shader(ShaderNode::new(ShaderLanguage::Wgsl, common::VERTEX_SHADER.to_string())), 0, 0);
+    let fragment = builder.add_node(ProtosNodeTemplate::Shader(ShaderNode::new(ShaderLanguage::Wgsl, fragment_shader.to_string())), 0, 1);
+    let mesh = builder.add_node(ProtosNodeTemplate::Mesh(MeshNode::from_shape(MeshShape::fullscreen_quad())), 0, 2);
+    let pass = builder.add_node(ProtosNodeTemplate::GraphicPass(GraphicPassNode::default()), 1, 1);
+    let backbuffer = builder.add_node(ProtosNodeTemplate::BackbufferPass(BackbufferPassNode::default()), 2, 1);
+    builder.connect(vertex, "VertexShader", pass, "VertexShader").unwrap();
+    builder.connect(fragment, "FragmentShader", pass, "FragmentShader").unwrap();
+    for input in mesh_inputs {
+        builder.connect(mesh, "Geometry", pass, input).unwrap();
+    }
+    builder.connect(pass, "RT0", backbuffer, "input").unwrap();
+    builder.set_backbuffer(backbuffer);
+    (builder, SinglePass { fragment, pass })
+}
+
+#[test]
+fn missing_geometry() {
+    let (builder, nodes) = single_pass_project(FRAGMENT_SHADER, &[]);
+    let error = render(builder);
+    assert_eq!(error.kind, ProtosErrorKind::MissingInput);
+    assert_eq!(error.node, Some(nodes.pass));
+    assert_eq!(error.port.as_deref(), Some("Geometry"));
+}
+
+#[test]
+fn shader_compilation() {
+    let (builder, nodes) = single_pass_project(INVALID_FRAGMENT_SHADER, &["Geometry"]);
+    let error = render(builder);
+    assert_eq!(error.node, Some(nodes.fragment));
+    match error.kind {
+        ProtosErrorKind::ShaderCompilation { spans, .. } => {
+            let span = spans.first().expect("Error should point to the source").clone();
+            assert_eq!(&INVALID_FRAGMENT_SHADER[span], "undefined_value");
+        }
+        kind => panic!("Unexpected error {}", kind),
+    }
+}
+
+#[test]
+fn type_mismatch() {
+    // The editor refuses the connection to SRV0, but a project file can hold it.
+    let (builder, nodes) = single_pass_project(FRAGMENT_SHADER, &["Geometry", "SRV0"]);
+    let error = render(builder);
+    assert_eq!(error.kind, ProtosErrorKind::TypeMismatch { expected: String::from("texture"), found: String::from("mesh") });
+    assert_eq!(error.node, Some(nodes.pass));
+    assert_eq!(error.port.as_deref(), Some("SRV0"));
+}
@@ -0,0 +1,235 @@
+// Golden image tests: render fixture projects headlessly & compare them against references in tests/golden.
+// Set PROTOS_UPDATE_GOLDEN=1 to overwrite references with the current output after checking it is correct.
+// On failure, actual, expected & diff images are written to target/golden.
+// Project files in tests/fixtures are saved projects, older versions are kept to check their migrations.
+mod common;
+
+use std::path::PathBuf;
+
+use protos_rs::{
+    cli,
+    gfx::{BackbufferMode, MeshShape, RenderTargetSize, ShaderLanguage},
+    graph::{import_shadertoy, nodes::{BackbufferPassNode, GraphicPassNode, MeshNode, ShaderNode, TextureFileNode}, FrameInfo, GraphBuilder, ProtosEditorState, ProtosGraphState, ProtosNodeTemplate, ProtosValueType},
+};
+
+const WIDTH: u32 = 64;
+const HEIGHT: u32 = 64;
+// Maximum difference allowed per channel, to account for rounding differences between adapters.
+const TOLERANCE: u8 = 2;
+
+// Also written in the fixture projects.
+const SOLID_FRAGMENT_SHADER: &str = r#"@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0, 0.5, 0.0, 1.0);
+}
+"#;
+
+const TEXTURE_FRAGMENT_SHADER: &str = r#"@group(0) @binding(0) var source: texture_2d<f32>;
+@fragment
+fn fs_main(@location(0) tex_coords: vec2<f32>) -> @location(0) vec4<f32> {
+    // Load texels without filtering so that the result does not depend on the sampler.
+    let size = vec2<f32>(textureDimensions(source));
+    return textureLoad(source, vec2<i32>(tex_coords * size), 0);
+}
+"#;
+
+const SHADERTOY_GRADIENT: &str = r#"void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    fragColor = vec4(fragCoord / iResolution.xy, iTime * 0.25, 1.0);
+}
+"#;
+
+fn fixture_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures").join(name)
+}
+
+// Single pass drawing the mesh with the given fragment shader to the backbuffer.
+fn single_pass_project(shape: MeshShape, fragment_shader: &str, texture: Option<PathBuf>) -> anyhow::Result<(ProtosEditorState, ProtosGraphState)> {
+    let mut builder = GraphBuilder::new();
+    let vertex = builder.add_node(ProtosNodeTemplate::Shader(ShaderNode::new(ShaderLanguage::Wgsl, common::VERTEX_SHADER.to_string())), 0, 0);
+    let fragment = builder.add_node(ProtosNodeTemplate::Shader(ShaderNode::new(ShaderLanguage::Wgsl, fragment_shader.to_string())), 0, 1);
+    let mesh = builder.add_node(ProtosNodeTemplate::Mesh(MeshNode::from_shape(shape)), 0, 2);
+    let pass = builder.add_node(ProtosNodeTemplate::GraphicPass(GraphicPassNode::default()), 1, 1);
+    let backbuffer = builder.add_node(ProtosNodeTemplate::BackbufferPass(BackbufferPassNode::default()), 2, 1);
+    builder.connect(vertex, "VertexShader", pass, "VertexShader")?;
+    builder.connect(fragment, "FragmentShader", pass, "FragmentShader")?;
+    builder.connect(mesh, "Geometry", pass, "Geometry")?;
+    if let Some(path) = texture {
+        let texture = builder.add_node(ProtosNodeTemplate::FileTexture(TextureFileNode::default()), 0, 3);
+        builder.set_value(texture, "Path", ProtosValueType::String(path.to_string_lossy().to_string()))?;
+        builder.connect(texture, "texture", pass, "SRV0")?;
+    }
+    builder.connect(pass, "RT0", backbuffer, "input")?;
+    builder.set_backbuffer(backbuffer);
+    Ok(builder.build())
+}
+
+// Go through the project file format, as if the fixture was loaded from disk.
+#[cfg(feature = "persistence")]
+fn reload(project: (ProtosEditorState, ProtosGraphState)) -> anyhow::Result<(ProtosEditorState, ProtosGraphState)> {
+    use protos_rs::project::{Project, ProjectMetadata};
+    let json = Project::to_json(&ProjectMetadata::default(), &project.0, &project.1)?;
+    let project = Project::from_json(json.as_str())?;
+    Ok((project.state, project.user_state))
+}
+
+// Saved project from tests/fixtures.
+#[cfg(feature = "persistence")]
+fn load_fixture(name: &str) -> (ProtosEditorState, ProtosGraphState) {
+    let project = protos_rs::project::Project::load(&fixture_path(name)).expect("Failed to load fixture");
+    (project.state, project.user_state)
+}
+
+#[cfg(not(feature = "persistence"))]
+fn reload(project: (ProtosEditorState, ProtosGraphState)) -> anyhow::Result<(ProtosEditorState, ProtosGraphState)> {
+    Ok(project)
+}
+
+fn compare(name: &str, actual: &image::RgbaImage) -> anyhow::Result<()> {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let reference_path = root.join("tests").join("golden").join(format!("{}.png", name));
+    if std::env::var("PROTOS_UPDATE_GOLDEN").is_ok() {
+        actual.save(&reference_path)?;
+        return Ok(());
+    }
+    let expected = image::open(&reference_path)?.to_rgba8();
+    if expected.dimensions() != actual.dimensions() {
+        anyhow::bail!("{} : size {:?} does not match reference {:?}", name, actual.dimensions(), expected.dimensions());
+    }
+    let mut failed_pixels = 0;
+    let diff = image::RgbaImage::from_fn(actual.width(), actual.height(), |x, y| {
+        let a = actual.get_pixel(x, y).0;
+        let e = expected.get_pixel(x, y).0;
+        let delta = (0..4).map(|c| a[c].abs_diff(e[c])).max().unwrap_or(0);
+        if delta > TOLERANCE {
+            failed_pixels += 1;
+            image::Rgba([255, 0, 0, 255])
+        } else {
+            // Keep a faded version of the image to locate the errors.
+            image::Rgba([e[0] / 4, e[1] / 4, e[2] / 4, 255])
+        }
+    });
+    if failed_pixels > 0 {
+        let output = root.join("target").join("golden").join(name);
+        std::fs::create_dir_all(&output)?;
+        actual.save(output.join("actual.png"))?;
+        expected.save(output.join("expected.png"))?;
+        diff.save(output.join("diff.png"))?;
+        anyhow::bail!("{} : {} pixels differ from reference, see {}", name, failed_pixels, output.display());
+    }
+    Ok(())
+}
+
+// Rendered at the test size, which the backbuffer resolves its resolution from.
+fn render(project: &(ProtosEditorState, ProtosGraphState), frame: FrameInfo) -> image::RgbaImage {
+    let (device, queue) = common::create_device();
+    let (state, user_state) = project;
+    cli::render_frame(&device, &queue, state, user_state, WIDTH, HEIGHT, &frame).expect("Failed to render project");
+    cli::read_backbuffer(&device, &queue, state, user_state).expect("Failed to read backbuffer")
+}
+
+fn check_golden(name: &str, project: (ProtosEditorState, ProtosGraphState), frame: FrameInfo) {
+    let actual = render(&project, frame);
+    if let Err(err) = compare(name, &actual) {
+        panic!("{}", err);
+    }
+}
+
+#[test]
+#[cfg(feature = "persistence")]
+fn solid_fullscreen_quad() {
+    check_golden("solid_fullscreen_quad", load_fixture("solid_fullscreen_quad.protos"), FrameInfo::default());
+}
+
+#[test]
+#[cfg(feature = "persistence")]
+fn legacy_v1_project() {
+    // Same graph saved before resources were shared, migrated on load.
+    check_golden("solid_fullscreen_quad", load_fixture("legacy_v1.protos"), FrameInfo::default());
+}
+
+#[test]
+#[cfg(feature = "persistence")]
+fn file_texture() {
+    // The texture path is relative to the crate, where tests run.
+    check_golden("file_texture", load_fixture("file_texture.protos"), FrameInfo::default());
+}
+
+#[test]
+fn quad_shape() {
+    let project = single_pass_project(MeshShape::default_quad(), SOLID_FRAGMENT_SHADER, None).unwrap();
+    check_golden("quad_shape", reload(project).expect("Failed to reload project"), FrameInfo::default());
+}
+
+#[test]
+fn file_texture_from_code() {
+    let project = single_pass_project(MeshShape::fullscreen_quad(), TEXTURE_FRAGMENT_SHADER, Some(fixture_path("checker.png"))).unwrap();
+    check_golden("file_texture", reload(project).expect("Failed to reload project"), FrameInfo::default());
+}
+
+#[test]
+fn shadertoy_gradient() {
+    let project = import_shadertoy(SHADERTOY_GRADIENT).unwrap();
+    let frame = FrameInfo {
+        time: 1.0,
+        delta_time: 1.0 / 60.0,
+        index: 60,
+    };
+    check_golden("shadertoy_gradient", reload(project).expect("Failed to reload project"), frame);
+}
+
+fn set_backbuffer_mode(project: &(ProtosEditorState, ProtosGraphState), mode: BackbufferMode) {
+    let node_id = project.1.backbuffer_node.unwrap();
+    if let ProtosNodeTemplate::BackbufferPass(node) = &project.0.graph.nodes[node_id].user_data.template {
+        node.handle.lock().unwrap().visit_desc_mut(|desc| {
+            desc.mode = mode;
+            true
+        });
+    }
+}
+
+fn set_render_target_size(project: &(ProtosEditorState, ProtosGraphState), size: RenderTargetSize) {
+    for (_, node) in project.0.graph.nodes.iter() {
+        if let ProtosNodeTemplate::GraphicPass(pass) = &node.user_data.template {
+            pass.handle.lock().unwrap().visit_desc_mut(|desc| {
+                desc.render_target_size = size.clone();
+                true
+            });
+        }
+    }
+}
+
+#[test]
+fn fixed_backbuffer_resolution() {
+    let project = single_pass_project(MeshShape::fullscreen_quad(), SOLID_FRAGMENT_SHADER, None).unwrap();
+    set_backbuffer_mode(&project, BackbufferMode::Fixed { width: 32, height: 16 });
+    let actual = render(&project, FrameInfo::default());
+    assert_eq!(actual.dimensions(), (32, 16));
+    let color = actual.get_pixel(0, 0);
+    assert!(actual.pixels().all(|pixel| pixel == color));
+}
+
+#[test]
+fn aspect_backbuffer_resolution() {
+    let project = single_pass_project(MeshShape::fullscreen_quad(), SOLID_FRAGMENT_SHADER, None).unwrap();
+    set_backbuffer_mode(&project, BackbufferMode::Aspect { width: 2, height: 1 });
+    assert_eq!(render(&project, FrameInfo::default()).dimensions(), (WIDTH, HEIGHT / 2));
+}
+
+#[test]
+fn scaled_passes_are_resampled() {
+    // Passes at half & twice the backbuffer resolution, or sized by expressions, still cover all of it.
+    let expression = |width: &str, height: &str| RenderTargetSize::Expression { width: width.to_string(), height: height.to_string() };
+    let sizes = [
+        RenderTargetSize::Relative { percent: 50 },
+        RenderTargetSize::Relative { percent: 200 },
+        expression("bb.w / 2", "min(bb.h, 16)"),
+        expression("max(bb.w * 2, 100)", "(bb.h + 1) * 3"),
+    ];
+    for size in sizes {
+        let project = single_pass_project(MeshShape::fullscreen_quad(), SOLID_FRAGMENT_SHADER, None).unwrap();
+        set_render_target_size(&project, size.clone());
+        if let Err(err) = compare("solid_fullscreen_quad", &render(&project, FrameInfo::default())) {
+            panic!("{:?} : {}", size, err);
+        }
+    }
+}
@@ -0,0 +1,158 @@
+// Recording must run each pass once per frame, whatever the number of inputs reading it,
+// and feedbacks must hold previous frame, whatever the order passes are reached in.
+mod common;
+
+use egui_node_graph::NodeId;
+use protos_rs::{
+    cli,
+    gfx::{MeshShape, ShaderLanguage},
+    graph::{nodes::{BackbufferPassNode, FeedbackNode, GraphicPassNode, MeshNode, ShaderNode}, GraphBuilder, ProtosNodeTemplate},
+};
+
+const SIZE: u32 = 16;
+const FRAMES: u32 = 3;
+const STEP: f32 = 0.1;
+
+// Add STEP to the red channel of previous frame.
+const ACCUMULATE_FRAGMENT_SHADER: &str = r#"@group(0) @binding(0) var previous: texture_2d<f32>;
+@fragment
+fn fs_main(@location(0) tex_coords: vec2<f32>) -> @location(0) vec4<f32> {
+    let size = vec2<f32>(textureDimensions(previous));
+    let value = textureLoad(previous, vec2<i32>(tex_coords * size), 0);
+    return vec4<f32>(value.r + 0.1, 0.0, 0.0, 1.0);
+}
+"#;
+
+// Average of the same texture bound twice.
+const AVERAGE_FRAGMENT_SHADER: &str = r#"@group(0) @binding(0) var first: texture_2d<f32>;
+@group(0) @binding(1) var second: texture_2d<f32>;
+@fragment
+fn fs_main(@location(0) tex_coords: vec2<f32>) -> @location(0) vec4<f32> {
+    let size = vec2<f32>(textureDimensions(first));
+    let coords = vec2<i32>(tex_coords * size);
+    return (textureLoad(first, coords, 0) + textureLoad(second, coords, 0)) * 0.5;
+}
+"#;
+
+// Output the texture bound second, the first one is only read to be recorded before it.
+const SECOND_FRAGMENT_SHADER: &str = r#"@group(0) @binding(0) var first: texture_2d<f32>;
+@group(0) @binding(1) var second: texture_2d<f32>;
+@fragment
+fn fs_main(@location(0) tex_coords: vec2<f32>) -> @location(0) vec4<f32> {
+    let size = vec2<f32>(textureDimensions(second));
+    let coords = vec2<i32>(tex_coords * size);
+    return textureLoad(second, coords, 0) + textureLoad(first, coords, 0) * 0.0;
+}
+"#;
+
+// Output the texture bound first.
+const COPY_FRAGMENT_SHADER: &str = r#"@group(0) @binding(0) var source: texture_2d<f32>;
+@fragment
+fn fs_main(@location(0) tex_coords: vec2<f32>) -> @location(0) vec4<f32> {
+    let size = vec2<f32>(textureDimensions(source));
+    return textureLoad(source, vec2<i32>(tex_coords * size), 0);
+}
+"#;
+
+fn srgb_to_linear(value: u8) -> f32 {
+    let value = value as f32 / 255.0;
+    if value <= 0.04045 { value / 12.92 } else { ((value + 0.055) / 1.055).powf(2.4) }
+}
+
+#[test]
+fn pass_read_by_two_inputs() {
+    let mut builder = GraphBuilder::new();
+    let vertex = builder.add_node(ProtosNodeTemplate::Shader(ShaderNode::new(ShaderLanguage::Wgsl, common::VERTEX_SHADER.to_string())), 0, 0);
+    let accumulate = builder.add_node(ProtosNodeTemplate::Shader(ShaderNode::new(ShaderLanguage::Wgsl, ACCUMULATE_FRAGMENT_SHADER.to_string())), 0, 1);
+    let average = builder.add_node(ProtosNodeTemplate::Shader(ShaderNode::new(ShaderLanguage::Wgsl, AVERAGE_FRAGMENT_SHADER.to_string())), 0, 2);
+    let mesh = builder.add_node(ProtosNodeTemplate::Mesh(MeshNode::from_shape(MeshShape::fullscreen_quad())), 0, 3);
+    let feedback = builder.add_node(ProtosNodeTemplate::Feedback(FeedbackNode::default()), 1, 0);
+    let shared = builder.add_node(ProtosNodeTemplate::GraphicPass(GraphicPassNode::default()), 1, 1);
+    let reader = builder.add_node(ProtosNodeTemplate::GraphicPass(GraphicPassNode::default()), 2, 1);
+    let backbuffer = builder.add_node(ProtosNodeTemplate::BackbufferPass(BackbufferPassNode::default()), 3, 1);
+    for pass in [shared, reader] {
+        builder.connect(vertex, "VertexShader", pass, "VertexShader").unwrap();
+        builder.connect(mesh, "Geometry", pass, "Geometry").unwrap();
+    }
+    builder.connect(accumulate, "FragmentShader", shared, "FragmentShader").unwrap();
+    builder.connect(average, "FragmentShader", reader, "FragmentShader").unwrap();
+    builder.connect(shared, "RT0", feedback, "Input").unwrap();
+    builder.connect(feedback, "Previous", shared, "SRV0").unwrap();
+    builder.connect(shared, "RT0", reader, "SRV0").unwrap();
+    builder.connect(shared, "RT0", reader, "SRV1").unwrap();
+    builder.connect(reader, "RT0", backbuffer, "input").unwrap();
+    builder.set_backbuffer(backbuffer);
+    let (state, user_state) = builder.build();
+
+    let (device, queue) = common::create_device();
+    cli::render_frames(&device, &queue, &state, &user_state, (SIZE, SIZE), 1.0, FRAMES).expect("Failed to render project");
+    let image = cli::read_backbuffer(&device, &queue, &state, &user_state).expect("Failed to read backbuffer");
+    // Recorded twice, the feedback would advance two steps per frame.
+    let value = srgb_to_linear(image.get_pixel(SIZE / 2, SIZE / 2).0[0]);
+    let expected = FRAMES as f32 * STEP;
+    assert!((value - expected).abs() < 0.02, "Feedback advanced to {} after {} frames, expected {}", value, FRAMES, expected);
+}
+
+// Passes drawing a fullscreen quad with the given fragment shaders, sharing the vertex shader & mesh.
+fn add_passes(builder: &mut GraphBuilder, fragment_shaders: &[&str]) -> Vec<NodeId> {
+    let vertex = builder.add_node(ProtosNodeTemplate::Shader(ShaderNode::new(ShaderLanguage::Wgsl, common::VERTEX_SHADER.to_string())), 0, 0);
+    let mesh = builder.add_node(ProtosNodeTemplate::Mesh(MeshNode::from_shape(MeshShape::fullscreen_quad())), 0, 1);
+    fragment_shaders.iter().enumerate().map(|(index, code)| {
+        let fragment = builder.add_node(ProtosNodeTemplate::Shader(ShaderNode::new(ShaderLanguage::Wgsl, code.to_string())), 1, index);
+        let pass = builder.add_node(ProtosNodeTemplate::GraphicPass(GraphicPassNode::default()), 2, index);
+        builder.connect(vertex, "VertexShader", pass, "VertexShader").unwrap();
+        builder.connect(mesh, "Geometry", pass, "Geometry").unwrap();
+        builder.connect(fragment, "FragmentShader", pass, "FragmentShader").unwrap();
+        pass
+    }).collect()
+}
+
+// Red channel at the center of the backbuffer after rendering FRAMES frames.
+fn render_red(builder: GraphBuilder) -> f32 {
+    let (state, user_state) = builder.build();
+    let (device, queue) = common::create_device();
+    cli::render_frames(&device, &queue, &state, &user_state, (SIZE, SIZE), 1.0, FRAMES).expect("Failed to render project");
+    let image = cli::read_backbuffer(&device, &queue, &state, &user_state).expect("Failed to read backbuffer");
+    srgb_to_linear(image.get_pixel(SIZE / 2, SIZE / 2).0[0])
+}
+
+#[test]
+fn pass_read_only_through_feedback() {
+    // The accumulating pass is only reached through the feedback, it must still be rendered every frame.
+    let mut builder = GraphBuilder::new();
+    let passes = add_passes(&mut builder, &[ACCUMULATE_FRAGMENT_SHADER, COPY_FRAGMENT_SHADER]);
+    let (accumulate, reader) = (passes[0], passes[1]);
+    let own_feedback = builder.add_node(ProtosNodeTemplate::Feedback(FeedbackNode::default()), 3, 0);
+    let feedback = builder.add_node(ProtosNodeTemplate::Feedback(FeedbackNode::default()), 3, 1);
+    let backbuffer = builder.add_node(ProtosNodeTemplate::BackbufferPass(BackbufferPassNode::default()), 4, 1);
+    builder.connect(accumulate, "RT0", own_feedback, "Input").unwrap();
+    builder.connect(own_feedback, "Previous", accumulate, "SRV0").unwrap();
+    builder.connect(accumulate, "RT0", feedback, "Input").unwrap();
+    builder.connect(feedback, "Previous", reader, "SRV0").unwrap();
+    builder.connect(reader, "RT0", backbuffer, "input").unwrap();
+    builder.set_backbuffer(backbuffer);
+    let value = render_red(builder);
+    let expected = (FRAMES - 1) as f32 * STEP;
+    assert!((value - expected).abs() < 0.02, "Read {} through the feedback after {} frames, expected {}", value, FRAMES, expected);
+}
+
+#[test]
+fn feedback_read_after_its_input() {
+    // The reader reaches the accumulating pass before its feedback, which must still hold previous frame.
+    let mut builder = GraphBuilder::new();
+    let passes = add_passes(&mut builder, &[ACCUMULATE_FRAGMENT_SHADER, SECOND_FRAGMENT_SHADER]);
+    let (accumulate, reader) = (passes[0], passes[1]);
+    let own_feedback = builder.add_node(ProtosNodeTemplate::Feedback(FeedbackNode::default()), 3, 0);
+    let feedback = builder.add_node(ProtosNodeTemplate::Feedback(FeedbackNode::default()), 3, 1);
+    let backbuffer = builder.add_node(ProtosNodeTemplate::BackbufferPass(BackbufferPassNode::default()), 4, 1);
+    builder.connect(accumulate, "RT0", own_feedback, "Input").unwrap();
+    builder.connect(own_feedback, "Previous", accumulate, "SRV0").unwrap();
+    builder.connect(accumulate, "RT0", reader, "SRV0").unwrap();
+    builder.connect(accumulate, "RT0", feedback, "Input").unwrap();
+    builder.connect(feedback, "Previous", reader, "SRV1").unwrap();
+    builder.connect(reader, "RT0", backbuffer, "input").unwrap();
+    builder.set_backbuffer(backbuffer);
+    let value = render_red(builder);
+    let expected = (FRAMES - 1) as f32 * STEP;
+    assert!((value - expected).abs() < 0.02, "Feedback held {} after {} frames, expected previous frame {}", value, FRAMES, expected);
+}